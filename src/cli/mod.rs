@@ -0,0 +1,7 @@
+mod options;
+
+pub use self::options::Options;
+pub use self::options::Input;
+pub use self::options::ErrorFormat;
+pub use self::options::Emit;
+pub use self::options::OptLevel;
@@ -0,0 +1,212 @@
+use diagnostic::Message;
+use diagnostic::Lang;
+
+use optimize::Pass;
+
+pub enum Input {
+    Stdin,
+    File(String),
+}
+
+// 診断の出力形式。json は1行に1つの診断を書き出す
+#[derive(Debug, PartialEq)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+// 出力するもの。ir は中間表現をテキストで書き出す
+#[derive(Debug, PartialEq)]
+pub enum Emit {
+    Assembly,
+    Ir,
+}
+
+// 最適化の段階。-O は -O1 と同じ
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum OptLevel {
+    O0,
+    O1,
+    O2,
+}
+
+pub struct Options {
+    pub input: Input,
+    pub output: Option<String>,
+    pub error_format: ErrorFormat,
+    pub emit: Emit,
+    pub opt_level: OptLevel,
+    // 指定があれば、最適化の段階に関わらずこの順にかける
+    pub passes: Option<Vec<Pass>>,
+    pub print_after: Option<Pass>,
+    pub time_passes: bool,
+    // 指定がなければ環境変数LANGに従う
+    pub lang: Option<Lang>,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Options, Message> {
+        let mut input = None;
+        let mut output = None;
+        let mut error_format = ErrorFormat::Human;
+        let mut emit = Emit::Assembly;
+        let mut opt_level = OptLevel::O0;
+        let mut passes = None;
+        let mut print_after = None;
+        let mut time_passes = false;
+        let mut lang = None;
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            match arg.as_str() {
+                "-o" => match rest.next() {
+                    Some(path) => output = Some(path.clone()),
+                    None => return Err(Message::MissingOutputPath),
+                },
+                "-" => {
+                    if input.is_some() {
+                        return Err(Message::MultipleInputs);
+                    }
+                    input = Some(Input::Stdin)
+                },
+                "-O0" => opt_level = OptLevel::O0,
+                "-O" | "-O1" => opt_level = OptLevel::O1,
+                "-O2" => opt_level = OptLevel::O2,
+                "--time-passes" => time_passes = true,
+                flag if flag.starts_with("--passes=") => {
+                    let mut list = Vec::new();
+                    for name in flag["--passes=".len()..].split(',').filter(|name| !name.is_empty()) {
+                        match Pass::from_name(name) {
+                            Some(pass) => list.push(pass),
+                            None => return Err(Message::UnknownPass(name.to_string())),
+                        }
+                    }
+                    passes = Some(list);
+                },
+                flag if flag.starts_with("--print-after=") => {
+                    let name = &flag["--print-after=".len()..];
+                    match Pass::from_name(name) {
+                        Some(pass) => print_after = Some(pass),
+                        None => return Err(Message::UnknownPass(name.to_string())),
+                    }
+                },
+                "--error-format=human" => error_format = ErrorFormat::Human,
+                "--error-format=json" => error_format = ErrorFormat::Json,
+                "--emit=asm" => emit = Emit::Assembly,
+                "--emit=ir" => emit = Emit::Ir,
+                flag if flag.starts_with("--lang=") => {
+                    let name = &flag["--lang=".len()..];
+                    match Lang::from_name(name) {
+                        Some(l) => lang = Some(l),
+                        None => return Err(Message::UnknownLanguage(name.to_string())),
+                    }
+                },
+                flag if flag.starts_with('-') => {
+                    return Err(Message::UnknownOption(flag.to_string()))
+                },
+                path => {
+                    if input.is_some() {
+                        return Err(Message::MultipleInputs);
+                    }
+                    input = Some(Input::File(path.to_string()))
+                },
+            }
+        }
+        // 中間表現を書き出すときは、アセンブリにかける最適化まで進まない
+        if let (Emit::Ir, Some(pass)) = (&emit, print_after) {
+            if !pass.is_ir() {
+                return Err(Message::PrintAfterAssemblyPass(pass.name().to_string()));
+            }
+        }
+        match input {
+            Some(input) => Ok(Options { input, output, error_format, emit, opt_level, passes, print_after, time_passes, lang }),
+            None => Err(Message::MissingInput),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_file() {
+        let options = Options::parse(&args("prog.src -o out.s")).unwrap();
+
+        if let Input::File(path) = options.input {
+            assert_eq!(path, "prog.src".to_string());
+        } else {
+            panic!("ファイル入力になっていません。")
+        }
+        assert_eq!(options.output, Some("out.s".to_string()));
+        assert_eq!(options.error_format, ErrorFormat::Human);
+        assert_eq!(options.emit, Emit::Assembly);
+    }
+
+    #[test]
+    fn test_parse_error_format() {
+        let options = Options::parse(&args("--error-format=json prog.src")).unwrap();
+
+        assert_eq!(options.error_format, ErrorFormat::Json);
+        assert!(Options::parse(&args("--error-format=xml prog.src")).is_err());
+    }
+
+    #[test]
+    fn test_parse_emit() {
+        assert_eq!(Options::parse(&args("--emit=ir prog.src")).unwrap().emit, Emit::Ir);
+        assert_eq!(Options::parse(&args("--emit=asm prog.src")).unwrap().emit, Emit::Assembly);
+        assert!(Options::parse(&args("--emit=obj prog.src")).is_err());
+    }
+
+    #[test]
+    fn test_parse_opt_level() {
+        assert_eq!(Options::parse(&args("prog.src")).unwrap().opt_level, OptLevel::O0);
+        assert_eq!(Options::parse(&args("-O prog.src")).unwrap().opt_level, OptLevel::O1);
+        assert_eq!(Options::parse(&args("-O2 prog.src")).unwrap().opt_level, OptLevel::O2);
+        assert!(Options::parse(&args("-O3 prog.src")).is_err());
+    }
+
+    #[test]
+    fn test_parse_passes() {
+        let options = Options::parse(&args("--passes=inline,fold --print-after=fold --time-passes prog.src")).unwrap();
+
+        assert_eq!(options.passes, Some(vec![Pass::Inline, Pass::Fold]));
+        assert_eq!(options.print_after, Some(Pass::Fold));
+        assert!(options.time_passes);
+        assert_eq!(Options::parse(&args("--passes= prog.src")).unwrap().passes, Some(Vec::new()));
+        assert_eq!(Options::parse(&args("--print-after=unroll prog.src")).err(), Some(Message::UnknownPass("unroll".to_string())));
+        assert_eq!(Options::parse(&args("--emit=ir --print-after=peephole prog.src")).err(), Some(Message::PrintAfterAssemblyPass("peephole".to_string())));
+        assert!(Options::parse(&args("--emit=ir --print-after=fold prog.src")).is_ok());
+    }
+
+    #[test]
+    fn test_parse_lang() {
+        assert_eq!(Options::parse(&args("--lang=en prog.src")).unwrap().lang, Some(Lang::En));
+        assert_eq!(Options::parse(&args("prog.src")).unwrap().lang, None);
+        assert_eq!(Options::parse(&args("--lang=fr prog.src")).err(), Some(Message::UnknownLanguage("fr".to_string())));
+    }
+
+    #[test]
+    fn test_parse_stdin() {
+        let options = Options::parse(&args("-")).unwrap();
+
+        if let Input::Stdin = options.input {
+        } else {
+            panic!("標準入力になっていません。")
+        }
+        assert_eq!(options.output, None);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(Options::parse(&args("")).is_err());
+        assert!(Options::parse(&args("a.src b.src")).is_err());
+        assert!(Options::parse(&args("a.src -o")).is_err());
+        // 標準入力はファイルの前でも後でも、2つ目の入力になる
+        assert!(matches!(Options::parse(&args("a.src -")), Err(Message::MultipleInputs)));
+        assert!(matches!(Options::parse(&args("- a.src")), Err(Message::MultipleInputs)));
+    }
+}
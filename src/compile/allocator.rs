@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use ir;
+use ir::Value;
+
+use super::assembly::Register;
+
+// 値が生きている範囲。関数全体を通して命令に振った番号で表す
+struct Interval {
+    value: Value,
+    start: usize,
+    end: usize,
+    // 途中で関数を呼ぶなら、呼び出しで壊れないレジスタが要る
+    crosses_call: bool,
+}
+
+pub struct Allocation {
+    pub registers: HashMap<Value, Register>,
+    // 使ったcallee-savedレジスタ。プロローグで保存し、エピローグで戻す
+    pub callee_saved: Vec<Register>,
+}
+
+// 線形スキャンでレジスタを割り当てる。割り当てられなかった値はスタックに置く
+pub struct LinearScan;
+
+impl LinearScan {
+    pub fn allocate(func: &ir::Func, excluded: &HashSet<Value>) -> Allocation {
+        let intervals = Self::intervals(func, excluded);
+        let mut registers = HashMap::new();
+        let mut free = Register::caller_saved();
+        free.append(&mut Register::callee_saved());
+        let mut active: Vec<(usize, Value, Register)> = Vec::new();
+        for interval in &intervals {
+            // 終わった値のレジスタを空ける
+            let mut index = 0;
+            while index < active.len() {
+                if active[index].0 < interval.start {
+                    free.push(active.remove(index).2);
+                } else {
+                    index += 1;
+                }
+            }
+            let usable = |register: &Register| !interval.crosses_call || Register::callee_saved().contains(register);
+            // 呼び出しをまたがない値には、保存のいらないレジスタを先に使う
+            let candidate = Register::caller_saved().into_iter()
+                .chain(Register::callee_saved())
+                .find(|register| usable(register) && free.contains(register));
+            if let Some(register) = candidate {
+                free.retain(|&r| r != register);
+                registers.insert(interval.value, register);
+                active.push((interval.end, interval.value, register));
+                continue;
+            }
+            // 空きがなければ、最も長く生きる値をスタックに追い出す
+            let victim = active.iter().enumerate()
+                .filter(|(_, a)| usable(&a.2))
+                .max_by_key(|(_, a)| a.0)
+                .map(|(index, a)| (index, a.0));
+            if let Some((index, end)) = victim {
+                if end > interval.end {
+                    let (_, spilled, register) = active[index];
+                    registers.remove(&spilled);
+                    registers.insert(interval.value, register);
+                    active[index] = (interval.end, interval.value, register);
+                }
+            }
+        }
+        let callee_saved = Register::callee_saved().into_iter()
+            .filter(|register| registers.values().any(|r| r == register))
+            .collect();
+        Allocation{registers, callee_saved}
+    }
+
+    // ブロックの頭で引数を定義し、命令と終端命令に順に番号を振る
+    fn intervals(func: &ir::Func, excluded: &HashSet<Value>) -> Vec<Interval> {
+        let mut ranges: HashMap<Value, (usize, usize)> = HashMap::new();
+        let extend = |ranges: &mut HashMap<Value, (usize, usize)>, value: Value, position: usize| {
+            let range = ranges.entry(value).or_insert((position, position));
+            range.0 = range.0.min(position);
+            range.1 = range.1.max(position);
+        };
+        let mut starts = Vec::new();
+        let mut ends = Vec::new();
+        let mut calls = Vec::new();
+        let mut position = 0;
+        for block in &func.blocks {
+            starts.push(position);
+            for &param in &block.params {
+                extend(&mut ranges, param, position);
+            }
+            position += 1;
+            for instruction in &block.instructions {
+                for operand in instruction.operands() {
+                    extend(&mut ranges, operand, position);
+                }
+                if let Some(result) = instruction.result() {
+                    extend(&mut ranges, result, position);
+                }
+                if let ir::Instruction::Call(_, _, _) = instruction {
+                    calls.push(position);
+                }
+                position += 1;
+            }
+            for operand in block.terminator.operands() {
+                extend(&mut ranges, operand, position);
+            }
+            // 飛び先のブロック引数は、飛ぶ直前に書き込む
+            for target in block.terminator.targets() {
+                for &param in &func.blocks[target.block.0].params {
+                    extend(&mut ranges, param, position);
+                }
+            }
+            ends.push(position);
+            position += 1;
+        }
+        let (live_in, live_out) = Self::liveness(func);
+        for index in 0..func.blocks.len() {
+            for &value in &live_in[index] {
+                extend(&mut ranges, value, starts[index]);
+            }
+            for &value in &live_out[index] {
+                extend(&mut ranges, value, ends[index]);
+            }
+        }
+        let mut intervals: Vec<Interval> = ranges.into_iter()
+            .filter(|(value, _)| !excluded.contains(value))
+            .map(|(value, (start, end))| Interval {
+                value,
+                start,
+                end,
+                crosses_call: calls.iter().any(|&call| start < call && call < end),
+            })
+            .collect();
+        intervals.sort_by_key(|interval| (interval.start, interval.value));
+        intervals
+    }
+
+    // ブロックの入口と出口で生きている値
+    fn liveness(func: &ir::Func) -> (Vec<HashSet<Value>>, Vec<HashSet<Value>>) {
+        let mut uses = Vec::new();
+        let mut defs = Vec::new();
+        for block in &func.blocks {
+            let mut block_uses = HashSet::new();
+            let mut block_defs: HashSet<Value> = block.params.iter().cloned().collect();
+            for instruction in &block.instructions {
+                for operand in instruction.operands() {
+                    if !block_defs.contains(&operand) {
+                        block_uses.insert(operand);
+                    }
+                }
+                if let Some(result) = instruction.result() {
+                    block_defs.insert(result);
+                }
+            }
+            for operand in block.terminator.operands() {
+                if !block_defs.contains(&operand) {
+                    block_uses.insert(operand);
+                }
+            }
+            uses.push(block_uses);
+            defs.push(block_defs);
+        }
+        let mut live_in: Vec<HashSet<Value>> = vec![HashSet::new(); func.blocks.len()];
+        let mut live_out: Vec<HashSet<Value>> = vec![HashSet::new(); func.blocks.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, block) in func.blocks.iter().enumerate().rev() {
+                let mut out = HashSet::new();
+                for target in block.terminator.targets() {
+                    out.extend(live_in[target.block.0].iter().cloned());
+                }
+                let mut in_ = uses[index].clone();
+                in_.extend(out.difference(&defs[index]).cloned());
+                if in_ != live_in[index] || out != live_out[index] {
+                    live_in[index] = in_;
+                    live_out[index] = out;
+                    changed = true;
+                }
+            }
+        }
+        (live_in, live_out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ir::lower;
+
+    // 同時に生きている値が同じレジスタを使っていない
+    fn assert_no_conflict(func: &ir::Func, allocation: &Allocation) {
+        let intervals = LinearScan::intervals(func, &HashSet::new());
+        for a in &intervals {
+            for b in &intervals {
+                if a.value == b.value || a.end < b.start || b.end < a.start {
+                    continue;
+                }
+                if let (Some(x), Some(y)) = (allocation.registers.get(&a.value), allocation.registers.get(&b.value)) {
+                    assert_ne!(x, y, "{:?}と{:?}が同じレジスタです。", a.value, b.value);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_allocate() {
+        let program = lower("func main() 1 + 2");
+        let func = &program.funcs[0];
+
+        let allocation = LinearScan::allocate(func, &HashSet::new());
+
+        assert_eq!(allocation.registers.len(), func.values);
+        assert_eq!(allocation.registers[&Value(0)], Register::R10);
+        assert_eq!(allocation.registers[&Value(1)], Register::R11);
+        assert_no_conflict(func, &allocation);
+    }
+
+    #[test]
+    fn test_allocate_across_call() {
+        let program = lower("func f() 1
+            func main() 2 + f()");
+        let func = &program.funcs[1];
+
+        let allocation = LinearScan::allocate(func, &HashSet::new());
+
+        // 呼び出しをまたぐ値はcallee-savedのレジスタに置く
+        assert_eq!(allocation.registers[&Value(0)], Register::Rbx);
+        assert_eq!(allocation.callee_saved, vec![Register::Rbx]);
+        assert_no_conflict(func, &allocation);
+    }
+
+    #[test]
+    fn test_allocate_loop() {
+        let program = lower("func main() {
+            let i := 0
+            let s := 0
+            while i < 10 do {
+                s := s + i
+                i := i + 1
+            }
+            s
+        }");
+        let func = &program.funcs[0];
+
+        let allocation = LinearScan::allocate(func, &HashSet::new());
+
+        assert_no_conflict(func, &allocation);
+    }
+
+    #[test]
+    fn test_allocate_spill() {
+        let program = lower("func main() 1 + (2 + (3 + (4 + (5 + (6 + (7 + (8 + 9)))))))");
+        let func = &program.funcs[0];
+
+        let allocation = LinearScan::allocate(func, &HashSet::new());
+
+        assert!(allocation.registers.len() < func.values);
+        assert_eq!(allocation.callee_saved.len(), Register::callee_saved().len());
+        assert_no_conflict(func, &allocation);
+    }
+}
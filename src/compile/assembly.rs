@@ -1,10 +1,56 @@
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Line {
     Instruction(Instruction),
     Label(Label),
+    Directive(Directive),
 }
 
-#[derive(Clone)]
+impl Line {
+    pub fn code(&self) -> String {
+        match self {
+            Line::Instruction(instruction) => format!("  {}", instruction.destination_code()),
+            Line::Label(label) => format!("{}:", label.name),
+            Line::Directive(directive) => directive.code(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Directive {
+    Text,
+    Data,
+    Bss,
+    Rodata,
+    Quad(i64),
+    Zero(i64),
+    String(String),
+}
+
+impl Directive {
+    fn code(&self) -> String {
+        match self {
+            Directive::Text => ".text".to_string(),
+            Directive::Data => ".data".to_string(),
+            Directive::Bss => ".bss".to_string(),
+            Directive::Rodata => ".section .rodata".to_string(),
+            Directive::Quad(n) => format!("  .quad {}", n),
+            Directive::Zero(n) => format!("  .zero {}", n),
+            Directive::String(s) => format!("  .string \"{}\"", Self::escape(s)),
+        }
+    }
+
+    // 表示できないバイトは8進数で書く
+    fn escape(s: &str) -> String {
+        s.bytes().map(|b| match b {
+            b'"' => "\\\"".to_string(),
+            b'\\' => "\\\\".to_string(),
+            0x20..=0x7e => (b as char).to_string(),
+            _ => format!("\\{:03o}", b),
+        }).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
     // stack
     Push(Readable),
@@ -16,6 +62,13 @@ pub enum Instruction {
     Imul(Register, Readable),
     Cqo,
     Idiv(Register),
+    And(Register, Readable),
+    Or(Register, Readable),
+    Xor(Register, Readable),
+    Not(Register),
+    Neg(Register),
+    Shl(Register, Readable),
+    Sar(Register, Readable),
     Cmp(Register, Readable),
     Sete(Register),
     Setne(Register),
@@ -27,6 +80,8 @@ pub enum Instruction {
     // move
     Mov(Writable, Readable),
     Movzb(Register, Readable),
+    Movabs(Register, i64),
+    Lea(Register, Readable),
 
     // jump
     Je(Label),
@@ -36,6 +91,7 @@ pub enum Instruction {
 }
 
 impl Instruction {
+    #[allow(clippy::match_ref_pats, clippy::useless_format)]
     pub fn destination_code(&self) -> String {
         match &self {
             &Instruction::Push(readable) => format!("push {}", readable.symbol()),
@@ -45,6 +101,13 @@ impl Instruction {
             &Instruction::Imul(acc, x) => format!("imul {}, {}", acc.symbol(), x.symbol()),
             &Instruction::Cqo => format!("cqo"),
             &Instruction::Idiv(register) => format!("idiv {}", register.symbol()),
+            &Instruction::And(acc, x) => format!("and {}, {}", acc.symbol(), x.symbol()),
+            &Instruction::Or(acc, x) => format!("or {}, {}", acc.symbol(), x.symbol()),
+            &Instruction::Xor(acc, x) => format!("xor {}, {}", acc.symbol(), x.symbol()),
+            &Instruction::Not(register) => format!("not {}", register.symbol()),
+            &Instruction::Neg(register) => format!("neg {}", register.symbol()),
+            &Instruction::Shl(acc, x) => format!("shl {}, {}", acc.symbol(), x.symbol()),
+            &Instruction::Sar(acc, x) => format!("sar {}, {}", acc.symbol(), x.symbol()),
             &Instruction::Cmp(register, x) => format!("cmp {}, {}", register.symbol(), x.symbol()),
             &Instruction::Sete(register) => format!("sete {}", register.symbol()),
             &Instruction::Setne(register) => format!("setne {}", register.symbol()),
@@ -54,6 +117,8 @@ impl Instruction {
             &Instruction::Setge(register) => format!("setge {}", register.symbol()),
             &Instruction::Mov(register, x) => format!("mov {}, {}", register.symbol(), x.symbol()),
             &Instruction::Movzb(register, x) => format!("movzx {}, {}", register.symbol(), x.symbol()),
+            &Instruction::Movabs(register, n) => format!("movabs {}, {}", register.symbol(), n),
+            &Instruction::Lea(register, x) => format!("lea {}, {}", register.symbol(), x.symbol()),
             &Instruction::Je(label) => format!("je {}", label.name),
             &Instruction::Jmp(label) => format!("jmp {}", label.name),
             &Instruction::Call(label) => format!("call {}", label.name),
@@ -62,11 +127,13 @@ impl Instruction {
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Readable {
     Literal(i64),
     Register(Register),
     Address(Address),
+    Byte(Address),
+    RipRelative(Label),
 }
 
 impl Readable {
@@ -74,29 +141,34 @@ impl Readable {
         match &self {
             Self::Literal(n) => n.to_string(),
             Self::Register(r) => r.symbol(),
-            Self::Address(addr) => format!("[{}]", addr.register.symbol())
+            Self::Address(addr) => addr.symbol(),
+            Self::Byte(addr) => format!("byte ptr {}", addr.symbol()),
+            Self::RipRelative(label) => format!("[rip + {}]", label.name),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Writable {
     Register(Register),
     Address(Address),
+    Byte(Address),
 }
 
 impl Writable {
     pub fn symbol(&self) -> String {
         match &self {
             Self::Register(r) => r.symbol(),
-            Self::Address(addr) => format!("[{}]", addr.register.symbol()),
+            Self::Address(addr) => addr.symbol(),
+            Self::Byte(addr) => format!("byte ptr {}", addr.symbol()),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Register {
     Rax,
+    Rbx,
     Rcx,
     Rbp,
     Rdi,
@@ -105,13 +177,23 @@ pub enum Register {
     Rsp,
     R8,
     R9,
+    R10,
+    R11,
+    R12,
+    R13,
+    R14,
+    R15,
     Al,
+    Cl,
+    Dil,
 }
 
 impl Register {
+    #[allow(clippy::match_ref_pats)]
     fn symbol(&self) -> String {
         match &self {
             &Self::Rax => "rax".to_string(),
+            &Self::Rbx => "rbx".to_string(),
             &Self::Rcx => "rcx".to_string(),
             &Self::Rbp => "rbp".to_string(),
             &Self::Rdi => "rdi".to_string(),
@@ -120,7 +202,15 @@ impl Register {
             &Self::Rsp => "rsp".to_string(),
             &Self::R8 => "r8".to_string(),
             &Self::R9 => "r9".to_string(),
+            &Self::R10 => "r10".to_string(),
+            &Self::R11 => "r11".to_string(),
+            &Self::R12 => "r12".to_string(),
+            &Self::R13 => "r13".to_string(),
+            &Self::R14 => "r14".to_string(),
+            &Self::R15 => "r15".to_string(),
             &Self::Al => "al".to_string(),
+            &Self::Cl => "cl".to_string(),
+            &Self::Dil => "dil".to_string(),
         }
     }
 
@@ -134,20 +224,63 @@ impl Register {
             Self::R9,
         ]
     }
+
+    // 値の割り当てに使うレジスタ。関数呼び出しで壊れてよいもの
+    pub fn caller_saved() -> Vec<Self> {
+        vec![
+            Self::R10,
+            Self::R11,
+        ]
+    }
+
+    // 値の割り当てに使うレジスタ。使う関数が元の値を保存しておくもの
+    pub fn callee_saved() -> Vec<Self> {
+        vec![
+            Self::Rbx,
+            Self::R12,
+            Self::R13,
+            Self::R14,
+            Self::R15,
+        ]
+    }
 }
 
-#[derive(Clone)]
+// [base + index * scale + displacement]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Address {
-    pub register: Register
+    pub base: Register,
+    pub index: Option<(Register, i64)>,
+    pub displacement: i64,
 }
 
 impl Address {
-    pub fn new(register: Register) -> Address {
-        Address{register}
+    pub fn new(base: Register) -> Address {
+        Address{base, index: None, displacement: 0}
+    }
+
+    pub fn offset(self, displacement: i64) -> Address {
+        Address{displacement, ..self}
+    }
+
+    pub fn indexed(self, index: Register, scale: i64) -> Address {
+        Address{index: Some((index, scale)), ..self}
+    }
+
+    fn symbol(&self) -> String {
+        let mut s = self.base.symbol();
+        if let Some((index, scale)) = &self.index {
+            s = format!("{} + {}*{}", s, index.symbol(), scale);
+        }
+        if self.displacement > 0 {
+            s = format!("{} + {}", s, self.displacement);
+        } else if self.displacement < 0 {
+            s = format!("{} - {}", s, -self.displacement);
+        }
+        format!("[{}]", s)
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Label {
     pub name: String,
 }
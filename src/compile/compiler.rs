@@ -1,26 +1,20 @@
+use std::collections::HashMap;
 use std::env;
 
-use sourcecode::Span;
-
-use token::Operator;
-
-use parse::SyntaxTree;
-use parse::Root;
-use parse::Func;
-use parse::Statement;
-use parse::Expression;
-use parse::PureExpression;
-use parse::IfExpression;
-use parse::BlockExpression;
-use parse::Equality;
-use parse::Relational;
-use parse::Add;
-use parse::Multiply;
-use parse::Unary;
-use parse::Primary;
-use parse::FnCall;
-
-use super::scope::Scope;
+use ir;
+use ir::Program;
+use ir::GlobalContent;
+use ir::BinaryOperator;
+use ir::UnaryOperator;
+use ir::Width;
+
+use typecheck::align_to;
+
+use super::allocator::LinearScan;
+use super::peephole::Peephole;
+
+use super::assembly::Address;
+use super::assembly::Directive;
 use super::assembly::Instruction;
 use super::assembly::Label;
 use super::assembly::Line;
@@ -28,25 +22,94 @@ use super::assembly::Register;
 use super::assembly::Readable;
 use super::assembly::Writable;
 
-pub struct Compiler{
+pub struct Compiler {
+    pub data_lines: Vec<Line>,
     pub func_compilers: Vec<FuncCompiler>,
 }
 
 impl Compiler {
-    pub fn compile(syntaxtree: &Root) -> Result<Self, (Span, String)> {
-        let mut func_compilers = Vec::new();
-        for func in &syntaxtree.funcs {
-            match FuncCompiler::compile(func) {
-                Ok(c) => func_compilers.push(c),
-                Err(e) => return Err(e),
+    pub fn compile(program: &Program) -> Self {
+        let mut data_lines = Self::compile_globals(&program.globals);
+        data_lines.append(&mut Self::string_literals(&program.strings));
+        let func_compilers = program.funcs.iter().map(FuncCompiler::compile).collect();
+        Self{data_lines, func_compilers}
+    }
+
+    // 各関数の命令列にのぞき穴最適化をかける
+    pub fn optimize(&mut self, peephole: &Peephole) {
+        for func_compiler in &mut self.func_compilers {
+            func_compiler.lines = peephole.optimize(&func_compiler.lines);
+        }
+    }
+
+    pub fn global_label(name: &String) -> Label {
+        Label {
+            name: format!(".Lglobal.{}", name)
+        }
+    }
+
+    fn string_label(index: usize) -> Label {
+        Label {
+            name: format!(".Lstring.{}", index)
+        }
+    }
+
+    // 初期値のある変数は.data、ない変数は.bss、定数は.rodataに置く
+    fn compile_globals(globals: &[ir::Global]) -> Vec<Line> {
+        let mut data = Vec::new();
+        let mut bss = Vec::new();
+        let mut rodata = Vec::new();
+        for global in globals {
+            let label = Line::Label(Self::global_label(&global.name));
+            match global.content {
+                GlobalContent::Variable(value) => {
+                    data.push(label);
+                    data.push(Line::Directive(Directive::Quad(value)));
+                },
+                // 変数は8バイト単位で読み書きするので切り上げておく
+                GlobalContent::Zero(size) => {
+                    bss.push(label);
+                    bss.push(Line::Directive(Directive::Zero((size + 7) / 8 * 8)));
+                },
+                GlobalContent::Constant(value) => {
+                    rodata.push(label);
+                    rodata.push(Line::Directive(Directive::Quad(value)));
+                },
             }
         }
-        Ok(Self{func_compilers})
+        let mut lines = Vec::new();
+        for (directive, mut section) in [(Directive::Data, data), (Directive::Bss, bss), (Directive::Rodata, rodata)] {
+            if !section.is_empty() {
+                lines.push(Line::Directive(directive));
+                lines.append(&mut section);
+            }
+        }
+        lines
     }
 
+    fn string_literals(strings: &[String]) -> Vec<Line> {
+        let mut lines = Vec::new();
+        for (index, content) in strings.iter().enumerate() {
+            lines.push(Line::Label(Self::string_label(index)));
+            lines.push(Line::Directive(Directive::String(content.clone())));
+        }
+        if !lines.is_empty() {
+            lines.insert(0, Line::Directive(Directive::Rodata));
+        }
+        lines
+    }
+
+    #[allow(clippy::needless_borrow)]
     pub fn assembly_string(&self) -> String {
-        self.func_compilers.iter().map(|fc| fc.assembly_string())
+        let mut assembly = self.data_lines.iter().map(|line| line.code())
         .fold("".to_string(), |mut acc, s| {
+            acc.push_str(&s);
+            acc.push('\n');
+            acc
+        });
+        assembly.push_str(&Line::Directive(Directive::Text).code());
+        self.func_compilers.iter().map(|fc| fc.assembly_string())
+        .fold(assembly, |mut acc, s| {
             acc.push_str(&"\n\n");
             acc.push_str(&s);
             acc
@@ -54,43 +117,114 @@ impl Compiler {
     }
 }
 
+// 値の置き場所
+#[derive(Clone, Copy)]
+enum Location {
+    Register(Register),
+    // rbpからのオフセット
+    Stack(i64),
+    // ローカル変数の領域のアドレスと32ビットに収まる定数は、使う場所で作り直す
+    Slot(i64),
+    Constant(i64),
+}
+
+// 中間表現の関数を機械語に直す。
+// 値はレジスタに割り当て、割り当てられなかったものはスタックに置く
 pub struct FuncCompiler {
     pub lines: Vec<Line>,
-    next_label: u64,
-    scope: Scope,
-    stack_depth: i64,
+    name: String,
+    locations: Vec<Location>,
+    // 保存したcallee-savedレジスタと保存先のrbpからのオフセット
+    saved: Vec<(Register, i64)>,
+    params: Vec<Vec<ir::Value>>,
+    // 添字に要素の大きさを掛けてから足す足し算と、掛ける大きさ
+    scales: HashMap<ir::Value, i64>,
 }
 
 impl FuncCompiler {
-    pub fn compile(func: &Func) -> Result<Self, (Span, String)> {
-        let mut assembly = Self{
-            lines: Vec::new(),
-            next_label: 0,
-            scope: Scope::new(),
-            stack_depth: 0,
-        };
-        let mut arg_lines = Vec::new();
-        for (arg, reg) in func.args.iter().zip(Register::fn_args()) {
-            arg_lines.push(Line::Instruction(Instruction::Push(Readable::Register(reg))));
-            match assembly.scope.declare(arg) {
-                Ok(mut lines) => arg_lines.append(&mut lines),
-                Err(_) => return Err((func.span().clone(), "引数エラー".to_string())), // TODO
+    pub fn compile(func: &ir::Func) -> Self {
+        let (func, scales) = Self::fuse_elements(func);
+        let func = &func;
+        let mut next = 0;
+        let mut slots = Vec::new();
+        for slot in &func.slots {
+            next = align_to(next + slot.size, slot.align);
+            slots.push(next);
+        }
+        let mut rematerialized = HashMap::new();
+        for instruction in func.blocks.iter().flat_map(|block| block.instructions.iter()) {
+            match instruction {
+                ir::Instruction::SlotAddress(dst, slot) => {
+                    rematerialized.insert(*dst, Location::Slot(slots[slot.0]));
+                },
+                ir::Instruction::Const(dst, n) if *n == *n as i32 as i64 => {
+                    rematerialized.insert(*dst, Location::Constant(*n));
+                },
+                _ => (),
             }
         }
-        let mut body_lines = match assembly.compile_expression(&func.body) {
-            Ok(lines) => lines,
-            Err(e) => return Err(e),
+        let excluded = rematerialized.keys().cloned().collect();
+        let allocation = LinearScan::allocate(func, &excluded);
+        let mut locations = Vec::new();
+        for index in 0..func.values {
+            let value = ir::Value(index);
+            let location = match (rematerialized.get(&value), allocation.registers.get(&value)) {
+                (Some(&location), _) => location,
+                (None, Some(&register)) => Location::Register(register),
+                (None, None) => {
+                    next += 8;
+                    Location::Stack(next)
+                },
+            };
+            locations.push(location);
+        }
+        let mut saved = Vec::new();
+        for &register in &allocation.callee_saved {
+            next += 8;
+            saved.push((register, next));
+        }
+        let mut compiler = Self{
+            lines: Vec::new(),
+            name: func.name.clone(),
+            locations,
+            saved,
+            params: func.blocks.iter().map(|block| block.params.clone()).collect(),
+            scales,
         };
 
-        assembly.lines.push(Line::Label(Self::func_label(&func.name)));
-        assembly.lines.append(&mut assembly.scope.prologue());
-        assembly.lines.append(&mut arg_lines);
-        assembly.lines.append(&mut body_lines);
-        assembly.lines.push(Line::Instruction(Instruction::Pop(Register::Rax)));
-        assembly.lines.append(&mut assembly.scope.epilogue());
-        Ok(assembly)
+        compiler.lines.push(Line::Label(Self::func_label(&func.name)));
+        compiler.lines.append(&mut Self::prologue(align_to(next, 16)));
+        for (register, offset) in compiler.saved.clone() {
+            compiler.instruction(Instruction::Mov(Writable::Address(Address::new(Register::Rbp).offset(-offset)), Readable::Register(register)));
+        }
+        let registers = Register::fn_args();
+        for (i, &param) in func.entry().params.iter().enumerate() {
+            match registers.get(i) {
+                Some(&reg) => compiler.store_value(param, reg),
+                None => {
+                    // 7個目以降の引数はリターンアドレスと保存したrbpの上に積まれている
+                    let offset = 16 + 8 * (i - registers.len()) as i64;
+                    compiler.instruction(Instruction::Mov(Writable::Register(Register::Rax), Readable::Address(Address::new(Register::Rbp).offset(offset))));
+                    compiler.store_value(param, Register::Rax);
+                },
+            }
+        }
+        for block in &func.blocks {
+            compiler.lines.push(Line::Label(compiler.block_label(block.id)));
+            for instruction in &block.instructions {
+                compiler.compile_instruction(instruction);
+            }
+            compiler.compile_terminator(block.id, &block.terminator);
+        }
+        compiler.lines.push(Line::Label(compiler.return_label()));
+        for (register, offset) in compiler.saved.clone() {
+            compiler.instruction(Instruction::Mov(Writable::Register(register), Readable::Address(Address::new(Register::Rbp).offset(-offset))));
+        }
+        compiler.lines.append(&mut Self::epilogue());
+        compiler
     }
 
+    #[allow(clippy::cmp_owned)]
     fn func_label(name: &String) -> Label {
         if env::var("OS").map(|var| var == "MAC".to_string()).unwrap_or(false) {
             Label{name: format!("_{}", name)}
@@ -100,12 +234,7 @@ impl FuncCompiler {
     }
 
     pub fn assembly_string(&self) -> String {
-        self.lines.iter().map(|line| {
-            match line {
-                Line::Instruction(instruction) => format!("  {}", instruction.destination_code()),
-                Line::Label(label) => format!("{}:", label.name),
-            }
-        })
+        self.lines.iter().map(|line| line.code())
         .fold("".to_string(), |mut acc, s| {
             acc.push('\n');
             acc.push_str(&s);
@@ -113,377 +242,435 @@ impl FuncCompiler {
         })
     }
 
-    fn new_label(&mut self) -> Label {
-        let index = self.next_label;
-        self.next_label += 1;
+    // エピローグの直前に置くラベル。returnはraxに値を入れてここへ飛ぶ
+    fn return_label(&self) -> Label {
         Label {
-            name: format!(".L{}", index)
+            name: format!(".L{}.return", self.name)
         }
     }
 
-    fn compile_statement(&mut self, statement: &Statement) -> Result<Vec<Line>, (Span, String)> {
-        let before_stack_depth = self.stack_depth;
-        match statement {
-            Statement::Assignment(asn) => {
-                match self.compile_expression(asn.content()) {
-                    Ok(mut assign_lines) => {
-                        assign_lines.append(&mut self.scope.declare(&asn.identifier().value).unwrap());
-                        self.stack_depth += 8;
-                        Ok(assign_lines)
-                    },
-                    Err(e) => {
-                        self.stack_depth = before_stack_depth;
-                        Err(e)
-                    },
-                }
-            },
-            Statement::Return(ret) => {
-                match self.compile_expression(ret.content()) {
-                    Ok(mut return_ines) => {
-                        return_ines.push(Line::Instruction(Instruction::Pop(Register::Rax)));
-                        self.stack_depth -= 8;
-                        Ok(return_ines)
-                    },
-                    Err(e) => {
-                        self.stack_depth = before_stack_depth;
-                        Err(e)
-                    },
-                }
-            }
+    fn block_label(&self, block: ir::BlockId) -> Label {
+        Label {
+            name: format!(".L{}.{}", self.name, block.0)
         }
     }
 
-    fn compile_expression(&mut self, expression: &Expression) -> Result<Vec<Line>, (Span, String)> {
-        match expression {
-            Expression::PureExpression(expr) => self.compile_pure_expression(expr),
-            Expression::IfExpression(expr) => self.compile_if_expression(expr),
-            Expression::BlockExpression(expr) => self.compile_block_expression(expr),
+    // 0だったときに飛ぶ先へ値を渡すための経由地
+    fn else_label(&self, block: ir::BlockId) -> Label {
+        Label {
+            name: format!(".L{}.{}.else", self.name, block.0)
         }
     }
 
-    fn compile_pure_expression(&mut self, expr: &PureExpression) -> Result<Vec<Line>, (Span, String)> {
-        self.compile_equality(&expr.equality)
+    fn prologue(stack_size: i64) -> Vec<Line> {
+        vec![
+            Line::Instruction(Instruction::Push(Readable::Register(Register::Rbp))),
+            Line::Instruction(Instruction::Mov(Writable::Register(Register::Rbp), Readable::Register(Register::Rsp))),
+            Line::Instruction(Instruction::Sub(Register::Rsp, Readable::Literal(stack_size))),
+        ]
     }
 
-    fn compile_if_expression(&mut self, expr: &IfExpression) -> Result<Vec<Line>, (Span, String)> {
-        let before_stack_depth = self.stack_depth;
-        let else_label = self.new_label();
-        let end_label = self.new_label();
-
-        let condition_lines = match self.compile_expression(&*expr.condition) {
-            Ok(lines) => lines,
-            Err(e) => {
-                self.stack_depth = before_stack_depth;
-                return Err(e)
-            },
-        };
-
-        let mut lines = condition_lines;
-
-        lines.append(&mut vec![
-            Line::Instruction(Instruction::Pop(Register::Rax)),
-            Line::Instruction(Instruction::Cmp(Register::Rax, Readable::Literal(0))),
-        ]);
-        self.stack_depth -= 8;
-
-        let stack_depth_before_block = self.stack_depth;
-
-        let mut then_lines = match self.compile_expression(&*expr.then) {
-            Ok(lines) => lines,
-            Err(e) => {
-                self.stack_depth = before_stack_depth;
-                return Err(e)
-            },
-        };
+    fn epilogue() -> Vec<Line> {
+        vec![
+            Line::Instruction(Instruction::Mov(Writable::Register(Register::Rsp), Readable::Register(Register::Rbp))),
+            Line::Instruction(Instruction::Pop(Register::Rbp)),
+            Line::Instruction(Instruction::Ret),
+        ]
+    }
 
-        self.stack_depth = stack_depth_before_block;
+    // 要素のアドレス base + index * size は、sizeが1, 2, 4, 8ならleaの拡大縮小つきのアドレスで求められる。
+    // 掛け算の結果を足し算にしか使わなければ、掛け算を除いて足し算が添字を直接使うようにする
+    fn fuse_elements(func: &ir::Func) -> (ir::Func, HashMap<ir::Value, i64>) {
+        let instructions = || func.blocks.iter().flat_map(|block| block.instructions.iter());
+        let mut constants = HashMap::new();
+        let mut uses: HashMap<ir::Value, usize> = HashMap::new();
+        for block in &func.blocks {
+            for instruction in &block.instructions {
+                if let ir::Instruction::Const(dst, n) = instruction {
+                    constants.insert(*dst, *n);
+                }
+                for operand in instruction.operands() {
+                    *uses.entry(operand).or_insert(0) += 1;
+                }
+            }
+            for operand in block.terminator.operands() {
+                *uses.entry(operand).or_insert(0) += 1;
+            }
+        }
+        let mut scaled = HashMap::new();
+        for instruction in instructions() {
+            if let ir::Instruction::Binary(dst, BinaryOperator::Mul, index, size) = instruction {
+                match constants.get(size) {
+                    Some(&scale) if matches!(scale, 1 | 2 | 4 | 8) => {
+                        scaled.insert(*dst, (*index, scale));
+                    },
+                    _ => (),
+                }
+            }
+        }
+        let mut added: HashMap<ir::Value, usize> = HashMap::new();
+        for instruction in instructions() {
+            if let ir::Instruction::Binary(_, BinaryOperator::Add, base, offset) = instruction {
+                if base != offset && scaled.contains_key(offset) {
+                    *added.entry(*offset).or_insert(0) += 1;
+                }
+            }
+        }
+        scaled.retain(|offset, _| added.get(offset) == uses.get(offset));
+
+        let mut fused = func.clone();
+        let mut scales = HashMap::new();
+        for block in &mut fused.blocks {
+            block.instructions.retain(|instruction| match instruction {
+                ir::Instruction::Binary(dst, BinaryOperator::Mul, _, _) => !scaled.contains_key(dst),
+                _ => true,
+            });
+            for instruction in &mut block.instructions {
+                if let ir::Instruction::Binary(dst, BinaryOperator::Add, _, offset) = instruction {
+                    if let Some(&(index, scale)) = scaled.get(offset) {
+                        scales.insert(*dst, scale);
+                        *offset = index;
+                    }
+                }
+            }
+        }
+        (fused, scales)
+    }
 
-        let mut else_lines = match self.compile_expression(&*expr.else_) {
-            Ok(lines) => lines,
-            Err(e) => {
-                self.stack_depth = before_stack_depth;
-                return Err(e)
-            },
-        };
+    fn instruction(&mut self, instruction: Instruction) {
+        self.lines.push(Line::Instruction(instruction));
+    }
 
-        lines.push(Line::Instruction(Instruction::Je(else_label.clone())));
-        lines.append(&mut then_lines);
-        lines.push(Line::Instruction(Instruction::Jmp(end_label.clone())));
-        lines.push(Line::Label(else_label));
-        lines.append(&mut else_lines);
-        lines.push(Line::Label(end_label));
-        self.stack_depth += 8;
+    fn location(&self, value: ir::Value) -> Location {
+        self.locations[value.0]
+    }
 
-        Ok(lines)
+    fn stack_address(offset: i64) -> Address {
+        Address::new(Register::Rbp).offset(-offset)
     }
 
-    fn compile_block_expression(&mut self, expr: &BlockExpression) -> Result<Vec<Line>, (Span, String)> {
-        let before_stack_depth = self.stack_depth;
-        self.scope.into_block();
-        let mut lines = Vec::new();
-        for stmt in &expr.statements {
-            match self.compile_statement(stmt) {
-                Ok(mut stmt_lines) => lines.append(&mut stmt_lines),
-                Err(e) => {
-                    self.stack_depth = before_stack_depth;
-                    self.scope.outof_block();
-                    return Err(e)
-                },
-            }
+    // 値を置くレジスタ。スタックに置く値はraxで計算してから書き込む
+    fn destination(&self, value: ir::Value) -> Register {
+        match self.location(value) {
+            Location::Register(register) => register,
+            _ => Register::Rax,
         }
-        match self.compile_expression(&*expr.outcome) {
-            Ok(mut expr_lines) => lines.append(&mut expr_lines),
-            Err(e) => {
-                self.stack_depth = before_stack_depth;
-                self.scope.outof_block();
-                return Err(e)
-            },
-        }
-        self.scope.outof_block();
-        Ok(lines)
     }
 
-    fn compile_equality(&mut self, equality: &Equality) -> Result<Vec<Line>, (Span, String)> {
-        let before_stack_depth = self.stack_depth;
-        let mut lines = Vec::new();
-        match self.compile_relational(equality.head()) {
-            Ok(mut relational_lines) => lines.append(&mut relational_lines),
-            Err(e) => {
-                self.stack_depth = before_stack_depth;
-                return Err(e)
-            },
-        }
-        for (operator, relational) in equality.tail() {
-            match self.compile_relational(relational) {
-                Ok(mut relational_lines) => lines.append(&mut relational_lines),
-                Err(e) => return {
-                    self.stack_depth = before_stack_depth;
-                    Err(e)
-                },
-            }
-            lines.push(Line::Instruction(Instruction::Pop(Register::Rdi)));
-            lines.push(Line::Instruction(Instruction::Pop(Register::Rax)));
-            self.stack_depth -= 8 * 2;
-            lines.push(Line::Instruction(Instruction::Cmp(Register::Rax, Readable::Register(Register::Rdi))));
-            let setx = match operator.value {
-                Operator::Equal => Instruction::Sete(Register::Al),
-                _ => Instruction::Setne(Register::Al),
-            };
-            lines.push(Line::Instruction(setx));
-            lines.push(Line::Instruction(Instruction::Movzb(Register::Rax, Readable::Register(Register::Al))));
-            lines.push(Line::Instruction(Instruction::Push(Readable::Register(Register::Rax))));
-            self.stack_depth += 8;
+    fn load_value(&mut self, register: Register, value: ir::Value) {
+        match self.location(value) {
+            Location::Register(r) if r == register => (),
+            Location::Register(r) => self.instruction(Instruction::Mov(Writable::Register(register), Readable::Register(r))),
+            Location::Stack(offset) => self.instruction(Instruction::Mov(Writable::Register(register), Readable::Address(Self::stack_address(offset)))),
+            Location::Slot(offset) => self.instruction(Instruction::Lea(register, Readable::Address(Self::stack_address(offset)))),
+            Location::Constant(n) => self.instruction(Instruction::Mov(Writable::Register(register), Readable::Literal(n))),
         }
-        Ok(lines)
     }
 
-    fn compile_relational(&mut self, relational: &Relational) -> Result<Vec<Line>, (Span, String)> {
-        let before_stack_depth = self.stack_depth;
-        let mut lines = Vec::new();
-        match self.compile_add(relational.head()) {
-            Ok(mut add_lines) => lines.append(&mut add_lines),
-            Err(e) => {
-                self.stack_depth = before_stack_depth;
-                return Err(e)
-            },
-        }
-        for (operator, add) in relational.tail() {
-            match self.compile_add(add) {
-                Ok(mut add_lines) => lines.append(&mut add_lines),
-                Err(e) => {
-                    self.stack_depth = before_stack_depth;
-                    return Err(e)
-                },
-            }
-            lines.push(Line::Instruction(Instruction::Pop(Register::Rdi)));
-            lines.push(Line::Instruction(Instruction::Pop(Register::Rax)));
-            self.stack_depth -= 8 * 2;
-            lines.push(Line::Instruction(Instruction::Cmp(Register::Rax, Readable::Register(Register::Rdi))));
-            let setx = match operator.value {
-                Operator::Less => Instruction::Setl(Register::Al),
-                Operator::LessEq => Instruction::Setle(Register::Al),
-                Operator::Greater => Instruction::Setg(Register::Al),
-                _ => Instruction::Setge(Register::Al),
-            };
-            lines.push(Line::Instruction(setx));
-            lines.push(Line::Instruction(Instruction::Movzb(Register::Rax, Readable::Register(Register::Al))));
-            lines.push(Line::Instruction(Instruction::Push(Readable::Register(Register::Rax))));
-            self.stack_depth += 8;
+    fn store_value(&mut self, value: ir::Value, register: Register) {
+        match self.location(value) {
+            Location::Register(r) if r == register => (),
+            Location::Register(r) => self.instruction(Instruction::Mov(Writable::Register(r), Readable::Register(register))),
+            Location::Stack(offset) => self.instruction(Instruction::Mov(Writable::Address(Self::stack_address(offset)), Readable::Register(register))),
+            Location::Slot(_) | Location::Constant(_) => unreachable!(),
         }
-        Ok(lines)
     }
 
-    fn compile_add(&mut self, add: &Add) -> Result<Vec<Line>, (Span, String)> {
-        let before_stack_depth = self.stack_depth;
-        let mut lines = Vec::new();
-        let head = add.head();
-        match self.compile_multiply(head) {
-            Ok(mut multiply_lines) => lines.append(&mut multiply_lines),
-            Err(e) => {
-                self.stack_depth = before_stack_depth;
-                return Err(e)
+    // 命令のオペランドとして読む。作り直す必要があればscratchを使う
+    fn operand(&mut self, value: ir::Value, scratch: Register) -> Readable {
+        match self.location(value) {
+            Location::Register(r) => Readable::Register(r),
+            Location::Stack(offset) => Readable::Address(Self::stack_address(offset)),
+            Location::Constant(n) => Readable::Literal(n),
+            Location::Slot(_) => {
+                self.load_value(scratch, value);
+                Readable::Register(scratch)
             },
         }
-        for (operator, multiply) in add.tail() {
-            match self.compile_multiply(multiply) {
-                Ok(mut multiply_lines) => lines.append(&mut multiply_lines),
-                Err(e) => {
-                    self.stack_depth = before_stack_depth;
-                    return Err(e)
-                },
-            }
-            lines.push(Line::Instruction(Instruction::Pop(Register::Rdi)));
-            lines.push(Line::Instruction(Instruction::Pop(Register::Rax)));
-            self.stack_depth -= 8 * 2;
-            match operator.value {
-                Operator::Add => lines.push(Line::Instruction(Instruction::Add(Register::Rax, Readable::Register(Register::Rdi)))),
-                _ => lines.push(Line::Instruction(Instruction::Sub(Register::Rax, Readable::Register(Register::Rdi)))),
-            }
-            lines.push(Line::Instruction(Instruction::Push(Readable::Register(Register::Rax))));
-            self.stack_depth += 8;
-        }
-        Ok(lines)
     }
 
-    fn compile_multiply(&mut self, multiply: &Multiply) -> Result<Vec<Line>, (Span, String)> {
-        let before_stack_depth = self.stack_depth;
-        let mut lines = Vec::new();
-        let head = multiply.head();
-        match self.compile_unary(head) {
-            Ok(mut unary_lines) => lines.append(&mut unary_lines),
-            Err(e) => {
-                self.stack_depth = before_stack_depth;
-                return Err(e)
+    // アドレスの値が指す先。ローカル変数の領域はrbpから直接指す
+    fn memory(&mut self, value: ir::Value, scratch: Register) -> Address {
+        match self.location(value) {
+            Location::Register(r) => Address::new(r),
+            Location::Slot(offset) => Self::stack_address(offset),
+            _ => {
+                self.load_value(scratch, value);
+                Address::new(scratch)
             },
         }
-        for (operator, unary) in multiply.tail() {
-            match self.compile_unary(unary) {
-                Ok(mut unary_lines) => lines.append(&mut unary_lines),
-                Err(e) => {
-                    self.stack_depth = before_stack_depth;
-                    return Err(e)
-                },
-            }
-            lines.push(Line::Instruction(Instruction::Pop(Register::Rdi)));
-            lines.push(Line::Instruction(Instruction::Pop(Register::Rax)));
-            self.stack_depth -= 8 * 2;
-            match operator.value {
-                Operator::Mul => lines.push(Line::Instruction(Instruction::Imul(Register::Rax, Readable::Register(Register::Rdi)))),
-                _ => {
-                    lines.push(Line::Instruction(Instruction::Cqo));
-                    lines.push(Line::Instruction(Instruction::Idiv(Register::Rdi)));
-                }
-            }
-            lines.push(Line::Instruction(Instruction::Push(Readable::Register(Register::Rax))));
-            self.stack_depth += 8;
-        }
-        Ok(lines)
     }
 
-    fn compile_unary(&mut self, unary: &Unary) -> Result<Vec<Line>, (Span, String)> {
-        let before_stack_depth = self.stack_depth;
-        let mut lines = Vec::new();
-        match &unary {
-            &Unary::Positive(primary, _) => {
-                match self.compile_primary(&primary) {
-                    Ok(mut primary_lines) => lines.append(&mut primary_lines),
-                    Err(e) => {
-                        self.stack_depth = before_stack_depth;
-                        return Err(e)
+    fn compile_instruction(&mut self, instruction: &ir::Instruction) {
+        match instruction {
+            ir::Instruction::Const(dst, n) => {
+                if let Location::Constant(_) = self.location(*dst) {
+                    return;
+                }
+                // movの即値は32ビットまでなので、収まらなければmovabsを使う
+                let register = self.destination(*dst);
+                self.instruction(Instruction::Movabs(register, *n));
+                self.store_value(*dst, register);
+            },
+            ir::Instruction::Unary(dst, operator, x) => {
+                let register = match operator {
+                    UnaryOperator::Neg | UnaryOperator::Not => self.destination(*dst),
+                    UnaryOperator::LogicalNot | UnaryOperator::Narrow => Register::Rax,
+                };
+                self.load_value(register, *x);
+                match operator {
+                    UnaryOperator::Neg => self.instruction(Instruction::Neg(register)),
+                    UnaryOperator::Not => self.instruction(Instruction::Not(register)),
+                    UnaryOperator::LogicalNot => {
+                        self.instruction(Instruction::Cmp(Register::Rax, Readable::Literal(0)));
+                        self.instruction(Instruction::Sete(Register::Al));
+                        self.instruction(Instruction::Movzb(Register::Rax, Readable::Register(Register::Al)));
+                    },
+                    UnaryOperator::Narrow => {
+                        self.instruction(Instruction::Movzb(Register::Rax, Readable::Register(Register::Al)));
                     },
                 }
-            }
-            &Unary::Negative(primary, _) => {
-                match self.compile_primary(&primary) {
-                    Ok(mut primary_lines) => lines.append(&mut primary_lines),
-                    Err(e) => {
-                        self.stack_depth = before_stack_depth;
-                        return Err(e)
+                self.store_value(*dst, register);
+            },
+            ir::Instruction::Binary(dst, BinaryOperator::Add, base, index) if self.scales.contains_key(dst) => {
+                let scale = self.scales[dst];
+                let address = self.memory(*base, Register::Rax);
+                let index = match self.location(*index) {
+                    Location::Register(r) => r,
+                    _ => {
+                        self.load_value(Register::Rdi, *index);
+                        Register::Rdi
                     },
                 };
-                lines.push(Line::Instruction(Instruction::Pop(Register::Rdi)));
-                lines.push(Line::Instruction(Instruction::Mov(Writable::Register(Register::Rax), Readable::Literal(0))));
-                lines.push(Line::Instruction(Instruction::Sub(Register::Rax, Readable::Register(Register::Rdi))));
-                lines.push(Line::Instruction(Instruction::Push(Readable::Register(Register::Rax))));
-                self.stack_depth += 0;
-            }
+                let register = self.destination(*dst);
+                self.instruction(Instruction::Lea(register, Readable::Address(address.indexed(index, scale))));
+                self.store_value(*dst, register);
+            },
+            ir::Instruction::Binary(dst, operator, x, y) => self.compile_binary(*dst, *operator, *x, *y),
+            // 使う場所で作り直す
+            ir::Instruction::SlotAddress(_, _) => (),
+            ir::Instruction::GlobalAddress(dst, name) => {
+                let register = self.destination(*dst);
+                self.instruction(Instruction::Lea(register, Readable::RipRelative(Compiler::global_label(name))));
+                self.store_value(*dst, register);
+            },
+            ir::Instruction::StringAddress(dst, index) => {
+                let register = self.destination(*dst);
+                self.instruction(Instruction::Lea(register, Readable::RipRelative(Compiler::string_label(*index))));
+                self.store_value(*dst, register);
+            },
+            ir::Instruction::Load(dst, width, address) => {
+                let address = self.memory(*address, Register::Rax);
+                let register = self.destination(*dst);
+                self.instruction(Self::load(*width, register, address));
+                self.store_value(*dst, register);
+            },
+            ir::Instruction::Store(width, address, x) => {
+                let address = self.memory(*address, Register::Rax);
+                let register = match (width, self.location(*x)) {
+                    (Width::Quad, Location::Register(r)) => r,
+                    _ => {
+                        self.load_value(Register::Rdi, *x);
+                        Register::Rdi
+                    },
+                };
+                self.instruction(Self::store(*width, address, register));
+            },
+            ir::Instruction::CopyMemory(dst, src, size) => {
+                self.load_value(Register::Rax, *dst);
+                self.load_value(Register::Rsi, *src);
+                self.lines.append(&mut Self::copy(*size));
+            },
+            ir::Instruction::Call(dst, name, args) => {
+                self.compile_call(name, args);
+                self.store_value(*dst, Register::Rax);
+            },
         }
-        Ok(lines)
     }
 
-    fn compile_primary(&mut self, primary: &Primary) -> Result<Vec<Line>, (Span, String)> {
-        let before_stack_depth = self.stack_depth;
-        let mut lines = Vec::new();
-        match &primary {
-            &Primary::Integer(n) => {
-                lines.push(Line::Instruction(Instruction::Push(Readable::Literal(n.value))));
-                self.stack_depth += 8;
+    fn compile_binary(&mut self, dst: ir::Value, operator: BinaryOperator, x: ir::Value, y: ir::Value) {
+        match operator {
+            // 結果を置くレジスタで直接計算する。ただし右辺がそのレジスタにあれば上書きしないようraxを使う
+            BinaryOperator::Add
+            | BinaryOperator::Sub
+            | BinaryOperator::Mul
+            | BinaryOperator::And
+            | BinaryOperator::Or
+            | BinaryOperator::Xor => {
+                let register = match (self.destination(dst), self.location(y)) {
+                    (register, Location::Register(r)) if r == register => Register::Rax,
+                    (register, _) => register,
+                };
+                self.load_value(register, x);
+                let y = self.operand(y, Register::Rdi);
+                let instruction = match operator {
+                    BinaryOperator::Add => Instruction::Add(register, y),
+                    BinaryOperator::Sub => Instruction::Sub(register, y),
+                    BinaryOperator::Mul => Instruction::Imul(register, y),
+                    BinaryOperator::And => Instruction::And(register, y),
+                    BinaryOperator::Or => Instruction::Or(register, y),
+                    _ => Instruction::Xor(register, y),
+                };
+                self.instruction(instruction);
+                self.store_value(dst, register);
             },
-            &Primary::Identifier(name) => {
-                match self.scope.lookup(&name) {
-                    Ok(mut lookup_lines) => {
-                        lines.append(&mut lookup_lines);
-                        self.stack_depth += 8;
-                    },
-                    Err(span) => {
-                        self.stack_depth = before_stack_depth;
-                        return Err((span, String::from("未定義のシンボルです。")))
-                    },
+            // 商はrax、余りはrdxに入る
+            BinaryOperator::Div | BinaryOperator::Mod => {
+                self.load_value(Register::Rax, x);
+                self.load_value(Register::Rdi, y);
+                self.instruction(Instruction::Cqo);
+                self.instruction(Instruction::Idiv(Register::Rdi));
+                match operator {
+                    BinaryOperator::Div => self.store_value(dst, Register::Rax),
+                    _ => self.store_value(dst, Register::Rdx),
                 }
             },
-            &Primary::Expression(expression) => {
-                match self.compile_expression(&expression) {
-                    Ok(mut expression_lines) => lines.append(&mut expression_lines),
-                    Err(e) => {
-                        self.stack_depth = before_stack_depth;
-                        return Err(e)
+            // シフト量はclで渡す
+            BinaryOperator::Shl | BinaryOperator::Sar => {
+                self.load_value(Register::Rax, x);
+                self.load_value(Register::Rcx, y);
+                let shift = match operator {
+                    BinaryOperator::Shl => Instruction::Shl(Register::Rax, Readable::Register(Register::Cl)),
+                    _ => Instruction::Sar(Register::Rax, Readable::Register(Register::Cl)),
+                };
+                self.instruction(shift);
+                self.store_value(dst, Register::Rax);
+            },
+            BinaryOperator::Eq
+            | BinaryOperator::Ne
+            | BinaryOperator::Lt
+            | BinaryOperator::Le
+            | BinaryOperator::Gt
+            | BinaryOperator::Ge => {
+                self.load_value(Register::Rax, x);
+                let y = self.operand(y, Register::Rdi);
+                self.instruction(Instruction::Cmp(Register::Rax, y));
+                let setx = match operator {
+                    BinaryOperator::Eq => Instruction::Sete(Register::Al),
+                    BinaryOperator::Ne => Instruction::Setne(Register::Al),
+                    BinaryOperator::Lt => Instruction::Setl(Register::Al),
+                    BinaryOperator::Le => Instruction::Setle(Register::Al),
+                    BinaryOperator::Gt => Instruction::Setg(Register::Al),
+                    _ => Instruction::Setge(Register::Al),
+                };
+                self.instruction(setx);
+                self.instruction(Instruction::Movzb(Register::Rax, Readable::Register(Register::Al)));
+                self.store_value(dst, Register::Rax);
+            },
+        }
+    }
+
+    // スタックに渡す引数は後ろから積み、7個目がrspの位置に来るようにする。
+    // 割り当てに使うレジスタは引数のレジスタと重ならないので、順に移してよい
+    fn compile_call(&mut self, name: &String, args: &[ir::Value]) {
+        let registers = Register::fn_args();
+        let stack_args = args.len().saturating_sub(registers.len()) as i64;
+        let padding = if stack_args % 2 == 0 { 0 } else { 8 };
+        self.instruction(Instruction::Sub(Register::Rsp, Readable::Literal(padding)));
+        for &arg in args.iter().skip(registers.len()).rev() {
+            let readable = match self.location(arg) {
+                Location::Register(r) => Readable::Register(r),
+                Location::Constant(n) => Readable::Literal(n),
+                _ => {
+                    self.load_value(Register::Rax, arg);
+                    Readable::Register(Register::Rax)
+                },
+            };
+            self.instruction(Instruction::Push(readable));
+        }
+        for (&reg, &arg) in registers.iter().zip(args.iter()) {
+            self.load_value(reg, arg);
+        }
+        // 可変長引数の関数のため、ベクタレジスタで渡す引数の個数としてalを0にしておく
+        self.instruction(Instruction::Mov(Writable::Register(Register::Rax), Readable::Literal(0)));
+        self.instruction(Instruction::Call(Self::func_label(name)));
+        self.instruction(Instruction::Add(Register::Rsp, Readable::Literal(padding + 8 * stack_args)));
+    }
+
+    fn compile_terminator(&mut self, block: ir::BlockId, terminator: &ir::Terminator) {
+        match terminator {
+            ir::Terminator::Jump(target) => self.compile_jump(target),
+            ir::Terminator::Branch(condition, then, else_) => {
+                let register = match self.location(*condition) {
+                    Location::Register(r) => r,
+                    _ => {
+                        self.load_value(Register::Rax, *condition);
+                        Register::Rax
                     },
+                };
+                self.instruction(Instruction::Cmp(register, Readable::Literal(0)));
+                if else_.args.is_empty() {
+                    let else_label = self.block_label(else_.block);
+                    self.instruction(Instruction::Je(else_label));
+                    self.compile_jump(then);
+                } else {
+                    let else_label = self.else_label(block);
+                    self.instruction(Instruction::Je(else_label.clone()));
+                    self.compile_jump(then);
+                    self.lines.push(Line::Label(else_label));
+                    self.compile_jump(else_);
                 }
             },
-            &Primary::FnCall(fn_call) => {
-                match self.compile_fn_call(&fn_call) {
-                    Ok(mut fn_call_lines) => lines.append(&mut fn_call_lines),
-                    Err(e) => {
-                        self.stack_depth = before_stack_depth;
-                        return Err(e)
-                    }
-                }
+            ir::Terminator::Return(value) => {
+                self.load_value(Register::Rax, *value);
+                let return_label = self.return_label();
+                self.instruction(Instruction::Jmp(return_label));
+            },
+        }
+    }
+
+    // 飛び先のブロック引数に値を移してから飛ぶ。
+    // 複数の値は互いに上書きしないよう、いったんすべてスタックに積む
+    fn compile_jump(&mut self, target: &ir::Target) {
+        let params = self.params[target.block.0].clone();
+        if let ([param], [arg]) = (params.as_slice(), target.args.as_slice()) {
+            let register = self.destination(*param);
+            self.load_value(register, *arg);
+            self.store_value(*param, register);
+        } else {
+            for &arg in &target.args {
+                self.load_value(Register::Rax, arg);
+                self.instruction(Instruction::Push(Readable::Register(Register::Rax)));
+            }
+            for &param in params.iter().rev() {
+                self.instruction(Instruction::Pop(Register::Rax));
+                self.store_value(param, Register::Rax);
             }
         }
-        Ok(lines)
+        let label = self.block_label(target.block);
+        self.instruction(Instruction::Jmp(label));
     }
 
-    fn compile_fn_call(&mut self, fn_call: &FnCall) -> Result<Vec<Line>, (Span, String)> {
-        let before_stack_depth = self.stack_depth;
+    // rsiの指す領域からraxの指す領域へsizeバイトをコピーする
+    fn copy(size: i64) -> Vec<Line> {
         let mut lines = Vec::new();
-        for arg in fn_call.args.iter() {
-            match self.compile_expression(arg) {
-                Ok(mut arg_lines) => {
-                    lines.append(&mut arg_lines);
-                },
-                Err(e) => {
-                    self.stack_depth = before_stack_depth;
-                    return Err(e)
-                }
-            }
+        let mut offset = 0;
+        while offset + 8 <= size {
+            lines.push(Line::Instruction(Instruction::Mov(Writable::Register(Register::Rdi), Readable::Address(Address::new(Register::Rsi).offset(offset)))));
+            lines.push(Line::Instruction(Instruction::Mov(Writable::Address(Address::new(Register::Rax).offset(offset)), Readable::Register(Register::Rdi))));
+            offset += 8;
         }
-        for &reg in Register::fn_args().iter().take(fn_call.args.len()).rev() {
-                lines.push(Line::Instruction(Instruction::Pop(reg)));
-                self.stack_depth -= 8;
+        while offset < size {
+            lines.push(Line::Instruction(Instruction::Movzb(Register::Rdi, Readable::Byte(Address::new(Register::Rsi).offset(offset)))));
+            lines.push(Line::Instruction(Instruction::Mov(Writable::Byte(Address::new(Register::Rax).offset(offset)), Readable::Register(Register::Dil))));
+            offset += 1;
         }
-        let padding = (16 - self.stack_depth % 16) % 16;
-        lines.push(Line::Instruction(Instruction::Sub(Register::Rsp, Readable::Literal(padding))));
-        self.stack_depth += padding;
-
-        let fn_label = Self::func_label(&fn_call.func.value);
-        lines.push(Line::Instruction(Instruction::Call(fn_label)));
-
-        lines.push(Line::Instruction(Instruction::Add(Register::Rsp, Readable::Literal(padding))));
-        self.stack_depth -= padding;
+        lines
+    }
 
-        lines.push(Line::Instruction(Instruction::Push(Readable::Register(Register::Rax))));
-        self.stack_depth += 8;
-        Ok(lines)
+    // 1バイトで読むときは符号なしで拡張する
+    fn load(width: Width, register: Register, address: Address) -> Instruction {
+        match width {
+            Width::Byte => Instruction::Movzb(register, Readable::Byte(address)),
+            Width::Quad => Instruction::Mov(Writable::Register(register), Readable::Address(address)),
+        }
     }
 
+    // 1バイトで書くときはdilを使う
+    fn store(width: Width, address: Address, register: Register) -> Instruction {
+        match width {
+            Width::Byte => Instruction::Mov(Writable::Byte(address), Readable::Register(Register::Dil)),
+            Width::Quad => Instruction::Mov(Writable::Address(address), Readable::Register(register)),
+        }
+    }
 }
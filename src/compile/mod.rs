@@ -1,5 +1,7 @@
 mod assembly;
 mod compiler;
-mod scope;
+mod allocator;
+mod peephole;
 
-pub use self::compiler::Compiler;
\ No newline at end of file
+pub use self::compiler::Compiler;
+pub use self::peephole::Peephole;
@@ -0,0 +1,254 @@
+use super::assembly::Instruction;
+use super::assembly::Line;
+use super::assembly::Readable;
+use super::assembly::Register;
+use super::assembly::Writable;
+
+// のぞき穴最適化の規則。
+// 命令列の先頭が当てはまれば、置き換える行数と置き換え後の行を返す
+pub trait Rule {
+    fn rewrite(&self, lines: &[Line]) -> Option<(usize, Vec<Line>)>;
+}
+
+// 連続する数行だけを見て、無駄な命令を除いたり短い命令に置き換えたりする
+pub struct Peephole {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Peephole {
+    pub fn new(rules: Vec<Box<dyn Rule>>) -> Self {
+        Self{rules}
+    }
+
+    pub fn standard() -> Self {
+        Self::new(vec![
+            Box::new(ZeroStackAdjust),
+            Box::new(SelfMove),
+            Box::new(PushPop),
+            Box::new(StoreLoad),
+            Box::new(JumpToNext),
+        ])
+    }
+
+    // 書き換えられるところがなくなるまで繰り返す。
+    // 規則はどれも行を減らすか、二度と当てはまらない形にするので必ず止まる
+    pub fn optimize(&self, lines: &[Line]) -> Vec<Line> {
+        let mut lines = lines.to_vec();
+        loop {
+            let mut changed = false;
+            let mut optimized = Vec::new();
+            let mut index = 0;
+            while index < lines.len() {
+                match self.rules.iter().filter_map(|rule| rule.rewrite(&lines[index..])).next() {
+                    Some((count, mut replacement)) => {
+                        optimized.append(&mut replacement);
+                        index += count;
+                        changed = true;
+                    },
+                    None => {
+                        optimized.push(lines[index].clone());
+                        index += 1;
+                    },
+                }
+            }
+            lines = optimized;
+            if !changed {
+                return lines;
+            }
+        }
+    }
+}
+
+// sub rsp, 0 と add rsp, 0 を除く
+pub struct ZeroStackAdjust;
+
+impl Rule for ZeroStackAdjust {
+    fn rewrite(&self, lines: &[Line]) -> Option<(usize, Vec<Line>)> {
+        match lines.first() {
+            Some(Line::Instruction(Instruction::Sub(Register::Rsp, Readable::Literal(0))))
+            | Some(Line::Instruction(Instruction::Add(Register::Rsp, Readable::Literal(0)))) => Some((1, Vec::new())),
+            _ => None,
+        }
+    }
+}
+
+// mov rax, rax のような同じレジスタへのmovを除く
+pub struct SelfMove;
+
+impl Rule for SelfMove {
+    fn rewrite(&self, lines: &[Line]) -> Option<(usize, Vec<Line>)> {
+        match lines.first() {
+            Some(Line::Instruction(Instruction::Mov(Writable::Register(to), Readable::Register(from)))) if to == from => Some((1, Vec::new())),
+            _ => None,
+        }
+    }
+}
+
+// push x の直後の pop r は mov r, x にする
+pub struct PushPop;
+
+impl Rule for PushPop {
+    fn rewrite(&self, lines: &[Line]) -> Option<(usize, Vec<Line>)> {
+        match lines {
+            [Line::Instruction(Instruction::Push(Readable::Register(from))), Line::Instruction(Instruction::Pop(to)), ..] if from == to => Some((2, Vec::new())),
+            [Line::Instruction(Instruction::Push(from)), Line::Instruction(Instruction::Pop(to)), ..] => {
+                Some((2, vec![Line::Instruction(Instruction::Mov(Writable::Register(*to), from.clone()))]))
+            },
+            _ => None,
+        }
+    }
+}
+
+// 書き込んだ直後に同じ場所から読むなら、書き込んだレジスタから移す
+pub struct StoreLoad;
+
+impl Rule for StoreLoad {
+    fn rewrite(&self, lines: &[Line]) -> Option<(usize, Vec<Line>)> {
+        match lines {
+            [store @ Line::Instruction(Instruction::Mov(Writable::Address(stored_at), Readable::Register(from))),
+             Line::Instruction(Instruction::Mov(Writable::Register(to), Readable::Address(loaded_from))), ..] if stored_at == loaded_from => {
+                if to == from {
+                    return Some((2, vec![store.clone()]));
+                }
+                Some((2, vec![store.clone(), Line::Instruction(Instruction::Mov(Writable::Register(*to), Readable::Register(*from)))]))
+            },
+            _ => None,
+        }
+    }
+}
+
+// 直後のラベルへのjmpを除く
+pub struct JumpToNext;
+
+impl Rule for JumpToNext {
+    fn rewrite(&self, lines: &[Line]) -> Option<(usize, Vec<Line>)> {
+        match lines {
+            [Line::Instruction(Instruction::Jmp(target)), label @ Line::Label(next), ..] if target == next => Some((2, vec![label.clone()])),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use super::super::assembly::Address;
+    use super::super::assembly::Label;
+    use super::super::Compiler;
+
+    use ir::lower;
+
+    fn compile(src: &str) -> Compiler {
+        Compiler::compile(&lower(src))
+    }
+
+    fn count_instructions(compiler: &Compiler) -> usize {
+        compiler.func_compilers.iter()
+            .flat_map(|func_compiler| func_compiler.lines.iter())
+            .filter(|line| matches!(line, Line::Instruction(_)))
+            .count()
+    }
+
+    fn instruction(instruction: Instruction) -> Line {
+        Line::Instruction(instruction)
+    }
+
+    fn label(name: &str) -> Line {
+        Line::Label(Label{name: name.to_string()})
+    }
+
+    #[test]
+    fn test_zero_stack_adjust() {
+        let lines = vec![
+            instruction(Instruction::Sub(Register::Rsp, Readable::Literal(0))),
+            instruction(Instruction::Sub(Register::Rsp, Readable::Literal(8))),
+            instruction(Instruction::Add(Register::Rsp, Readable::Literal(0))),
+        ];
+
+        assert_eq!(Peephole::standard().optimize(&lines), vec![
+            instruction(Instruction::Sub(Register::Rsp, Readable::Literal(8))),
+        ]);
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let lines = vec![
+            instruction(Instruction::Push(Readable::Register(Register::Rax))),
+            instruction(Instruction::Push(Readable::Register(Register::R10))),
+            instruction(Instruction::Pop(Register::Rdi)),
+            instruction(Instruction::Pop(Register::Rax)),
+        ];
+
+        // 隣り合った組だけを置き換える
+        assert_eq!(Peephole::standard().optimize(&lines), vec![
+            instruction(Instruction::Push(Readable::Register(Register::Rax))),
+            instruction(Instruction::Mov(Writable::Register(Register::Rdi), Readable::Register(Register::R10))),
+            instruction(Instruction::Pop(Register::Rax)),
+        ]);
+        // 同じレジスタに戻すだけなら両方除く
+        assert_eq!(Peephole::standard().optimize(&[lines[0].clone(), lines[3].clone()]), Vec::new());
+    }
+
+    #[test]
+    fn test_store_load() {
+        let address = Address::new(Register::Rbp).offset(-8);
+        let lines = vec![
+            instruction(Instruction::Mov(Writable::Address(address.clone()), Readable::Register(Register::R10))),
+            instruction(Instruction::Mov(Writable::Register(Register::Rax), Readable::Address(address.clone()))),
+            instruction(Instruction::Mov(Writable::Address(address.clone()), Readable::Register(Register::Rax))),
+            instruction(Instruction::Mov(Writable::Register(Register::Rax), Readable::Address(address.clone()))),
+        ];
+
+        assert_eq!(Peephole::standard().optimize(&lines), vec![
+            instruction(Instruction::Mov(Writable::Address(address.clone()), Readable::Register(Register::R10))),
+            instruction(Instruction::Mov(Writable::Register(Register::Rax), Readable::Register(Register::R10))),
+            instruction(Instruction::Mov(Writable::Address(address), Readable::Register(Register::Rax))),
+        ]);
+    }
+
+    #[test]
+    fn test_jump_to_next() {
+        let lines = vec![
+            instruction(Instruction::Jmp(Label{name: ".L1".to_string()})),
+            label(".L1"),
+            instruction(Instruction::Jmp(Label{name: ".L3".to_string()})),
+            label(".L2"),
+        ];
+
+        assert_eq!(Peephole::standard().optimize(&lines), vec![
+            label(".L1"),
+            instruction(Instruction::Jmp(Label{name: ".L3".to_string()})),
+            label(".L2"),
+        ]);
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let lines = vec![
+            instruction(Instruction::Sub(Register::Rsp, Readable::Literal(0))),
+            instruction(Instruction::Mov(Writable::Register(Register::Rax), Readable::Register(Register::Rax))),
+        ];
+
+        // 与えた規則だけを使う
+        assert_eq!(Peephole::new(vec![Box::new(SelfMove)]).optimize(&lines), vec![
+            instruction(Instruction::Sub(Register::Rsp, Readable::Literal(0))),
+        ]);
+        assert_eq!(Peephole::new(Vec::new()).optimize(&lines), lines);
+    }
+
+    #[test]
+    fn test_optimize_program() {
+        let mut compiler = compile("func id(x) x
+            func main() {
+                let a := id(1)
+                let b := id(2)
+                if a < b then a + b else 0
+            }");
+        assert_eq!(count_instructions(&compiler), 53);
+
+        compiler.optimize(&Peephole::standard());
+
+        assert_eq!(count_instructions(&compiler), 44);
+    }
+}
@@ -0,0 +1,10 @@
+// 診断の種類ごとの番号。Eはエラー、Wは警告
+pub const LEXICAL: &str = "E0001";
+pub const SYNTAX: &str = "E0002";
+pub const TYPE: &str = "E0100";
+pub const UNDEFINED_SYMBOL: &str = "E0101";
+pub const DUPLICATE_DEFINITION: &str = "E0102";
+pub const STRUCT_BY_VALUE: &str = "E0103";
+pub const CONSTANT: &str = "E0200";
+pub const COMPILE: &str = "E0300";
+pub const UNREACHABLE: &str = "W0001";
@@ -0,0 +1,292 @@
+// 利用者に見せるメッセージの一覧。種類ごとに日本語と英語の文面を持つ
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Lang {
+    Ja,
+    En,
+}
+
+impl Lang {
+    // "ja"、"en" のほか、環境変数LANGの "en_US.UTF-8" のような形も受け付ける
+    pub fn from_name(name: &str) -> Option<Lang> {
+        let language = name.split(['_', '.', '-']).next().unwrap_or("");
+        match language {
+            "ja" => Some(Lang::Ja),
+            "en" => Some(Lang::En),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Message {
+    // 字句
+    UnterminatedComment,
+    UnterminatedQuote,
+    InvalidCharacterLiteral,
+    UnknownToken,
+    IntegerTooLarge,
+    MissingDigits,
+    InvalidDigit(char),
+    // 構文
+    Expected(&'static str),
+    ExpectedIdentifier,
+    ExpectedFieldName,
+    ExpectedType,
+    ExpectedArrayLength,
+    ExpectedLineBreak,
+    ExpectedAssignmentOperator,
+    ExpectedGlobal,
+    ExpectedBlock,
+    ExpectedPrimary,
+    ExpectedExpression,
+    ConstantWithoutValue,
+    Unreachable,
+    ReturnsHere,
+    // 型
+    UndefinedSymbol,
+    UndefinedType,
+    UndefinedStruct,
+    AssignToUndefined,
+    BuiltinTypeName,
+    DuplicateType,
+    DuplicateField,
+    DuplicateFunction,
+    DuplicateGlobal,
+    DuplicateFieldValue,
+    FirstDefinition,
+    RecursiveStruct,
+    StructByValueArgument,
+    StructByValueReturn,
+    UsePointer,
+    FunctionNotDeclared,
+    MissingTypeAnnotation,
+    ArrayLengthNotPositive,
+    Redeclared,
+    AssignToConstant,
+    AssignToArray,
+    NotAssignable,
+    NotAddressable,
+    AddressOfConstant,
+    StructNotComparable,
+    TypeMismatch(String, String),
+    NotComparable(String, String),
+    CannotAdd(String, String),
+    CannotSubtract(String, String),
+    ExpectedPointer(String),
+    ExpectedStruct(String),
+    ExpectedArrayOrPointer(String),
+    NoSuchField(String, String),
+    MissingFieldValue(String),
+    WrongArgumentCount(usize, usize),
+    // コード生成
+    NoField,
+    DuplicateArgument,
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    NotConstantExpression,
+    NotConstant,
+    DivisionByZero,
+    // コマンドライン
+    Usage,
+    MissingOutputPath,
+    UnknownOption(String),
+    UnknownLanguage(String),
+    UnknownPass(String),
+    PrintAfterAssemblyPass(String),
+    MultipleInputs,
+    MissingInput,
+    CannotRead(String),
+    CannotWrite(String, String),
+}
+
+impl Message {
+    pub fn text(&self, lang: Lang) -> String {
+        match lang {
+            Lang::Ja => self.ja(),
+            Lang::En => self.en(),
+        }
+    }
+
+    fn ja(&self) -> String {
+        match self {
+            Message::UnterminatedComment => "コメントが閉じられていません".to_string(),
+            Message::UnterminatedQuote => "引用符が正しく閉じられていません".to_string(),
+            Message::InvalidCharacterLiteral => "文字リテラルは1バイトの文字でなければなりません".to_string(),
+            Message::UnknownToken => "トークナイズできません".to_string(),
+            Message::IntegerTooLarge => "整数が大きすぎます".to_string(),
+            Message::MissingDigits => "数字がありません".to_string(),
+            Message::InvalidDigit(c) => format!("数値リテラルに使えない文字'{}'があります", c),
+            Message::Expected(token) => format!("{}を期待していました", token),
+            Message::ExpectedIdentifier => "識別子を期待していました".to_string(),
+            Message::ExpectedFieldName => "フィールド名を期待していました".to_string(),
+            Message::ExpectedType => "型を期待していました".to_string(),
+            Message::ExpectedArrayLength => "配列の長さを期待していました".to_string(),
+            Message::ExpectedLineBreak => "改行を期待していました".to_string(),
+            Message::ExpectedAssignmentOperator => "代入演算子を期待していました".to_string(),
+            Message::ExpectedGlobal => "letまたはconstを期待していました".to_string(),
+            Message::ExpectedBlock => "ブロックを期待していました".to_string(),
+            Message::ExpectedPrimary => "数字または識別子または\"(\"を期待しています。".to_string(),
+            Message::ExpectedExpression => "式を期待していましたが、トークンがありませんでした。".to_string(),
+            Message::ConstantWithoutValue => "定数には初期値が必要です。".to_string(),
+            Message::Unreachable => "到達しないコードです。".to_string(),
+            Message::ReturnsHere => "ここで関数から戻ります".to_string(),
+            Message::UndefinedSymbol => "未定義のシンボルです。".to_string(),
+            Message::UndefinedType => "未定義の型です。".to_string(),
+            Message::UndefinedStruct => "未定義の構造体です。".to_string(),
+            Message::AssignToUndefined => "未定義の変数に代入しようとしています。".to_string(),
+            Message::BuiltinTypeName => "組み込みの型と同じ名前です。".to_string(),
+            Message::DuplicateType => "型が重複して定義されています。".to_string(),
+            Message::DuplicateField => "フィールドが重複して定義されています。".to_string(),
+            Message::DuplicateFunction => "関数が重複して定義されています。".to_string(),
+            Message::DuplicateGlobal => "グローバル変数が重複して定義されています。".to_string(),
+            Message::DuplicateFieldValue => "フィールドが重複しています。".to_string(),
+            Message::FirstDefinition => "最初の定義".to_string(),
+            Message::RecursiveStruct => "構造体が自分自身を値として含んでいます。".to_string(),
+            Message::StructByValueArgument => "構造体は値渡しできません。".to_string(),
+            Message::StructByValueReturn => "構造体は値で返せません。".to_string(),
+            Message::UsePointer => "ポインタを使ってください。".to_string(),
+            Message::FunctionNotDeclared => "関数が宣言されていません。".to_string(),
+            Message::MissingTypeAnnotation => "初期値のない変数には型注釈が必要です。".to_string(),
+            Message::ArrayLengthNotPositive => "配列の長さは1以上でなければなりません。".to_string(),
+            Message::Redeclared => "同じブロックで変数が再宣言されています。".to_string(),
+            Message::AssignToConstant => "定数には代入できません。".to_string(),
+            Message::AssignToArray => "配列には代入できません。".to_string(),
+            Message::NotAssignable => "代入できない式です。".to_string(),
+            Message::NotAddressable => "アドレスを取れない式です。".to_string(),
+            Message::AddressOfConstant => "定数のアドレスは取れません。".to_string(),
+            Message::StructNotComparable => "構造体は比較できません。".to_string(),
+            Message::TypeMismatch(expected, actual) => format!("{}型を期待していましたが、{}型です。", expected, actual),
+            Message::NotComparable(left, right) => format!("{}型と{}型は比較できません。", left, right),
+            Message::CannotAdd(left, right) => format!("{}型と{}型の加算はできません。", left, right),
+            Message::CannotSubtract(left, right) => format!("{}型と{}型の減算はできません。", left, right),
+            Message::ExpectedPointer(actual) => format!("ポインタ型を期待していましたが、{}型です。", actual),
+            Message::ExpectedStruct(actual) => format!("構造体か構造体へのポインタを期待していましたが、{}型です。", actual),
+            Message::ExpectedArrayOrPointer(actual) => format!("配列かポインタを期待していましたが、{}型です。", actual),
+            Message::NoSuchField(name, field) => format!("{}にフィールド{}はありません。", name, field),
+            Message::MissingFieldValue(field) => format!("フィールド{}の値がありません。", field),
+            Message::WrongArgumentCount(expected, actual) => format!(
+                "引数の個数が正しくありません: {}個を期待していましたが、{}個です。", expected, actual,
+            ),
+            Message::NoField => "フィールドがありません。".to_string(),
+            Message::DuplicateArgument => "引数が重複して定義されています。".to_string(),
+            Message::BreakOutsideLoop => "ループの外でbreakしています。".to_string(),
+            Message::ContinueOutsideLoop => "ループの外でcontinueしています。".to_string(),
+            Message::NotConstantExpression => "定数式ではありません。".to_string(),
+            Message::NotConstant => "定数ではないため、初期値に使えません。".to_string(),
+            Message::DivisionByZero => "0で割っています。".to_string(),
+            Message::Usage => "使い方: rust-cc [-o 出力ファイル] [-O0|-O1|-O2] [--passes=最適化,...] [--print-after=最適化] [--time-passes] [--error-format=human|json] [--emit=asm|ir] [--lang=ja|en] <入力ファイル | ->".to_string(),
+            Message::MissingOutputPath => "-o の後に出力ファイルを指定してください".to_string(),
+            Message::UnknownOption(flag) => format!("不明なオプションです: {}", flag),
+            Message::UnknownLanguage(lang) => format!("対応していない言語です: {}", lang),
+            Message::UnknownPass(name) => format!("不明な最適化です: {}", name),
+            Message::PrintAfterAssemblyPass(name) => format!("--emit=ir ではアセンブリにかける最適化のあとは書き出せません: {}", name),
+            Message::MultipleInputs => "入力ファイルは1つだけ指定してください".to_string(),
+            Message::MissingInput => "入力ファイルを指定してください".to_string(),
+            Message::CannotRead(e) => format!("入力を読み込めません: {}", e),
+            Message::CannotWrite(path, e) => format!("{} に書き込めません: {}", path, e),
+        }
+    }
+
+    fn en(&self) -> String {
+        match self {
+            Message::UnterminatedComment => "unterminated block comment".to_string(),
+            Message::UnterminatedQuote => "unterminated quote".to_string(),
+            Message::InvalidCharacterLiteral => "character literal must be a single byte".to_string(),
+            Message::UnknownToken => "unrecognized token".to_string(),
+            Message::IntegerTooLarge => "integer literal is too large".to_string(),
+            Message::MissingDigits => "missing digits".to_string(),
+            Message::InvalidDigit(c) => format!("invalid character '{}' in number literal", c),
+            Message::Expected(token) => format!("expected {}", token),
+            Message::ExpectedIdentifier => "expected an identifier".to_string(),
+            Message::ExpectedFieldName => "expected a field name".to_string(),
+            Message::ExpectedType => "expected a type".to_string(),
+            Message::ExpectedArrayLength => "expected an array length".to_string(),
+            Message::ExpectedLineBreak => "expected a line break".to_string(),
+            Message::ExpectedAssignmentOperator => "expected an assignment operator".to_string(),
+            Message::ExpectedGlobal => "expected let or const".to_string(),
+            Message::ExpectedBlock => "expected a block".to_string(),
+            Message::ExpectedPrimary => "expected a number, an identifier or \"(\".".to_string(),
+            Message::ExpectedExpression => "expected an expression, but found no tokens.".to_string(),
+            Message::ConstantWithoutValue => "a constant needs an initial value.".to_string(),
+            Message::Unreachable => "unreachable code.".to_string(),
+            Message::ReturnsHere => "the function returns here".to_string(),
+            Message::UndefinedSymbol => "undefined symbol.".to_string(),
+            Message::UndefinedType => "undefined type.".to_string(),
+            Message::UndefinedStruct => "undefined struct.".to_string(),
+            Message::AssignToUndefined => "assignment to an undefined variable.".to_string(),
+            Message::BuiltinTypeName => "the name is already used by a built-in type.".to_string(),
+            Message::DuplicateType => "the type is defined more than once.".to_string(),
+            Message::DuplicateField => "the field is defined more than once.".to_string(),
+            Message::DuplicateFunction => "the function is defined more than once.".to_string(),
+            Message::DuplicateGlobal => "the global variable is defined more than once.".to_string(),
+            Message::DuplicateFieldValue => "the field is given more than once.".to_string(),
+            Message::FirstDefinition => "first defined here".to_string(),
+            Message::RecursiveStruct => "the struct contains itself by value.".to_string(),
+            Message::StructByValueArgument => "structs cannot be passed by value.".to_string(),
+            Message::StructByValueReturn => "structs cannot be returned by value.".to_string(),
+            Message::UsePointer => "use a pointer instead.".to_string(),
+            Message::FunctionNotDeclared => "the function is not declared.".to_string(),
+            Message::MissingTypeAnnotation => "a variable without an initial value needs a type annotation.".to_string(),
+            Message::ArrayLengthNotPositive => "array length must be at least 1.".to_string(),
+            Message::Redeclared => "the variable is already declared in this block.".to_string(),
+            Message::AssignToConstant => "cannot assign to a constant.".to_string(),
+            Message::AssignToArray => "cannot assign to an array.".to_string(),
+            Message::NotAssignable => "the expression cannot be assigned to.".to_string(),
+            Message::NotAddressable => "cannot take the address of the expression.".to_string(),
+            Message::AddressOfConstant => "cannot take the address of a constant.".to_string(),
+            Message::StructNotComparable => "structs cannot be compared.".to_string(),
+            Message::TypeMismatch(expected, actual) => format!("expected type {}, but found {}.", expected, actual),
+            Message::NotComparable(left, right) => format!("cannot compare {} with {}.", left, right),
+            Message::CannotAdd(left, right) => format!("cannot add {} and {}.", left, right),
+            Message::CannotSubtract(left, right) => format!("cannot subtract {} from {}.", right, left),
+            Message::ExpectedPointer(actual) => format!("expected a pointer type, but found {}.", actual),
+            Message::ExpectedStruct(actual) => format!("expected a struct or a pointer to a struct, but found {}.", actual),
+            Message::ExpectedArrayOrPointer(actual) => format!("expected an array or a pointer, but found {}.", actual),
+            Message::NoSuchField(name, field) => format!("{} has no field {}.", name, field),
+            Message::MissingFieldValue(field) => format!("missing value for field {}.", field),
+            Message::WrongArgumentCount(expected, actual) => format!(
+                "wrong number of arguments: expected {}, but found {}.", expected, actual,
+            ),
+            Message::NoField => "no such field.".to_string(),
+            Message::DuplicateArgument => "the argument is defined more than once.".to_string(),
+            Message::BreakOutsideLoop => "break outside of a loop.".to_string(),
+            Message::ContinueOutsideLoop => "continue outside of a loop.".to_string(),
+            Message::NotConstantExpression => "not a constant expression.".to_string(),
+            Message::NotConstant => "not a constant, so it cannot be used in an initial value.".to_string(),
+            Message::DivisionByZero => "division by zero.".to_string(),
+            Message::Usage => "usage: rust-cc [-o output] [-O0|-O1|-O2] [--passes=pass,...] [--print-after=pass] [--time-passes] [--error-format=human|json] [--emit=asm|ir] [--lang=ja|en] <input | ->".to_string(),
+            Message::MissingOutputPath => "specify an output file after -o".to_string(),
+            Message::UnknownOption(flag) => format!("unknown option: {}", flag),
+            Message::UnknownLanguage(lang) => format!("unsupported language: {}", lang),
+            Message::UnknownPass(name) => format!("unknown pass: {}", name),
+            Message::PrintAfterAssemblyPass(name) => format!("cannot print after an assembly pass with --emit=ir: {}", name),
+            Message::MultipleInputs => "specify only one input file".to_string(),
+            Message::MissingInput => "specify an input file".to_string(),
+            Message::CannotRead(e) => format!("cannot read the input: {}", e),
+            Message::CannotWrite(path, e) => format!("cannot write to {}: {}", path, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lang_from_name() {
+        assert_eq!(Lang::from_name("ja"), Some(Lang::Ja));
+        assert_eq!(Lang::from_name("en_US.UTF-8"), Some(Lang::En));
+        assert_eq!(Lang::from_name("ja_JP.UTF-8"), Some(Lang::Ja));
+        assert_eq!(Lang::from_name("C.UTF-8"), None);
+        assert_eq!(Lang::from_name(""), None);
+    }
+
+    #[test]
+    fn test_text() {
+        let message = Message::TypeMismatch("int".to_string(), "bool".to_string());
+
+        assert_eq!(message.text(Lang::Ja), "int型を期待していましたが、bool型です。".to_string());
+        assert_eq!(message.text(Lang::En), "expected type int, but found bool.".to_string());
+        assert_eq!(Message::Expected("then").text(Lang::Ja), "thenを期待していました".to_string());
+    }
+}
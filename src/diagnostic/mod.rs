@@ -0,0 +1,13 @@
+mod report;
+pub use self::report::Diagnostic;
+pub use self::report::Severity;
+
+pub mod code;
+
+mod render;
+pub use self::render::render_human;
+pub use self::render::render_json;
+
+mod message;
+pub use self::message::Message;
+pub use self::message::Lang;
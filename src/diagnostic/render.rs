@@ -0,0 +1,140 @@
+use sourcecode::Source;
+use sourcecode::Span;
+
+use super::Diagnostic;
+use super::Severity;
+use super::Lang;
+
+fn severity_name(severity: Severity, lang: Lang) -> &'static str {
+    match (severity, lang) {
+        (Severity::Error, Lang::Ja) => "エラー",
+        (Severity::Warning, Lang::Ja) => "警告",
+        (Severity::Error, Lang::En) => "error",
+        (Severity::Warning, Lang::En) => "warning",
+    }
+}
+
+fn note_name(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Ja => "注",
+        Lang::En => "note",
+    }
+}
+
+// 人が読むための表示。主な位置に^を引いてメッセージを添え、関連する位置と注記を続ける
+pub fn render_human(src: &Source, diagnostic: &Diagnostic, lang: Lang) -> String {
+    let header = format!("{}[{}]", severity_name(diagnostic.severity, lang), diagnostic.code);
+    let message = diagnostic.message.text(lang);
+    let mut parts = vec![match diagnostic.primary {
+        Some(span) => format!("{}: {}\n{}", src.location(span.start), header, src.snippet(span, &message)),
+        None => format!("{}: {}: {}", src.name, header, message),
+    }];
+    for (span, label) in &diagnostic.secondary {
+        parts.push(src.point_span(*span, &label.text(lang)));
+    }
+    for note in &diagnostic.notes {
+        parts.push(format!("{}: {}", note_name(lang), note.text(lang)));
+    }
+    parts.join("\n")
+}
+
+// エディタやCI向けに、1つの診断を1行のJSONで表す。severity は言語によらない
+pub fn render_json(src: &Source, diagnostic: &Diagnostic, lang: Lang) -> String {
+    let severity = match diagnostic.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+    let primary = match diagnostic.primary {
+        Some(span) => json_span(span),
+        None => "null".to_string(),
+    };
+    let secondary: Vec<String> = diagnostic.secondary.iter()
+        .map(|(span, label)| format!("{{\"span\":{},\"label\":{}}}", json_span(*span), json_string(&label.text(lang))))
+        .collect();
+    let notes: Vec<String> = diagnostic.notes.iter().map(|note| json_string(&note.text(lang))).collect();
+    format!(
+        "{{\"code\":{},\"severity\":{},\"message\":{},\"file\":{},\"primary\":{},\"secondary\":[{}],\"notes\":[{}]}}",
+        json_string(diagnostic.code),
+        json_string(severity),
+        json_string(&diagnostic.message.text(lang)),
+        json_string(&src.name),
+        primary,
+        secondary.join(","),
+        notes.join(","),
+    )
+}
+
+// 行と列は1から数え、終わりの列は範囲に含まない
+fn json_span(span: Span) -> String {
+    format!(
+        "{{\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{}}}",
+        span.start.line + 1,
+        span.start.pos + 1,
+        span.end.line + 1,
+        span.end.pos + 1,
+    )
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use diagnostic::code;
+    use diagnostic::Message;
+
+    fn source() -> Source {
+        Source::new("a.src".to_string(), "func f() 1\nfunc f() \"x\"".to_string())
+    }
+
+    #[test]
+    fn test_render_human() {
+        let diagnostic = Diagnostic::error(code::STRUCT_BY_VALUE, Some(Span::new(1, 5, 1)), Message::StructByValueReturn)
+            .with_secondary(Span::new(0, 5, 1), Message::FirstDefinition)
+            .with_note(Message::UsePointer);
+
+        assert_eq!(
+            render_human(&source(), &diagnostic, Lang::Ja),
+            "a.src:2:6: エラー[E0103]\nfunc f() \"x\"\n     ^構造体は値で返せません。\na.src:1:6\nfunc f() 1\n     ^最初の定義\n注: ポインタを使ってください。".to_string(),
+        );
+        assert_eq!(
+            render_human(&source(), &diagnostic, Lang::En),
+            "a.src:2:6: error[E0103]\nfunc f() \"x\"\n     ^structs cannot be returned by value.\na.src:1:6\nfunc f() 1\n     ^first defined here\nnote: use a pointer instead.".to_string(),
+        );
+
+        let diagnostic = Diagnostic::error(code::SYNTAX, None, Message::Expected("}"));
+        assert_eq!(render_human(&source(), &diagnostic, Lang::Ja), "a.src: エラー[E0002]: }を期待していました".to_string());
+    }
+
+    #[test]
+    fn test_render_json() {
+        let diagnostic = Diagnostic::warning(code::UNREACHABLE, Some(Span::new(1, 9, 3)), Message::UnknownOption("\"-x\"\t\\".to_string()))
+            .with_secondary(Span::new(0, 0, 4), Message::ReturnsHere);
+
+        assert_eq!(
+            render_json(&source(), &diagnostic, Lang::En),
+            "{\"code\":\"W0001\",\"severity\":\"warning\",\"message\":\"unknown option: \\\"-x\\\"\\t\\\\\",\"file\":\"a.src\",\
+\"primary\":{\"line\":2,\"column\":10,\"end_line\":2,\"end_column\":13},\
+\"secondary\":[{\"span\":{\"line\":1,\"column\":1,\"end_line\":1,\"end_column\":5},\"label\":\"the function returns here\"}],\"notes\":[]}".to_string(),
+        );
+
+        let diagnostic = Diagnostic::error(code::SYNTAX, None, Message::ExpectedBlock);
+        assert!(render_json(&source(), &diagnostic, Lang::Ja).contains("\"primary\":null"));
+    }
+}
@@ -0,0 +1,54 @@
+use sourcecode::Span;
+
+use super::Message;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+// 位置のわからない誤り(入力の途中で終わったなど)は primary を持たない
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: Message,
+    pub primary: Option<Span>,
+    pub secondary: Vec<(Span, Message)>,
+    pub notes: Vec<Message>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, primary: Option<Span>, message: Message) -> Diagnostic {
+        Diagnostic {
+            code,
+            severity: Severity::Error,
+            message,
+            primary,
+            secondary: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn warning(code: &'static str, primary: Option<Span>, message: Message) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(code, primary, message)
+        }
+    }
+
+    pub fn with_secondary(mut self, span: Span, label: Message) -> Diagnostic {
+        self.secondary.push((span, label));
+        self
+    }
+
+    pub fn with_note(mut self, note: Message) -> Diagnostic {
+        self.notes.push(note);
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
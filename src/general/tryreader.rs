@@ -4,6 +4,15 @@ pub struct TryReader<'l, T> {
     needle: usize,
 }
 
+impl<'l, T> Clone for TryReader<'l, T> {
+    fn clone(&self) -> Self {
+        TryReader {
+            elements: self.elements,
+            needle: self.needle,
+        }
+    }
+}
+
 impl<'l, T> TryReader<'l, T> {
     pub fn new(elements: &'l Vec<T>) -> TryReader<'l, T> {
         TryReader {
@@ -21,6 +30,15 @@ impl<'l, T> TryReader<'l, T> {
         }
     }
 
+    pub fn peek(&self) -> Option<&'l T> {
+        self.elements.get(self.needle)
+    }
+
+    // 先頭から読んだ要素の数
+    pub fn position(&self) -> usize {
+        self.needle
+    }
+
     pub fn has_next(&self) -> bool {
         self.elements.len() != self.needle
     }
@@ -54,6 +72,7 @@ impl<'l, T> TryReader<'l, T> {
         }
     }
 
+    #[allow(clippy::needless_borrow, clippy::redundant_pattern_matching)]
     pub fn drop_while<F>(&mut self, f: F)
     where F: Fn(&T) -> bool {
         while let Ok(_) = self.try_next(|elem| {
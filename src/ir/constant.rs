@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+
+use sourcecode::Span;
+
+use diagnostic::Message;
+
+use token::Operator;
+
+use parse::SyntaxTree;
+use parse::Expression;
+use parse::LogicalOr;
+use parse::LogicalAnd;
+use parse::LogicalNot;
+use parse::BitOr;
+use parse::BitXor;
+use parse::BitAnd;
+use parse::Equality;
+use parse::Relational;
+use parse::Shift;
+use parse::Add;
+use parse::Multiply;
+use parse::Unary;
+use parse::Primary;
+
+// コンパイル時に値の決まる定数。グローバル変数と定数の初期値を評価する
+pub struct Constants {
+    values: HashMap<String, i64>,
+}
+
+impl Constants {
+    pub fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+        }
+    }
+
+    pub fn define(&mut self, name: &str, value: i64) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    // 名前の隠された定数を除く
+    pub fn without<F: Fn(&str) -> bool>(&self, hidden: F) -> Self {
+        Self {
+            values: self.values.iter()
+                .filter(|(name, _)| !hidden(name))
+                .map(|(name, &value)| (name.clone(), value))
+                .collect(),
+        }
+    }
+
+    pub fn evaluate(&self, expression: &Expression) -> Result<i64, (Span, Message)> {
+        match expression {
+            Expression::PureExpression(expr) => self.evaluate_logical_or(&expr.logical_or),
+            Expression::IfExpression(expr) => match self.evaluate(&expr.condition) {
+                Ok(0) => self.evaluate(&expr.else_),
+                Ok(_) => self.evaluate(&expr.then),
+                Err(e) => Err(e),
+            },
+            _ => Err((expression.span(), Message::NotConstantExpression)),
+        }
+    }
+
+    fn evaluate_logical_or(&self, logical_or: &LogicalOr) -> Result<i64, (Span, Message)> {
+        let mut left = self.evaluate_logical_and(logical_or.head())?;
+        for (_, logical_and) in logical_or.tail() {
+            if left != 0 {
+                return Ok(1);
+            }
+            left = match self.evaluate_logical_and(logical_and) {
+                Ok(n) => (n != 0) as i64,
+                Err(e) => return Err(e),
+            };
+        }
+        Ok(left)
+    }
+
+    fn evaluate_logical_and(&self, logical_and: &LogicalAnd) -> Result<i64, (Span, Message)> {
+        let mut left = self.evaluate_logical_not(logical_and.head())?;
+        for (_, logical_not) in logical_and.tail() {
+            if left == 0 {
+                return Ok(0);
+            }
+            left = match self.evaluate_logical_not(logical_not) {
+                Ok(n) => (n != 0) as i64,
+                Err(e) => return Err(e),
+            };
+        }
+        Ok(left)
+    }
+
+    fn evaluate_logical_not(&self, logical_not: &LogicalNot) -> Result<i64, (Span, Message)> {
+        match logical_not {
+            LogicalNot::Not(operand, _) => self.evaluate_logical_not(operand).map(|n| (n == 0) as i64),
+            LogicalNot::BitOr(bit_or) => self.evaluate_bit_or(bit_or),
+        }
+    }
+
+    fn evaluate_bit_or(&self, bit_or: &BitOr) -> Result<i64, (Span, Message)> {
+        let mut left = self.evaluate_bit_xor(bit_or.head())?;
+        for (_, bit_xor) in bit_or.tail() {
+            let right = self.evaluate_bit_xor(bit_xor)?;
+            left |= right;
+        }
+        Ok(left)
+    }
+
+    fn evaluate_bit_xor(&self, bit_xor: &BitXor) -> Result<i64, (Span, Message)> {
+        let mut left = self.evaluate_bit_and(bit_xor.head())?;
+        for (_, bit_and) in bit_xor.tail() {
+            let right = self.evaluate_bit_and(bit_and)?;
+            left ^= right;
+        }
+        Ok(left)
+    }
+
+    fn evaluate_bit_and(&self, bit_and: &BitAnd) -> Result<i64, (Span, Message)> {
+        let mut left = self.evaluate_equality(bit_and.head())?;
+        for (_, equality) in bit_and.tail() {
+            let right = self.evaluate_equality(equality)?;
+            left &= right;
+        }
+        Ok(left)
+    }
+
+    fn evaluate_equality(&self, equality: &Equality) -> Result<i64, (Span, Message)> {
+        let mut left = self.evaluate_relational(equality.head())?;
+        for (operator, relational) in equality.tail() {
+            let right = self.evaluate_relational(relational)?;
+            left = match operator.value {
+                Operator::Equal => (left == right) as i64,
+                _ => (left != right) as i64,
+            };
+        }
+        Ok(left)
+    }
+
+    fn evaluate_relational(&self, relational: &Relational) -> Result<i64, (Span, Message)> {
+        let mut left = self.evaluate_shift(relational.head())?;
+        for (operator, shift) in relational.tail() {
+            let right = self.evaluate_shift(shift)?;
+            left = match operator.value {
+                Operator::Less => (left < right) as i64,
+                Operator::LessEq => (left <= right) as i64,
+                Operator::Greater => (left > right) as i64,
+                _ => (left >= right) as i64,
+            };
+        }
+        Ok(left)
+    }
+
+    // シフト量は x86 と同じく下位6ビットだけを使う
+    fn evaluate_shift(&self, shift: &Shift) -> Result<i64, (Span, Message)> {
+        let mut left = self.evaluate_add(shift.head())?;
+        for (operator, add) in shift.tail() {
+            let right = self.evaluate_add(add)?;
+            left = match operator.value {
+                Operator::ShiftLeft => left.wrapping_shl(right as u32),
+                _ => left.wrapping_shr(right as u32),
+            };
+        }
+        Ok(left)
+    }
+
+    fn evaluate_add(&self, add: &Add) -> Result<i64, (Span, Message)> {
+        let mut left = self.evaluate_multiply(add.head())?;
+        for (operator, multiply) in add.tail() {
+            let right = self.evaluate_multiply(multiply)?;
+            left = match operator.value {
+                Operator::Add => left.wrapping_add(right),
+                _ => left.wrapping_sub(right),
+            };
+        }
+        Ok(left)
+    }
+
+    fn evaluate_multiply(&self, multiply: &Multiply) -> Result<i64, (Span, Message)> {
+        let mut left = self.evaluate_unary(multiply.head())?;
+        for (operator, unary) in multiply.tail() {
+            let right = self.evaluate_unary(unary)?;
+            left = match operator.value {
+                Operator::Mul => left.wrapping_mul(right),
+                _ if right == 0 => return Err((unary.span(), Message::DivisionByZero)),
+                Operator::Mod => left.wrapping_rem(right),
+                _ => left.wrapping_div(right),
+            };
+        }
+        Ok(left)
+    }
+
+    pub fn evaluate_unary(&self, unary: &Unary) -> Result<i64, (Span, Message)> {
+        match unary {
+            Unary::Positive(primary, _) => self.evaluate_primary(primary),
+            Unary::Negative(primary, _) => self.evaluate_primary(primary).map(|n| n.wrapping_neg()),
+            Unary::BitNot(operand, _) => self.evaluate_unary(operand).map(|n| !n),
+            _ => Err((unary.span(), Message::NotConstantExpression)),
+        }
+    }
+
+    fn evaluate_primary(&self, primary: &Primary) -> Result<i64, (Span, Message)> {
+        match primary {
+            Primary::Integer(n) => Ok(n.value),
+            Primary::Boolean(b) => Ok(b.value as i64),
+            Primary::Identifier(name) => self.values.get(&name.value)
+                .cloned()
+                .ok_or((name.span, Message::NotConstant)),
+            Primary::Expression(expression) => self.evaluate(expression),
+            _ => Err((primary.span(), Message::NotConstantExpression)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use parse::TokenReader;
+    use token::tokenize;
+
+    fn evaluate(constants: &Constants, src: &str) -> Result<i64, (Span, Message)> {
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+        let expression = Expression::parse(&mut token_reader).unwrap();
+        constants.evaluate(&expression)
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut constants = Constants::new();
+        constants.define("N", 4);
+
+        assert_eq!(evaluate(&constants, "1 + 2 * 3 - 4 / 2").unwrap(), 5);
+        assert_eq!(evaluate(&constants, "-(N * N)").unwrap(), -16);
+        assert_eq!(evaluate(&constants, "N < 5").unwrap(), 1);
+        assert_eq!(evaluate(&constants, "if N == 4 then 10 else 20").unwrap(), 10);
+        assert_eq!(evaluate(&constants, "N > 3 && !(N == 5)").unwrap(), 1);
+        assert_eq!(evaluate(&constants, "1 << N | 3 ^ 1 & 7").unwrap(), 18);
+        assert_eq!(evaluate(&constants, "~N % 3 + (-16 >> 2)").unwrap(), -6);
+        assert!(evaluate(&constants, "N % (N - 4)").is_err());
+        assert!(evaluate(&constants, "N < 3 || N / 0 == 1").is_err());
+        assert_eq!(evaluate(&constants, "N > 3 || N / 0 == 1").unwrap(), 1);
+        assert!(evaluate(&constants, "M + 1").is_err());
+        assert!(evaluate(&constants, "f(1)").is_err());
+
+        let (span, _) = evaluate(&constants, "N / (N - 4)").err().unwrap();
+        assert_eq!(span, Span::new(0, 5, 5));
+    }
+}
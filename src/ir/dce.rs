@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use super::program::Func;
+use super::program::Instruction;
+use super::program::Program;
+use super::program::Slot;
+use super::program::Value;
+
+// 使われない値を求めるだけの命令と、読まれない領域への書き込みを除く。
+// 関数呼び出しは、値を使わなくても残す
+pub struct DeadCodeEliminator;
+
+impl DeadCodeEliminator {
+    pub fn eliminate(mut program: Program) -> Program {
+        for func in &mut program.funcs {
+            Self::eliminate_func(func);
+        }
+        program
+    }
+
+    // 除いた命令が使っていた値も使われなくなるので、除けなくなるまで繰り返す
+    fn eliminate_func(func: &mut Func) {
+        loop {
+            let used = Self::used_values(func);
+            let mut changed = Self::eliminate_stores(func);
+            for block in &mut func.blocks {
+                let before = block.instructions.len();
+                block.instructions.retain(|instruction| match instruction.result() {
+                    Some(result) => Self::has_side_effect(instruction) || used.contains(&result),
+                    None => true,
+                });
+                changed |= block.instructions.len() != before;
+            }
+            if !changed {
+                return;
+            }
+        }
+    }
+
+    // 書き込み先としてしか使われない領域は読まれることがないので、書き込みを除く
+    fn eliminate_stores(func: &mut Func) -> bool {
+        let mut addresses: HashMap<Value, Slot> = HashMap::new();
+        for instruction in func.blocks.iter().flat_map(|block| block.instructions.iter()) {
+            if let Instruction::SlotAddress(dst, slot) = instruction {
+                addresses.insert(*dst, *slot);
+            }
+        }
+        let mut read: HashSet<Slot> = HashSet::new();
+        for block in &func.blocks {
+            for instruction in &block.instructions {
+                let operands = match instruction {
+                    Instruction::Store(_, _, value) => vec![*value],
+                    _ => instruction.operands(),
+                };
+                read.extend(operands.iter().filter_map(|operand| addresses.get(operand)));
+            }
+            read.extend(block.terminator.operands().iter().filter_map(|operand| addresses.get(operand)));
+        }
+        let mut changed = false;
+        for block in &mut func.blocks {
+            let before = block.instructions.len();
+            block.instructions.retain(|instruction| match instruction {
+                Instruction::Store(_, address, _) => match addresses.get(address) {
+                    Some(slot) => read.contains(slot),
+                    None => true,
+                },
+                _ => true,
+            });
+            changed |= block.instructions.len() != before;
+        }
+        changed
+    }
+
+    fn used_values(func: &Func) -> HashSet<Value> {
+        let mut used = HashSet::new();
+        for block in &func.blocks {
+            for instruction in &block.instructions {
+                used.extend(instruction.operands());
+            }
+            used.extend(block.terminator.operands());
+        }
+        used
+    }
+
+    // 書き込みは値を定義しないので、ここでは呼び出しだけを考えればよい
+    fn has_side_effect(instruction: &Instruction) -> bool {
+        matches!(instruction, Instruction::Call(_, _, _))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ir::lower;
+    use ir::ConstantFolder;
+
+    #[test]
+    fn test_eliminate() {
+        let program = ConstantFolder::fold(lower("func main() 1 + 2 * 3 - 4 / 2"));
+
+        let program = DeadCodeEliminator::eliminate(program);
+
+        assert_eq!(program.funcs[0].code(), "func main() {
+bb0:
+  %8 = const 5
+  ret %8
+}");
+    }
+
+    #[test]
+    fn test_eliminate_keeps_side_effects() {
+        let program = lower("func f() 1
+            func main() {
+                let x := 2
+                f()
+                x + 1
+                0
+            }");
+
+        let program = DeadCodeEliminator::eliminate(program);
+        let instructions = &program.funcs[1].blocks[0].instructions;
+
+        // 使われない読み込みと足し算、読まれない変数への書き込みは除き、呼び出しは残す
+        assert!(!instructions.iter().any(|instruction| matches!(instruction, Instruction::Store(_, _, _))));
+        assert!(instructions.iter().any(|instruction| matches!(instruction, Instruction::Call(_, _, _))));
+        assert!(!instructions.iter().any(|instruction| matches!(instruction, Instruction::Load(_, _, _) | Instruction::Binary(_, _, _, _))));
+    }
+
+    #[test]
+    fn test_eliminate_keeps_escaped_stores() {
+        let program = lower("func set(p: *int) { *p := 1 }
+            func main() {
+                let x := 2
+                set(&x)
+                0
+            }");
+
+        let program = DeadCodeEliminator::eliminate(program);
+
+        // アドレスを渡した変数への書き込みは残す
+        assert!(program.funcs[1].blocks[0].instructions.iter().any(|instruction| matches!(instruction, Instruction::Store(_, _, _))));
+    }
+}
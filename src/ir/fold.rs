@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+use std::mem;
+
+use super::program::BinaryOperator;
+use super::program::Func;
+use super::program::Instruction;
+use super::program::Program;
+use super::program::Slot;
+use super::program::Terminator;
+use super::program::UnaryOperator;
+use super::program::Value;
+use super::program::Width;
+
+// 定数の式をコンパイル時に計算し、x*1やx-xのような式を簡単にする。
+// 条件が定数の分岐は飛び先を1つに決め、通らなくなったブロックを除く
+pub struct ConstantFolder;
+
+impl ConstantFolder {
+    pub fn fold(mut program: Program) -> Program {
+        for func in &mut program.funcs {
+            FuncFolder::fold(func);
+        }
+        program
+    }
+}
+
+enum Simplified {
+    Constant(i64),
+    // ほかの値と同じになる
+    Alias(Value),
+}
+
+struct FuncFolder<'a> {
+    func: &'a mut Func,
+    // 取り除いた値と、代わりに使う値
+    replacements: HashMap<Value, Value>,
+}
+
+impl <'a> FuncFolder<'a> {
+    // 変わるところがなくなるまで繰り返す
+    fn fold(func: &'a mut Func) {
+        let mut folder = Self{func, replacements: HashMap::new()};
+        loop {
+            let mut changed = folder.fold_instructions();
+            changed |= folder.fold_branches();
+            folder.func.remove_unreachable_blocks();
+            changed |= folder.remove_params();
+            changed |= folder.merge_blocks();
+            if !changed {
+                return;
+            }
+        }
+    }
+
+    fn resolve(replacements: &HashMap<Value, Value>, mut value: Value) -> Value {
+        while let Some(&next) = replacements.get(&value) {
+            value = next;
+        }
+        value
+    }
+
+    fn replace(&mut self, from: Value, to: Value) {
+        self.replacements.insert(from, to);
+    }
+
+    fn constants(&self) -> HashMap<Value, i64> {
+        let mut constants = HashMap::new();
+        for instruction in self.func.blocks.iter().flat_map(|block| block.instructions.iter()) {
+            if let Instruction::Const(dst, n) = instruction {
+                constants.insert(*dst, *n);
+            }
+        }
+        constants
+    }
+
+    fn fold_instructions(&mut self) -> bool {
+        let mut constants = self.constants();
+        let mut slots: HashMap<Value, Slot> = HashMap::new();
+        let mut changed = false;
+        for index in 0..self.func.blocks.len() {
+            // 同じ領域から読んだ値や書き込んだ値は使い回す。
+            // ポインタを通した書き込みや呼び出しがあれば、どの領域も変わりうるので読み直す
+            let mut loaded: HashMap<(Slot, Width), Value> = HashMap::new();
+            let instructions = mem::take(&mut self.func.blocks[index].instructions);
+            let mut folded = Vec::new();
+            for mut instruction in instructions {
+                instruction.map_operands(|value| Self::resolve(&self.replacements, value));
+                match Self::simplify(&instruction, &constants) {
+                    Some(Simplified::Constant(n)) => {
+                        let dst = instruction.result().unwrap();
+                        constants.insert(dst, n);
+                        instruction = Instruction::Const(dst, n);
+                        changed = true;
+                    },
+                    Some(Simplified::Alias(value)) => {
+                        self.replace(instruction.result().unwrap(), value);
+                        changed = true;
+                        continue;
+                    },
+                    None => (),
+                }
+                match &instruction {
+                    Instruction::SlotAddress(dst, slot) => {
+                        slots.insert(*dst, *slot);
+                    },
+                    Instruction::Load(dst, width, address) if slots.contains_key(address) => {
+                        let key = (slots[address], *width);
+                        if let Some(&value) = loaded.get(&key) {
+                            self.replace(*dst, value);
+                            changed = true;
+                            continue;
+                        }
+                        loaded.insert(key, *dst);
+                    },
+                    // 1バイトの書き込みは上位のバイトを捨てるので、値を使い回さない
+                    Instruction::Store(width, address, value) if slots.contains_key(address) => {
+                        let slot = slots[address];
+                        loaded.retain(|&(s, _), _| s != slot);
+                        if let Width::Quad = width {
+                            loaded.insert((slot, Width::Quad), *value);
+                        }
+                    },
+                    Instruction::Store(_, _, _)
+                    | Instruction::CopyMemory(_, _, _)
+                    | Instruction::Call(_, _, _) => loaded.clear(),
+                    _ => (),
+                }
+                folded.push(instruction);
+            }
+            self.func.blocks[index].instructions = folded;
+        }
+        changed
+    }
+
+    fn simplify(instruction: &Instruction, constants: &HashMap<Value, i64>) -> Option<Simplified> {
+        match instruction {
+            Instruction::Unary(_, operator, x) => constants.get(x)
+                .map(|&n| Simplified::Constant(Self::evaluate_unary(*operator, n))),
+            Instruction::Binary(_, operator, x, y) => Self::simplify_binary(*operator, *x, *y, constants),
+            _ => None,
+        }
+    }
+
+    fn simplify_binary(operator: BinaryOperator, x: Value, y: Value, constants: &HashMap<Value, i64>) -> Option<Simplified> {
+        let left = constants.get(&x).cloned();
+        let right = constants.get(&y).cloned();
+        // 0で割る式は、実行したときと同じく0で割ったところで止まるよう残す。
+        // 割る数がコンパイル時に0と決まるものは、中間表現にするときに報告している
+        match (operator, right) {
+            (BinaryOperator::Div, Some(0)) | (BinaryOperator::Mod, Some(0)) => return None,
+            _ => (),
+        }
+        if let (Some(left), Some(right)) = (left, right) {
+            return Some(Simplified::Constant(Self::evaluate_binary(operator, left, right)));
+        }
+        match (operator, left, right) {
+            (BinaryOperator::Add, Some(0), _) | (BinaryOperator::Mul, Some(1), _) => Some(Simplified::Alias(y)),
+            (BinaryOperator::Add, _, Some(0))
+            | (BinaryOperator::Sub, _, Some(0))
+            | (BinaryOperator::Mul, _, Some(1))
+            | (BinaryOperator::Div, _, Some(1)) => Some(Simplified::Alias(x)),
+            (BinaryOperator::Mul, Some(0), _) | (BinaryOperator::Mul, _, Some(0)) => Some(Simplified::Constant(0)),
+            (BinaryOperator::Sub, _, _) | (BinaryOperator::Xor, _, _) if x == y => Some(Simplified::Constant(0)),
+            _ => None,
+        }
+    }
+
+    fn evaluate_unary(operator: UnaryOperator, n: i64) -> i64 {
+        match operator {
+            UnaryOperator::Neg => n.wrapping_neg(),
+            UnaryOperator::Not => !n,
+            UnaryOperator::LogicalNot => (n == 0) as i64,
+            UnaryOperator::Narrow => n & 0xff,
+        }
+    }
+
+    // 実行したときと同じく、あふれた桁は捨て、シフト量は下位6ビットだけを使う
+    fn evaluate_binary(operator: BinaryOperator, left: i64, right: i64) -> i64 {
+        match operator {
+            BinaryOperator::Add => left.wrapping_add(right),
+            BinaryOperator::Sub => left.wrapping_sub(right),
+            BinaryOperator::Mul => left.wrapping_mul(right),
+            BinaryOperator::Div => left.wrapping_div(right),
+            BinaryOperator::Mod => left.wrapping_rem(right),
+            BinaryOperator::And => left & right,
+            BinaryOperator::Or => left | right,
+            BinaryOperator::Xor => left ^ right,
+            BinaryOperator::Shl => left.wrapping_shl(right as u32),
+            BinaryOperator::Sar => left.wrapping_shr(right as u32),
+            BinaryOperator::Eq => (left == right) as i64,
+            BinaryOperator::Ne => (left != right) as i64,
+            BinaryOperator::Lt => (left < right) as i64,
+            BinaryOperator::Le => (left <= right) as i64,
+            BinaryOperator::Gt => (left > right) as i64,
+            BinaryOperator::Ge => (left >= right) as i64,
+        }
+    }
+
+    // 条件が定数なら、通るほうへ無条件に飛ぶ
+    fn fold_branches(&mut self) -> bool {
+        let constants = self.constants();
+        let replacements = &self.replacements;
+        let mut changed = false;
+        for block in &mut self.func.blocks {
+            block.terminator.map_operands(|value| Self::resolve(replacements, value));
+            let target = match &block.terminator {
+                Terminator::Branch(condition, then, else_) => match constants.get(condition) {
+                    Some(0) => else_.clone(),
+                    Some(_) => then.clone(),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            block.terminator = Terminator::Jump(target);
+            changed = true;
+        }
+        changed
+    }
+
+    // どこから飛んできても同じ値を受け取るブロック引数は、その値で置き換える
+    fn remove_params(&mut self) -> bool {
+        let mut incoming: Vec<Vec<Vec<Value>>> = self.func.blocks.iter()
+            .map(|block| vec![Vec::new(); block.params.len()])
+            .collect();
+        for block in &self.func.blocks {
+            for target in block.terminator.targets() {
+                for (index, &arg) in target.args.iter().enumerate() {
+                    incoming[target.block.0][index].push(Self::resolve(&self.replacements, arg));
+                }
+            }
+        }
+        let mut removed: Vec<Vec<bool>> = Vec::new();
+        let mut replacements = Vec::new();
+        for (block, args) in self.func.blocks.iter().zip(incoming.iter()) {
+            let mut block_removed = Vec::new();
+            for (&param, args) in block.params.iter().zip(args.iter()) {
+                // 先頭のブロックの引数は関数の引数なので残す
+                let mut others = args.iter().filter(|&&arg| arg != param);
+                let single = match others.next() {
+                    Some(&first) if block.id.0 != 0 && others.all(|&arg| arg == first) => Some(first),
+                    _ => None,
+                };
+                if let Some(value) = single {
+                    replacements.push((param, value));
+                }
+                block_removed.push(single.is_some());
+            }
+            removed.push(block_removed);
+        }
+        if replacements.is_empty() {
+            return false;
+        }
+        for block in &mut self.func.blocks {
+            let flags = &removed[block.id.0];
+            let mut index = 0;
+            block.params.retain(|_| {
+                index += 1;
+                !flags[index - 1]
+            });
+            for target in block.terminator.targets_mut() {
+                let flags = &removed[target.block.0];
+                let mut index = 0;
+                target.args.retain(|_| {
+                    index += 1;
+                    !flags[index - 1]
+                });
+            }
+        }
+        for (param, value) in replacements {
+            self.replace(param, value);
+        }
+        true
+    }
+
+    // 1か所からしか飛んでこないブロックへ無条件に飛ぶなら、飛ぶ元につなげる
+    fn merge_blocks(&mut self) -> bool {
+        let mut changed = false;
+        while let Some((from, to)) = self.mergeable() {
+            let mut instructions = mem::take(&mut self.func.blocks[to].instructions);
+            self.func.blocks[from].instructions.append(&mut instructions);
+            self.func.blocks[from].terminator = self.func.blocks[to].terminator.clone();
+            self.func.remove_unreachable_blocks();
+            changed = true;
+        }
+        changed
+    }
+
+    fn mergeable(&self) -> Option<(usize, usize)> {
+        let predecessors = self.func.predecessors();
+        self.func.blocks.iter().filter_map(|block| match &block.terminator {
+            Terminator::Jump(target) if target.args.is_empty()
+                && target.block != block.id
+                && target.block.0 != 0
+                && predecessors[target.block.0].len() == 1 => Some((block.id.0, target.block.0)),
+            _ => None,
+        }).next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ir::lower;
+
+    fn fold(src: &str) -> Program {
+        ConstantFolder::fold(lower(src))
+    }
+
+    // 返す値を求める命令
+    fn returned(func: &Func) -> Option<&Instruction> {
+        let block = func.blocks.last().unwrap();
+        let value = match block.terminator {
+            Terminator::Return(value) => value,
+            _ => return None,
+        };
+        block.instructions.iter().find(|instruction| instruction.result() == Some(value))
+    }
+
+    #[test]
+    fn test_fold_arithmetic() {
+        let program = fold("func main() 1 + 2 * 3 - 4 / 2");
+        let func = &program.funcs[0];
+
+        assert_eq!(func.blocks.len(), 1);
+        assert_eq!(returned(func), Some(&Instruction::Const(Value(8), 5)));
+        assert!(func.blocks[0].instructions.iter().all(|instruction| matches!(instruction, Instruction::Const(_, _))));
+    }
+
+    #[test]
+    fn test_fold_identity() {
+        let program = fold("func f(x) x * 1 + 0
+            func g(x) x - x
+            func h(x, y) (x + y) * 0");
+
+        // 引数をそのまま返す
+        assert_eq!(program.funcs[0].blocks[0].terminator, Terminator::Return(Value(0)));
+        match returned(&program.funcs[1]) {
+            Some(Instruction::Const(_, 0)) => (),
+            instruction => panic!("{:?}", instruction),
+        }
+        match returned(&program.funcs[2]) {
+            Some(Instruction::Const(_, 0)) => (),
+            instruction => panic!("{:?}", instruction),
+        }
+    }
+
+    #[test]
+    fn test_fold_if() {
+        let program = fold("func main() if 1 < 2 then 3 else 4");
+        let func = &program.funcs[0];
+
+        assert_eq!(func.blocks.len(), 1);
+        match returned(func) {
+            Some(Instruction::Const(_, 3)) => (),
+            instruction => panic!("{:?}", instruction),
+        }
+    }
+
+    #[test]
+    fn test_fold_while() {
+        let program = fold("func main() {
+            let i := 0
+            while i < 10 do {
+                i := i + 1 * 1
+            }
+            i
+        }");
+        let func = &program.funcs[0];
+
+        // 条件が定数でないループは残す
+        assert_eq!(func.blocks.len(), 4);
+        assert!(func.blocks.iter().all(|block| block.params.is_empty()));
+    }
+
+    #[test]
+    fn test_fold_division_by_zero() {
+        let program = fold("func main() {
+            let zero := 0
+            4 / zero
+        }
+        func f(x) x % (x - x)");
+
+        // 割る数が0になるとわかっても、畳み込まずに実行したときに任せる
+        let divides = |func: &Func| func.blocks.iter()
+            .flat_map(|block| block.instructions.iter())
+            .any(|instruction| matches!(instruction, Instruction::Binary(_, BinaryOperator::Div, _, _) | Instruction::Binary(_, BinaryOperator::Mod, _, _)));
+        assert!(divides(&program.funcs[0]));
+        assert!(divides(&program.funcs[1]));
+    }
+}
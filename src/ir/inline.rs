@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use super::program::Block;
+use super::program::BlockId;
+use super::program::Func;
+use super::program::Instruction;
+use super::program::Program;
+use super::program::Slot;
+use super::program::Target;
+use super::program::Terminator;
+use super::program::Value;
+
+// これ以下の命令数の関数を展開する
+const INLINE_LIMIT: usize = 20;
+
+// 小さな関数の呼び出しを、呼ばれる関数の本体で置き換える。
+// ほかの関数を呼ばない関数だけを展開するので、展開が終わらなくなることはない
+pub struct Inliner;
+
+impl Inliner {
+    pub fn inline(mut program: Program) -> Program {
+        let candidates: HashMap<String, Func> = program.funcs.iter()
+            .filter(|func| Self::is_candidate(func))
+            .map(|func| (func.name.clone(), func.clone()))
+            .collect();
+        for func in &mut program.funcs {
+            while let Some((block, index, callee)) = Self::find_call(func, &candidates) {
+                Self::inline_call(func, block, index, callee);
+            }
+        }
+        program
+    }
+
+    fn is_candidate(func: &Func) -> bool {
+        let instructions = func.blocks.iter().flat_map(|block| block.instructions.iter());
+        let mut size = 0;
+        for instruction in instructions {
+            if let Instruction::Call(_, _, _) = instruction {
+                return false;
+            }
+            size += 1;
+        }
+        size <= INLINE_LIMIT
+    }
+
+    // 展開できる呼び出しのあるブロックと、何番目の命令か
+    fn find_call<'a>(func: &Func, candidates: &'a HashMap<String, Func>) -> Option<(usize, usize, &'a Func)> {
+        for (block_index, block) in func.blocks.iter().enumerate() {
+            for (index, instruction) in block.instructions.iter().enumerate() {
+                if let Instruction::Call(_, name, args) = instruction {
+                    match candidates.get(name) {
+                        Some(callee) if callee.entry().params.len() == args.len() => return Some((block_index, index, callee)),
+                        _ => (),
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // 呼び出しの前後でブロックを分け、間に呼ばれる関数のブロックを写す。
+    // 呼ばれる関数の戻り値は、呼び出しの後半のブロックの引数で受け取る
+    fn inline_call(func: &mut Func, block_index: usize, index: usize, callee: &Func) {
+        let (result, args) = match &func.blocks[block_index].instructions[index] {
+            Instruction::Call(result, _, args) => (*result, args.clone()),
+            _ => unreachable!(),
+        };
+        let renaming = Renaming {
+            params: callee.entry().params.iter().cloned().zip(args).collect(),
+            values: func.values,
+            slots: func.slots.len(),
+            blocks: func.blocks.len(),
+        };
+        func.values += callee.values;
+        func.slots.extend(callee.slots.iter().cloned());
+        let continuation = BlockId(func.blocks.len() + callee.blocks.len());
+
+        let caller = &mut func.blocks[block_index];
+        let rest = caller.instructions.split_off(index + 1);
+        caller.instructions.pop();
+        let terminator = caller.terminator.clone();
+        caller.terminator = Terminator::Jump(Target{block: renaming.block(callee.entry().id), args: Vec::new()});
+
+        for block in &callee.blocks {
+            let terminator = match &block.terminator {
+                Terminator::Return(value) => Terminator::Jump(Target{block: continuation, args: vec![renaming.value(*value)]}),
+                terminator => renaming.terminator(terminator),
+            };
+            func.blocks.push(Block {
+                id: renaming.block(block.id),
+                // 先頭のブロックの引数は呼び出しの引数で置き換える
+                params: if block.id.0 == 0 { Vec::new() } else { block.params.iter().map(|&param| renaming.value(param)).collect() },
+                instructions: block.instructions.iter().map(|instruction| renaming.instruction(instruction)).collect(),
+                terminator,
+            });
+        }
+        func.blocks.push(Block {
+            id: continuation,
+            params: vec![result],
+            instructions: rest,
+            terminator,
+        });
+    }
+}
+
+// 呼ばれる関数の値、領域、ブロックを、呼ぶ関数の中で重ならない番号に付け替える
+struct Renaming {
+    params: HashMap<Value, Value>,
+    values: usize,
+    slots: usize,
+    blocks: usize,
+}
+
+impl Renaming {
+    fn value(&self, value: Value) -> Value {
+        match self.params.get(&value) {
+            Some(&arg) => arg,
+            None => Value(value.0 + self.values),
+        }
+    }
+
+    fn block(&self, block: BlockId) -> BlockId {
+        BlockId(block.0 + self.blocks)
+    }
+
+    fn instruction(&self, instruction: &Instruction) -> Instruction {
+        let mut renamed = match instruction {
+            Instruction::Const(dst, n) => Instruction::Const(self.value(*dst), *n),
+            Instruction::Unary(dst, operator, x) => Instruction::Unary(self.value(*dst), *operator, *x),
+            Instruction::Binary(dst, operator, x, y) => Instruction::Binary(self.value(*dst), *operator, *x, *y),
+            Instruction::SlotAddress(dst, slot) => Instruction::SlotAddress(self.value(*dst), Slot(slot.0 + self.slots)),
+            Instruction::GlobalAddress(dst, name) => Instruction::GlobalAddress(self.value(*dst), name.clone()),
+            Instruction::StringAddress(dst, index) => Instruction::StringAddress(self.value(*dst), *index),
+            Instruction::Load(dst, width, address) => Instruction::Load(self.value(*dst), *width, *address),
+            Instruction::Call(dst, name, args) => Instruction::Call(self.value(*dst), name.clone(), args.clone()),
+            Instruction::Store(_, _, _) | Instruction::CopyMemory(_, _, _) => instruction.clone(),
+        };
+        renamed.map_operands(|value| self.value(value));
+        renamed
+    }
+
+    fn terminator(&self, terminator: &Terminator) -> Terminator {
+        let mut renamed = terminator.clone();
+        renamed.map_operands(|value| self.value(value));
+        for target in renamed.targets_mut() {
+            target.block = self.block(target.block);
+        }
+        renamed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ir::lower;
+
+    fn calls(func: &Func) -> usize {
+        func.blocks.iter()
+            .flat_map(|block| block.instructions.iter())
+            .filter(|instruction| matches!(instruction, Instruction::Call(_, _, _)))
+            .count()
+    }
+
+    #[test]
+    fn test_inline() {
+        let program = lower("func max(a, b) if a > b then a else b
+            func main() max(3, 4) + 1");
+
+        let program = Inliner::inline(program);
+        let func = &program.funcs[1];
+
+        assert_eq!(calls(func), 0);
+        // 呼び出しの前、maxの4つのブロック、呼び出しの後
+        assert_eq!(func.blocks.len(), 6);
+        assert_eq!(func.slots.len(), 2);
+        assert!(func.blocks.iter().enumerate().all(|(index, block)| block.id.0 == index));
+        match &func.blocks[5].terminator {
+            Terminator::Return(_) => (),
+            terminator => panic!("{:?}", terminator),
+        }
+        assert_eq!(func.blocks[5].params.len(), 1);
+    }
+
+    #[test]
+    fn test_inline_skips() {
+        let program = lower("func fib(n) if n < 2 then n else fib(n - 1) + fib(n - 2)
+            func main() fib(10) + printf(\"\")");
+
+        let program = Inliner::inline(program);
+
+        // 再帰する関数と外部の関数は展開しない
+        assert_eq!(calls(&program.funcs[1]), 2);
+    }
+}
@@ -0,0 +1,951 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use sourcecode::Code;
+use sourcecode::Span;
+
+use token::Operator;
+
+use diagnostic::Diagnostic;
+use diagnostic::code;
+use diagnostic::Message;
+
+use parse::SyntaxTree;
+use parse::Root;
+use parse::Func as FuncTree;
+use parse::Global as GlobalTree;
+use parse::GlobalKind;
+use parse::Statement;
+use parse::Expression;
+use parse::IfExpression;
+use parse::WhileExpression;
+use parse::BlockExpression;
+use parse::LogicalOr;
+use parse::LogicalAnd;
+use parse::LogicalNot;
+use parse::BitOr;
+use parse::BitXor;
+use parse::BitAnd;
+use parse::Equality;
+use parse::Relational;
+use parse::Shift;
+use parse::Add;
+use parse::Multiply;
+use parse::Unary;
+use parse::Primary;
+use parse::FnCall;
+use parse::StructLiteral;
+
+use typecheck::Type;
+use typecheck::TypeTable;
+
+use super::constant::Constants;
+use super::scope::Location;
+use super::scope::Scope;
+use super::program::BinaryOperator;
+use super::program::Block;
+use super::program::BlockId;
+use super::program::Func;
+use super::program::Global;
+use super::program::GlobalContent;
+use super::program::Instruction;
+use super::program::Program;
+use super::program::Slot;
+use super::program::StackSlot;
+use super::program::Target;
+use super::program::Terminator;
+use super::program::UnaryOperator;
+use super::program::Value;
+use super::program::Width;
+
+pub struct Lowerer;
+
+impl Lowerer {
+    pub fn lower(syntaxtree: &Root, types: &TypeTable) -> Result<Program, Vec<Diagnostic>> {
+        let mut errors = Vec::new();
+        let mut constants = Constants::new();
+        let globals = match Self::lower_globals(&syntaxtree.globals, types, &mut constants) {
+            Ok(globals) => globals,
+            Err((span, message)) => {
+                errors.push(Diagnostic::error(code::CONSTANT, Some(span), message));
+                Vec::new()
+            },
+        };
+        let names: HashSet<String> = syntaxtree.globals.iter()
+            .map(|global| global.name.value.clone())
+            .collect();
+        let mut strings = Vec::new();
+        let mut funcs = Vec::new();
+        for func in &syntaxtree.funcs {
+            match FuncLowerer::lower(func, types, &constants, &names, &mut strings) {
+                Ok(f) => funcs.push(f),
+                Err(mut e) => errors.append(&mut e),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(Program{globals, strings, funcs})
+    }
+
+    // 初期値はコンパイル時に評価しておく。定数の値は関数の中でも使う
+    fn lower_globals(globals: &[GlobalTree], types: &TypeTable, constants: &mut Constants) -> Result<Vec<Global>, (Span, Message)> {
+        let mut lowered = Vec::new();
+        for global in globals {
+            let name = global.name.value.clone();
+            let content = match &global.content {
+                Some(content) => content,
+                None => {
+                    let size = types.size_of(&types.variable_type(&global.name.span));
+                    lowered.push(Global{name, content: GlobalContent::Zero(size)});
+                    continue;
+                },
+            };
+            let value = constants.evaluate(content)?;
+            let content = match global.kind {
+                GlobalKind::Variable => GlobalContent::Variable(value),
+                GlobalKind::Constant => {
+                    constants.define(&name, value);
+                    GlobalContent::Constant(value)
+                },
+            };
+            lowered.push(Global{name, content});
+        }
+        Ok(lowered)
+    }
+}
+
+// 組み立て中のブロック。終端命令はあとから決まる
+struct PartialBlock {
+    params: Vec<Value>,
+    instructions: Vec<Instruction>,
+    terminator: Option<Terminator>,
+}
+
+// break / continue の飛び先
+struct Loop {
+    begin: BlockId,
+    end: BlockId,
+}
+
+struct FuncLowerer<'a> {
+    types: &'a TypeTable,
+    constants: &'a Constants,
+    strings: &'a mut Vec<String>,
+    scope: Scope,
+    blocks: Vec<PartialBlock>,
+    // ブロックを書き始めた順。並べるときはこの順にする
+    layout: Vec<BlockId>,
+    current: BlockId,
+    slots: Vec<StackSlot>,
+    values: usize,
+    loops: Vec<Loop>,
+    // 0で割る式のように、見つけても中間表現にし続けられる誤り
+    errors: Vec<Diagnostic>,
+}
+
+impl <'a> FuncLowerer<'a> {
+    // 中間表現にできなくなる誤りは、それまでに見つけた誤りと合わせて返す
+    fn lower(func: &FuncTree, types: &'a TypeTable, constants: &'a Constants, globals: &HashSet<String>, strings: &'a mut Vec<String>) -> Result<Func, Vec<Diagnostic>> {
+        let mut lowerer = Self{
+            types,
+            constants,
+            strings,
+            scope: Scope::new(globals.clone()),
+            blocks: Vec::new(),
+            layout: Vec::new(),
+            current: BlockId(0),
+            slots: Vec::new(),
+            values: 0,
+            loops: Vec::new(),
+            errors: Vec::new(),
+        };
+        if let Err((span, message)) = lowerer.lower_body(func) {
+            lowerer.errors.push(Diagnostic::error(code::COMPILE, Some(span), message));
+        }
+        if !lowerer.errors.is_empty() {
+            return Err(lowerer.errors);
+        }
+        Ok(lowerer.finish(&func.name))
+    }
+
+    fn lower_body(&mut self, func: &FuncTree) -> Result<(), (Span, Message)> {
+        let entry = self.new_block();
+        self.switch_to(entry);
+        let params: Vec<Value> = func.args.iter().map(|_| self.new_value()).collect();
+        self.blocks[entry.0].params = params.clone();
+        // 引数もほかの変数と同じくスタックに置く
+        for (arg, &value) in func.args.iter().zip(params.iter()) {
+            let slot = self.new_slot(8, 8);
+            if self.scope.declare(&arg.name.value, slot).is_err() {
+                return Err((arg.name.span, Message::DuplicateArgument));
+            }
+            let address = self.slot_address(slot);
+            self.emit(Instruction::Store(Width::Quad, address, value));
+        }
+        let value = self.lower_expression(&func.body)?;
+        self.terminate(Terminator::Return(value));
+        Ok(())
+    }
+
+    // 到達できないブロックを除き、書き始めた順に番号を振り直す
+    fn finish(self, name: &str) -> Func {
+        let mut reachable = HashSet::new();
+        let mut work = vec![BlockId(0)];
+        while let Some(id) = work.pop() {
+            if !reachable.insert(id) {
+                continue;
+            }
+            if let Some(terminator) = &self.blocks[id.0].terminator {
+                work.extend(terminator.targets().iter().map(|target| target.block));
+            }
+        }
+        let order: Vec<BlockId> = self.layout.iter().cloned().filter(|id| reachable.contains(id)).collect();
+        let numbers: HashMap<BlockId, BlockId> = order.iter().enumerate()
+            .map(|(index, &id)| (id, BlockId(index)))
+            .collect();
+        let renumber = |target: &Target| Target{block: numbers[&target.block], args: target.args.clone()};
+        let mut partial_blocks: Vec<Option<PartialBlock>> = self.blocks.into_iter().map(Some).collect();
+        let blocks = order.iter().map(|id| {
+            let block = partial_blocks[id.0].take().unwrap();
+            let terminator = match block.terminator.unwrap() {
+                Terminator::Jump(target) => Terminator::Jump(renumber(&target)),
+                Terminator::Branch(condition, then, else_) => Terminator::Branch(condition, renumber(&then), renumber(&else_)),
+                Terminator::Return(value) => Terminator::Return(value),
+            };
+            Block{id: numbers[id], params: block.params, instructions: block.instructions, terminator}
+        }).collect();
+        Func{name: name.to_string(), blocks, slots: self.slots, values: self.values}
+    }
+
+    fn new_value(&mut self) -> Value {
+        self.values += 1;
+        Value(self.values - 1)
+    }
+
+    fn new_slot(&mut self, size: i64, align: i64) -> Slot {
+        self.slots.push(StackSlot{size, align});
+        Slot(self.slots.len() - 1)
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(PartialBlock{params: Vec::new(), instructions: Vec::new(), terminator: None});
+        BlockId(self.blocks.len() - 1)
+    }
+
+    // 合流先のブロック。引数で値を受け取る
+    fn new_join_block(&mut self) -> (BlockId, Value) {
+        let block = self.new_block();
+        let value = self.new_value();
+        self.blocks[block.0].params.push(value);
+        (block, value)
+    }
+
+    fn switch_to(&mut self, block: BlockId) {
+        self.layout.push(block);
+        self.current = block;
+    }
+
+    fn emit(&mut self, instruction: Instruction) {
+        self.blocks[self.current.0].instructions.push(instruction);
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        self.blocks[self.current.0].terminator = Some(terminator);
+    }
+
+    // 飛んだあとに続く式は、どこからも飛んでこないブロックに書く
+    fn terminate_and_continue(&mut self, terminator: Terminator) {
+        self.terminate(terminator);
+        let next = self.new_block();
+        self.switch_to(next);
+    }
+
+    fn jump(block: BlockId, args: Vec<Value>) -> Target {
+        Target{block, args}
+    }
+
+    fn constant(&mut self, n: i64) -> Value {
+        let value = self.new_value();
+        self.emit(Instruction::Const(value, n));
+        value
+    }
+
+    fn unary(&mut self, operator: UnaryOperator, x: Value) -> Value {
+        let value = self.new_value();
+        self.emit(Instruction::Unary(value, operator, x));
+        value
+    }
+
+    fn binary(&mut self, operator: BinaryOperator, x: Value, y: Value) -> Value {
+        let value = self.new_value();
+        self.emit(Instruction::Binary(value, operator, x, y));
+        value
+    }
+
+    fn slot_address(&mut self, slot: Slot) -> Value {
+        let value = self.new_value();
+        self.emit(Instruction::SlotAddress(value, slot));
+        value
+    }
+
+    fn load(&mut self, t: &Type, address: Value) -> Value {
+        let value = self.new_value();
+        self.emit(Instruction::Load(value, Self::width(t), address));
+        value
+    }
+
+    // charは1バイト、それ以外は8バイトで読み書きする
+    fn width(t: &Type) -> Width {
+        match t {
+            Type::Char => Width::Byte,
+            _ => Width::Quad,
+        }
+    }
+
+    // 配列と構造体は値ではなくアドレスで扱う
+    fn is_aggregate(t: &Type) -> bool {
+        matches!(t, Type::Array(_, _) | Type::Struct(_))
+    }
+
+    fn by_address(&self, span: &Span) -> bool {
+        self.types.decays(span) || Self::is_aggregate(&self.types.type_of(span))
+    }
+
+    fn lower_statement(&mut self, statement: &Statement) -> Result<(), (Span, Message)> {
+        match statement {
+            // 配列と構造体は大きさに合わせて領域を確保し、構造体の初期値はコピーする
+            Statement::Assignment(asn) if Self::is_aggregate(&self.types.variable_type(&asn.identifier().span)) => {
+                let name = asn.identifier();
+                let t = self.types.variable_type(&name.span);
+                let size = self.types.size_of(&t);
+                let content = match asn.content() {
+                    Some(content) => match self.lower_expression(content) {
+                        Ok(value) => Some(value),
+                        Err(e) => return Err(e),
+                    },
+                    None => None,
+                };
+                let slot = self.new_slot(size, self.types.align_of(&t));
+                self.scope.declare(&name.value, slot).unwrap();
+                if let Some(content) = content {
+                    let address = self.slot_address(slot);
+                    self.emit(Instruction::CopyMemory(address, content, size));
+                }
+                Ok(())
+            },
+            Statement::Assignment(asn) => {
+                let name = asn.identifier();
+                let content = match asn.content() {
+                    Some(content) => match self.lower_expression(content) {
+                        Ok(value) => Some(value),
+                        Err(e) => return Err(e),
+                    },
+                    None => None,
+                };
+                let slot = self.new_slot(8, 8);
+                self.scope.declare(&name.value, slot).unwrap();
+                if let Some(content) = content {
+                    let address = self.slot_address(slot);
+                    self.emit(Instruction::Store(Width::Quad, address, content));
+                }
+                Ok(())
+            },
+            // 変数は型によらず8バイトで書き込む
+            Statement::Reassignment(asn) if asn.target().identifier().is_some()
+                && !Self::is_aggregate(&self.types.type_of(&asn.target().span())) => {
+                let name = asn.target().identifier().unwrap();
+                let content = self.lower_expression(asn.content())?;
+                let address = self.lower_variable_address(name)?;
+                self.emit(Instruction::Store(Width::Quad, address, content));
+                Ok(())
+            },
+            Statement::Reassignment(asn) => {
+                let address = self.lower_address(asn.target())?;
+                let content = self.lower_expression(asn.content())?;
+                let target_type = self.types.type_of(&asn.target().span());
+                if Self::is_aggregate(&target_type) {
+                    self.emit(Instruction::CopyMemory(address, content, self.types.size_of(&target_type)));
+                } else {
+                    self.emit(Instruction::Store(Self::width(&target_type), address, content));
+                }
+                Ok(())
+            },
+            Statement::Return(ret) => {
+                let value = self.lower_expression(ret.content())?;
+                self.terminate_and_continue(Terminator::Return(value));
+                Ok(())
+            },
+            Statement::Expression(expr) => self.lower_expression(expr).map(|_| ()),
+        }
+    }
+
+    fn lower_expression(&mut self, expression: &Expression) -> Result<Value, (Span, Message)> {
+        let value = self.lower_expression_value(expression)?;
+        if self.types.narrows(&expression.span()) {
+            return Ok(self.unary(UnaryOperator::Narrow, value));
+        }
+        Ok(value)
+    }
+
+    fn lower_expression_value(&mut self, expression: &Expression) -> Result<Value, (Span, Message)> {
+        match expression {
+            Expression::PureExpression(expr) => self.lower_logical_or(&expr.logical_or),
+            Expression::IfExpression(expr) => self.lower_if_expression(expr),
+            Expression::WhileExpression(expr) => self.lower_while_expression(expr),
+            Expression::BlockExpression(expr) => self.lower_block_expression(expr),
+            Expression::Break(span) => self.lower_jump(span, |l| l.end, Message::BreakOutsideLoop),
+            Expression::Continue(span) => self.lower_jump(span, |l| l.begin, Message::ContinueOutsideLoop),
+        }
+    }
+
+    // 分岐した先の値は合流先のブロック引数で受け取る
+    fn lower_if_expression(&mut self, expr: &IfExpression) -> Result<Value, (Span, Message)> {
+        let condition = self.lower_expression(&expr.condition)?;
+        let then_block = self.new_block();
+        let else_block = self.new_block();
+        let (end_block, result) = self.new_join_block();
+        self.terminate(Terminator::Branch(condition, Self::jump(then_block, Vec::new()), Self::jump(else_block, Vec::new())));
+
+        self.switch_to(then_block);
+        let then = self.lower_expression(&expr.then)?;
+        self.terminate(Terminator::Jump(Self::jump(end_block, vec![then])));
+
+        self.switch_to(else_block);
+        let else_ = self.lower_expression(&expr.else_)?;
+        self.terminate(Terminator::Jump(Self::jump(end_block, vec![else_])));
+
+        self.switch_to(end_block);
+        Ok(result)
+    }
+
+    fn lower_while_expression(&mut self, expr: &WhileExpression) -> Result<Value, (Span, Message)> {
+        let begin_block = self.new_block();
+        let body_block = self.new_block();
+        let end_block = self.new_block();
+        self.terminate(Terminator::Jump(Self::jump(begin_block, Vec::new())));
+
+        self.switch_to(begin_block);
+        let condition = self.lower_expression(&expr.condition)?;
+        self.terminate(Terminator::Branch(condition, Self::jump(body_block, Vec::new()), Self::jump(end_block, Vec::new())));
+
+        self.switch_to(body_block);
+        self.loops.push(Loop {
+            begin: begin_block,
+            end: end_block,
+        });
+        let body_result = self.lower_expression(&expr.body);
+        self.loops.pop();
+        body_result?;
+        self.terminate(Terminator::Jump(Self::jump(begin_block, Vec::new())));
+
+        self.switch_to(end_block);
+        Ok(self.constant(0))
+    }
+
+    // 後続の式のために値を1つ返しておく
+    fn lower_jump<F>(&mut self, span: &Span, target: F, outside_message: Message) -> Result<Value, (Span, Message)>
+    where F: Fn(&Loop) -> BlockId {
+        let block = match self.loops.last() {
+            Some(l) => target(l),
+            None => return Err((*span, outside_message)),
+        };
+        self.terminate_and_continue(Terminator::Jump(Self::jump(block, Vec::new())));
+        Ok(self.constant(0))
+    }
+
+    fn lower_block_expression(&mut self, expr: &BlockExpression) -> Result<Value, (Span, Message)> {
+        self.scope.into_block();
+        let result = self.lower_block_content(expr);
+        self.scope.outof_block();
+        result
+    }
+
+    fn lower_block_content(&mut self, expr: &BlockExpression) -> Result<Value, (Span, Message)> {
+        for stmt in &expr.statements {
+            self.lower_statement(stmt)?;
+        }
+        match &expr.outcome {
+            Some(outcome) => self.lower_expression(outcome),
+            None => Ok(self.constant(0)),
+        }
+    }
+
+    // 左から順に評価し、真になった時点で残りを飛ばす
+    fn lower_logical_or(&mut self, logical_or: &LogicalOr) -> Result<Value, (Span, Message)> {
+        let mut value = self.lower_logical_and(logical_or.head())?;
+        if logical_or.tail().next().is_none() {
+            return Ok(value);
+        }
+        let (end_block, result) = self.new_join_block();
+        for (_, logical_and) in logical_or.tail() {
+            let next_block = self.new_block();
+            let one = self.constant(1);
+            self.terminate(Terminator::Branch(value, Self::jump(end_block, vec![one]), Self::jump(next_block, Vec::new())));
+            self.switch_to(next_block);
+            value = self.lower_logical_and(logical_and)?;
+        }
+        let one = self.constant(1);
+        let zero = self.constant(0);
+        self.terminate(Terminator::Branch(value, Self::jump(end_block, vec![one]), Self::jump(end_block, vec![zero])));
+        self.switch_to(end_block);
+        Ok(result)
+    }
+
+    // 左から順に評価し、偽になった時点で残りを飛ばす
+    fn lower_logical_and(&mut self, logical_and: &LogicalAnd) -> Result<Value, (Span, Message)> {
+        let mut value = self.lower_logical_not(logical_and.head())?;
+        if logical_and.tail().next().is_none() {
+            return Ok(value);
+        }
+        let (end_block, result) = self.new_join_block();
+        for (_, logical_not) in logical_and.tail() {
+            let next_block = self.new_block();
+            let zero = self.constant(0);
+            self.terminate(Terminator::Branch(value, Self::jump(next_block, Vec::new()), Self::jump(end_block, vec![zero])));
+            self.switch_to(next_block);
+            value = self.lower_logical_not(logical_not)?;
+        }
+        let one = self.constant(1);
+        let zero = self.constant(0);
+        self.terminate(Terminator::Branch(value, Self::jump(end_block, vec![one]), Self::jump(end_block, vec![zero])));
+        self.switch_to(end_block);
+        Ok(result)
+    }
+
+    fn lower_logical_not(&mut self, logical_not: &LogicalNot) -> Result<Value, (Span, Message)> {
+        match logical_not {
+            LogicalNot::Not(operand, _) => self.lower_logical_not(operand)
+                .map(|value| self.unary(UnaryOperator::LogicalNot, value)),
+            LogicalNot::BitOr(bit_or) => self.lower_bit_or(bit_or),
+        }
+    }
+
+    fn lower_bit_or(&mut self, bit_or: &BitOr) -> Result<Value, (Span, Message)> {
+        let mut left = self.lower_bit_xor(bit_or.head())?;
+        for (_, bit_xor) in bit_or.tail() {
+            let right = self.lower_bit_xor(bit_xor)?;
+            left = self.binary(BinaryOperator::Or, left, right);
+        }
+        Ok(left)
+    }
+
+    fn lower_bit_xor(&mut self, bit_xor: &BitXor) -> Result<Value, (Span, Message)> {
+        let mut left = self.lower_bit_and(bit_xor.head())?;
+        for (_, bit_and) in bit_xor.tail() {
+            let right = self.lower_bit_and(bit_and)?;
+            left = self.binary(BinaryOperator::Xor, left, right);
+        }
+        Ok(left)
+    }
+
+    fn lower_bit_and(&mut self, bit_and: &BitAnd) -> Result<Value, (Span, Message)> {
+        let mut left = self.lower_equality(bit_and.head())?;
+        for (_, equality) in bit_and.tail() {
+            let right = self.lower_equality(equality)?;
+            left = self.binary(BinaryOperator::And, left, right);
+        }
+        Ok(left)
+    }
+
+    fn lower_equality(&mut self, equality: &Equality) -> Result<Value, (Span, Message)> {
+        let mut left = self.lower_relational(equality.head())?;
+        for (operator, relational) in equality.tail() {
+            let right = self.lower_relational(relational)?;
+            let operator = match operator.value {
+                Operator::Equal => BinaryOperator::Eq,
+                _ => BinaryOperator::Ne,
+            };
+            left = self.binary(operator, left, right);
+        }
+        Ok(left)
+    }
+
+    fn lower_relational(&mut self, relational: &Relational) -> Result<Value, (Span, Message)> {
+        let mut left = self.lower_shift(relational.head())?;
+        for (operator, shift) in relational.tail() {
+            let right = self.lower_shift(shift)?;
+            let operator = match operator.value {
+                Operator::Less => BinaryOperator::Lt,
+                Operator::LessEq => BinaryOperator::Le,
+                Operator::Greater => BinaryOperator::Gt,
+                _ => BinaryOperator::Ge,
+            };
+            left = self.binary(operator, left, right);
+        }
+        Ok(left)
+    }
+
+    fn lower_shift(&mut self, shift: &Shift) -> Result<Value, (Span, Message)> {
+        let mut left = self.lower_add(shift.head())?;
+        for (operator, add) in shift.tail() {
+            let right = self.lower_add(add)?;
+            let operator = match operator.value {
+                Operator::ShiftLeft => BinaryOperator::Shl,
+                _ => BinaryOperator::Sar,
+            };
+            left = self.binary(operator, left, right);
+        }
+        Ok(left)
+    }
+
+    fn lower_add(&mut self, add: &Add) -> Result<Value, (Span, Message)> {
+        let head = add.head();
+        let mut left = self.lower_multiply(head)?;
+        let mut left_type = self.types.type_of(&head.span());
+        for (operator, multiply) in add.tail() {
+            let mut right = self.lower_multiply(multiply)?;
+            let right_type = self.types.type_of(&multiply.span());
+            // ポインタの演算は指す先の大きさを単位とする
+            let left_size = left_type.pointee().map(|t| self.types.size_of(t));
+            let right_size = right_type.pointee().map(|t| self.types.size_of(t));
+            match (left_size, right_size) {
+                (Some(size), None) => right = self.scale(right, size),
+                (None, Some(size)) => {
+                    left = self.scale(left, size);
+                    left_type = right_type.clone();
+                },
+                _ => (),
+            }
+            let operator = match operator.value {
+                Operator::Add => BinaryOperator::Add,
+                _ => BinaryOperator::Sub,
+            };
+            left = self.binary(operator, left, right);
+            if let (Some(size), Some(_)) = (left_size, right_size) {
+                let size = self.constant(size);
+                left = self.binary(BinaryOperator::Div, left, size);
+                left_type = Type::Int;
+            }
+        }
+        Ok(left)
+    }
+
+    fn scale(&mut self, value: Value, size: i64) -> Value {
+        let size = self.constant(size);
+        self.binary(BinaryOperator::Mul, value, size)
+    }
+
+    fn lower_multiply(&mut self, multiply: &Multiply) -> Result<Value, (Span, Message)> {
+        let mut left = self.lower_unary(multiply.head())?;
+        for (operator, unary) in multiply.tail() {
+            let right = self.lower_unary(unary)?;
+            let operator = match operator.value {
+                Operator::Mul => BinaryOperator::Mul,
+                Operator::Mod => BinaryOperator::Mod,
+                _ => BinaryOperator::Div,
+            };
+            // 割る数がコンパイル時に0と決まるときは報告する。通らない枝の中でも同じ。
+            // 変数を通して0になる割り算は、実行したときに任せる
+            if operator != BinaryOperator::Mul && self.is_zero(unary) {
+                self.errors.push(Diagnostic::error(code::CONSTANT, Some(unary.span()), Message::DivisionByZero));
+            }
+            left = self.binary(operator, left, right);
+        }
+        Ok(left)
+    }
+
+    // ローカル変数に隠された定数は使わない
+    fn is_zero(&self, unary: &Unary) -> bool {
+        let constants = self.constants.without(|name| self.scope.is_local(name));
+        matches!(constants.evaluate_unary(unary), Ok(0))
+    }
+
+    fn lower_unary(&mut self, unary: &Unary) -> Result<Value, (Span, Message)> {
+        match unary {
+            Unary::Positive(primary, _) => self.lower_primary(primary),
+            Unary::Negative(primary, _) => self.lower_primary(primary)
+                .map(|value| self.unary(UnaryOperator::Neg, value)),
+            Unary::Address(operand, _) => self.lower_address(operand),
+            Unary::Dereference(operand, _) => {
+                let value = self.lower_unary(operand)?;
+                // 配列を指していれば、そのアドレスが先頭要素へのポインタになる
+                if self.by_address(&unary.span()) {
+                    return Ok(value);
+                }
+                Ok(self.load(&self.types.type_of(&unary.span()), value))
+            },
+            Unary::BitNot(operand, _) => self.lower_unary(operand)
+                .map(|value| self.unary(UnaryOperator::Not, value)),
+        }
+    }
+
+    // 代入できる式のアドレスを求める
+    fn lower_address(&mut self, unary: &Unary) -> Result<Value, (Span, Message)> {
+        match unary.primary() {
+            Some(Primary::Identifier(name)) => return self.lower_variable_address(name),
+            Some(Primary::Index(base, index, _)) => return self.lower_element(base, index),
+            Some(Primary::Field(base, field)) => return self.lower_field(base, field),
+            _ => (),
+        }
+        match unary {
+            Unary::Dereference(operand, _) => self.lower_unary(operand),
+            _ => Err((unary.span(), Message::NotAddressable)),
+        }
+    }
+
+    fn lower_variable_address(&mut self, name: &Code<String>) -> Result<Value, (Span, Message)> {
+        let value = self.new_value();
+        match self.scope.resolve(name) {
+            Ok(Location::Local(slot)) => self.emit(Instruction::SlotAddress(value, slot)),
+            Ok(Location::Global(global)) => self.emit(Instruction::GlobalAddress(value, global)),
+            Err(span) => return Err((span, Message::UndefinedSymbol)),
+        }
+        Ok(value)
+    }
+
+    // 先頭へのポインタに、添字と要素の大きさの積を足す
+    fn lower_element(&mut self, base: &Primary, index: &Expression) -> Result<Value, (Span, Message)> {
+        let base_value = self.lower_primary(base)?;
+        let index_value = self.lower_expression(index)?;
+        let size = self.types.type_of(&base.span()).pointee().map(|t| self.types.size_of(t)).unwrap_or(8);
+        let offset = self.scale(index_value, size);
+        Ok(self.binary(BinaryOperator::Add, base_value, offset))
+    }
+
+    // 構造体へのポインタ、または構造体そのもののアドレスにフィールドの位置を足す
+    fn lower_field(&mut self, base: &Primary, field: &Code<String>) -> Result<Value, (Span, Message)> {
+        let base_value = self.lower_primary(base)?;
+        let offset = match self.types.type_of(&base.span()) {
+            Type::Struct(name) => self.field_offset(&name, field),
+            Type::Pointer(pointee) => match *pointee {
+                Type::Struct(name) => self.field_offset(&name, field),
+                _ => None,
+            },
+            _ => None,
+        };
+        match offset {
+            Some(offset) => {
+                let offset = self.constant(offset);
+                Ok(self.binary(BinaryOperator::Add, base_value, offset))
+            },
+            None => Err((field.span, Message::NoField)),
+        }
+    }
+
+    fn field_offset(&self, name: &str, field: &Code<String>) -> Option<i64> {
+        self.types.struct_layout(name)
+            .and_then(|layout| layout.field(&field.value))
+            .map(|field_layout| field_layout.offset)
+    }
+
+    // 一時的な領域に各フィールドを書き込み、そのアドレスを値とする
+    fn lower_struct_literal(&mut self, literal: &StructLiteral) -> Result<Value, (Span, Message)> {
+        let t = Type::Struct(literal.name.value.clone());
+        let slot = self.new_slot(self.types.size_of(&t), self.types.align_of(&t));
+        for (field, value) in &literal.fields {
+            let value = self.lower_expression(value)?;
+            let field_layout = self.types.struct_layout(&literal.name.value)
+                .and_then(|layout| layout.field(&field.value));
+            let (field_offset, field_type) = match field_layout {
+                Some(field_layout) => (field_layout.offset, field_layout.field_type.clone()),
+                None => return Err((field.span, Message::NoField)),
+            };
+            let base = self.slot_address(slot);
+            let offset = self.constant(field_offset);
+            let address = self.binary(BinaryOperator::Add, base, offset);
+            if Self::is_aggregate(&field_type) {
+                self.emit(Instruction::CopyMemory(address, value, self.types.size_of(&field_type)));
+            } else {
+                self.emit(Instruction::Store(Self::width(&field_type), address, value));
+            }
+        }
+        Ok(self.slot_address(slot))
+    }
+
+    fn lower_primary(&mut self, primary: &Primary) -> Result<Value, (Span, Message)> {
+        match primary {
+            Primary::Integer(n) => Ok(self.constant(n.value)),
+            Primary::Boolean(b) => Ok(self.constant(b.value as i64)),
+            Primary::Character(c) => Ok(self.constant(c.value as i64)),
+            Primary::String(content) => {
+                self.strings.push(content.value.clone());
+                let value = self.new_value();
+                self.emit(Instruction::StringAddress(value, self.strings.len() - 1));
+                Ok(value)
+            },
+            Primary::Identifier(name) if self.by_address(&name.span) => self.lower_variable_address(name),
+            // 変数は型によらず8バイトで読む
+            Primary::Identifier(name) => self.lower_variable_address(name)
+                .map(|address| self.load(&Type::Int, address)),
+            Primary::Expression(expression) => self.lower_expression(expression),
+            Primary::FnCall(fn_call) => self.lower_fn_call(fn_call),
+            Primary::Field(base, field) => {
+                let span = primary.span();
+                let address = self.lower_field(base, field)?;
+                if self.by_address(&span) {
+                    return Ok(address);
+                }
+                Ok(self.load(&self.types.type_of(&span), address))
+            },
+            Primary::StructLiteral(literal) => self.lower_struct_literal(literal),
+            Primary::Index(base, index, span) => {
+                let address = self.lower_element(base, index)?;
+                if self.by_address(span) {
+                    return Ok(address);
+                }
+                Ok(self.load(&self.types.type_of(span), address))
+            },
+        }
+    }
+
+    fn lower_fn_call(&mut self, fn_call: &FnCall) -> Result<Value, (Span, Message)> {
+        let mut args = Vec::new();
+        for arg in fn_call.args.iter() {
+            match self.lower_expression(arg) {
+                Ok(value) => args.push(value),
+                Err(e) => return Err(e),
+            }
+        }
+        let value = self.new_value();
+        self.emit(Instruction::Call(value, fn_call.func.value.clone(), args));
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use parse::TokenReader;
+    use token::tokenize;
+    use typecheck::TypeChecker;
+    use ir::lower;
+
+    #[test]
+    fn test_lower_arithmetic() {
+        let program = lower("func main() 1 + 2 * 3");
+
+        assert_eq!(program.funcs[0].code(), "func main() {
+bb0:
+  %0 = const 1
+  %1 = const 2
+  %2 = const 3
+  %3 = mul %1, %2
+  %4 = add %0, %3
+  ret %4
+}");
+    }
+
+    #[test]
+    fn test_lower_if() {
+        let program = lower("func f(x) if x then 1 else 2");
+        let func = &program.funcs[0];
+
+        assert_eq!(func.blocks.len(), 4);
+        assert_eq!(func.entry().params, vec![Value(0)]);
+        assert_eq!(func.slots, vec![StackSlot{size: 8, align: 8}]);
+        assert_eq!(func.blocks[3].params.len(), 1);
+        assert_eq!(func.blocks[3].terminator, Terminator::Return(func.blocks[3].params[0]));
+    }
+
+    #[test]
+    fn test_lower_while() {
+        let program = lower("func main() {
+            let i := 0
+            while i < 10 do {
+                if i == 5 then break else 0
+                i := i + 1
+            }
+            i
+        }");
+        let func = &program.funcs[0];
+
+        // 入口、条件、本体、ループの後、breakとその後、ifの合流
+        assert_eq!(func.blocks.len(), 7);
+        match &func.blocks[1].terminator {
+            Terminator::Branch(_, then, else_) => {
+                assert_eq!(then.block, BlockId(2));
+                assert_eq!(else_.block, BlockId(6));
+            },
+            _ => panic!("条件で分岐していません。"),
+        }
+        assert!(func.blocks.iter().all(|block| block.terminator.targets().iter().all(|target| target.block.0 < func.blocks.len())));
+    }
+
+    #[test]
+    fn test_lower_unreachable() {
+        let program = lower("func main() {
+            return 1
+            2
+        }");
+
+        assert_eq!(program.funcs[0].blocks.len(), 1);
+    }
+
+    #[test]
+    fn test_lower_globals() {
+        let program = lower("const N := 3
+            let x := N * 2
+            let buf: [char; 3]
+            func main() x");
+
+        assert_eq!(program.code(), "const @N = 3
+global @x = 6
+global @buf: zero 3
+
+func main() {
+bb0:
+  %0 = addr @x
+  %1 = load quad %0
+  ret %1
+}
+");
+    }
+
+    fn lower_errors(src: &str) -> Vec<Diagnostic> {
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+        let root = Root::parse(&mut token_reader).ok().unwrap();
+        let types = TypeChecker::check(&root).ok().unwrap();
+
+        match Lowerer::lower(&root, &types) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors,
+        }
+    }
+
+    #[test]
+    fn test_lower_error() {
+        let errors = lower_errors("func main() break");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, code::COMPILE);
+        assert_eq!(errors[0].message, Message::BreakOutsideLoop);
+    }
+
+    #[test]
+    fn test_lower_division_by_zero() {
+        let errors = lower_errors("func main() 4 / 0");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, code::CONSTANT);
+        assert_eq!(errors[0].message, Message::DivisionByZero);
+        assert_eq!(errors[0].primary, Some(Span::new(0, 16, 1)));
+
+        // 通らない枝や return のあとでも報告する
+        let errors = lower_errors("func f(x) if false then x % 0 else x
+            func g(x) {
+                return x
+                x / 0
+            }");
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|e| e.message == Message::DivisionByZero));
+
+        // 式や定数を通して0になるものも報告する
+        let errors = lower_errors("const Z := 0
+            func main() 4 / (2 - 2) + 4 / (0) + 4 % Z");
+
+        assert_eq!(errors.len(), 3);
+        assert!(errors.iter().all(|e| e.message == Message::DivisionByZero));
+
+        // 変数を通して0になるものは、実行したときに任せる
+        assert!(lower_errors("const Z := 0
+            func main() {
+                let zero := 0
+                let Z := 1
+                4 / zero + 4 / Z
+            }").is_empty());
+    }
+}
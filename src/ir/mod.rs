@@ -0,0 +1,40 @@
+mod program;
+mod scope;
+mod constant;
+mod lower;
+mod fold;
+mod dce;
+mod inline;
+
+pub use self::program::Program;
+pub use self::program::Func;
+pub use self::program::BlockId;
+pub use self::program::Value;
+pub use self::program::Global;
+pub use self::program::GlobalContent;
+pub use self::program::Instruction;
+pub use self::program::Terminator;
+pub use self::program::Target;
+pub use self::program::BinaryOperator;
+pub use self::program::UnaryOperator;
+pub use self::program::Width;
+pub use self::lower::Lowerer;
+pub use self::fold::ConstantFolder;
+pub use self::dce::DeadCodeEliminator;
+pub use self::inline::Inliner;
+
+// ソースコードから中間表現までを通して行う。各段階のテストで使う
+#[cfg(test)]
+pub fn lower(src: &str) -> Program {
+    use parse::SyntaxTree;
+    use parse::Root;
+    use parse::TokenReader;
+    use token::tokenize;
+    use typecheck::TypeChecker;
+
+    let tokens = tokenize(&src.to_string()).unwrap();
+    let mut token_reader = TokenReader::new(&tokens);
+    let root = Root::parse(&mut token_reader).ok().unwrap();
+    let types = TypeChecker::check(&root).ok().unwrap();
+    Lowerer::lower(&root, &types).ok().unwrap()
+}
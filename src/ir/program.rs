@@ -0,0 +1,417 @@
+// 構文木と機械語の間に置く中間表現。
+// 値は一度だけ定義される仮想レジスタで持ち、関数は基本ブロックの列で表す。
+// ブロックの合流で値を受け渡すときは、ブロック引数を使う。
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Value(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+// 変数や構造体リテラルのためにスタックに確保する領域
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Slot(pub usize);
+
+impl Value {
+    pub fn code(&self) -> String {
+        format!("%{}", self.0)
+    }
+}
+
+impl BlockId {
+    pub fn code(&self) -> String {
+        format!("bb{}", self.0)
+    }
+}
+
+impl Slot {
+    pub fn code(&self) -> String {
+        format!("slot{}", self.0)
+    }
+}
+
+pub struct Program {
+    pub globals: Vec<Global>,
+    pub strings: Vec<String>,
+    pub funcs: Vec<Func>,
+}
+
+pub struct Global {
+    pub name: String,
+    pub content: GlobalContent,
+}
+
+pub enum GlobalContent {
+    Variable(i64),
+    Constant(i64),
+    // 初期値のない変数。大きさだけを持つ
+    Zero(i64),
+}
+
+#[derive(Clone)]
+pub struct Func {
+    pub name: String,
+    // 先頭のブロックの引数が関数の引数になる
+    pub blocks: Vec<Block>,
+    pub slots: Vec<StackSlot>,
+    pub values: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StackSlot {
+    pub size: i64,
+    pub align: i64,
+}
+
+#[derive(Clone)]
+pub struct Block {
+    pub id: BlockId,
+    pub params: Vec<Value>,
+    pub instructions: Vec<Instruction>,
+    pub terminator: Terminator,
+}
+
+// メモリを読み書きする幅。charは1バイトで、読むときは符号なしで拡張する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Width {
+    Byte,
+    Quad,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Sar,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOperator {
+    Neg,
+    Not,
+    // 0なら1、それ以外は0
+    LogicalNot,
+    // 下位1バイトだけを残す
+    Narrow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Const(Value, i64),
+    Unary(Value, UnaryOperator, Value),
+    Binary(Value, BinaryOperator, Value, Value),
+    SlotAddress(Value, Slot),
+    GlobalAddress(Value, String),
+    StringAddress(Value, usize),
+    Load(Value, Width, Value),
+    // アドレス、書き込む値の順
+    Store(Width, Value, Value),
+    // 書き込み先、読み込み元、バイト数の順
+    CopyMemory(Value, Value, i64),
+    Call(Value, String, Vec<Value>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub block: BlockId,
+    pub args: Vec<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Jump(Target),
+    // 0でなければ1つ目、0なら2つ目へ飛ぶ
+    Branch(Value, Target, Target),
+    Return(Value),
+}
+
+impl Program {
+    pub fn code(&self) -> String {
+        let mut lines = Vec::new();
+        for global in &self.globals {
+            lines.push(global.code());
+        }
+        for (index, content) in self.strings.iter().enumerate() {
+            lines.push(format!("string {} = {:?}", index, content));
+        }
+        let mut sections = Vec::new();
+        if !lines.is_empty() {
+            sections.push(lines.join("\n"));
+        }
+        for func in &self.funcs {
+            sections.push(func.code());
+        }
+        let mut code = sections.join("\n\n");
+        code.push('\n');
+        code
+    }
+}
+
+impl Global {
+    fn code(&self) -> String {
+        match self.content {
+            GlobalContent::Variable(value) => format!("global @{} = {}", self.name, value),
+            GlobalContent::Constant(value) => format!("const @{} = {}", self.name, value),
+            GlobalContent::Zero(size) => format!("global @{}: zero {}", self.name, size),
+        }
+    }
+}
+
+impl Func {
+    pub fn entry(&self) -> &Block {
+        &self.blocks[0]
+    }
+
+    // 先頭のブロックから飛んでこられないブロックを除き、番号を振り直す
+    pub fn remove_unreachable_blocks(&mut self) {
+        let mut reachable = HashSet::new();
+        let mut work = vec![BlockId(0)];
+        while let Some(id) = work.pop() {
+            if !reachable.insert(id) {
+                continue;
+            }
+            work.extend(self.blocks[id.0].terminator.targets().iter().map(|target| target.block));
+        }
+        self.blocks.retain(|block| reachable.contains(&block.id));
+        let numbers: HashMap<BlockId, BlockId> = self.blocks.iter().enumerate()
+            .map(|(index, block)| (block.id, BlockId(index)))
+            .collect();
+        for block in &mut self.blocks {
+            block.id = numbers[&block.id];
+            for target in block.terminator.targets_mut() {
+                target.block = numbers[&target.block];
+            }
+        }
+    }
+
+    // 飛んでくるブロックの一覧
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+        for block in &self.blocks {
+            for target in block.terminator.targets() {
+                predecessors[target.block.0].push(block.id);
+            }
+        }
+        predecessors
+    }
+
+    pub fn code(&self) -> String {
+        let mut lines = vec![format!("func {}({}) {{", self.name, Self::values_code(&self.entry().params))];
+        for (index, slot) in self.slots.iter().enumerate() {
+            lines.push(format!("  {}: size {}, align {}", Slot(index).code(), slot.size, slot.align));
+        }
+        for block in &self.blocks {
+            lines.push(block.code());
+        }
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+
+    fn values_code(values: &[Value]) -> String {
+        values.iter().map(|value| value.code()).collect::<Vec<_>>().join(", ")
+    }
+}
+
+impl Block {
+    pub fn code(&self) -> String {
+        let mut lines = vec![if self.params.is_empty() {
+            format!("{}:", self.id.code())
+        } else {
+            format!("{}({}):", self.id.code(), Func::values_code(&self.params))
+        }];
+        for instruction in &self.instructions {
+            lines.push(format!("  {}", instruction.code()));
+        }
+        lines.push(format!("  {}", self.terminator.code()));
+        lines.join("\n")
+    }
+}
+
+impl Width {
+    fn code(&self) -> &'static str {
+        match self {
+            Width::Byte => "byte",
+            Width::Quad => "quad",
+        }
+    }
+}
+
+impl BinaryOperator {
+    fn code(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "add",
+            BinaryOperator::Sub => "sub",
+            BinaryOperator::Mul => "mul",
+            BinaryOperator::Div => "div",
+            BinaryOperator::Mod => "mod",
+            BinaryOperator::And => "and",
+            BinaryOperator::Or => "or",
+            BinaryOperator::Xor => "xor",
+            BinaryOperator::Shl => "shl",
+            BinaryOperator::Sar => "sar",
+            BinaryOperator::Eq => "eq",
+            BinaryOperator::Ne => "ne",
+            BinaryOperator::Lt => "lt",
+            BinaryOperator::Le => "le",
+            BinaryOperator::Gt => "gt",
+            BinaryOperator::Ge => "ge",
+        }
+    }
+}
+
+impl UnaryOperator {
+    fn code(&self) -> &'static str {
+        match self {
+            UnaryOperator::Neg => "neg",
+            UnaryOperator::Not => "not",
+            UnaryOperator::LogicalNot => "lnot",
+            UnaryOperator::Narrow => "narrow",
+        }
+    }
+}
+
+impl Instruction {
+    pub fn code(&self) -> String {
+        match self {
+            Instruction::Const(dst, n) => format!("{} = const {}", dst.code(), n),
+            Instruction::Unary(dst, op, x) => format!("{} = {} {}", dst.code(), op.code(), x.code()),
+            Instruction::Binary(dst, op, x, y) => format!("{} = {} {}, {}", dst.code(), op.code(), x.code(), y.code()),
+            Instruction::SlotAddress(dst, slot) => format!("{} = addr {}", dst.code(), slot.code()),
+            Instruction::GlobalAddress(dst, name) => format!("{} = addr @{}", dst.code(), name),
+            Instruction::StringAddress(dst, index) => format!("{} = addr string {}", dst.code(), index),
+            Instruction::Load(dst, width, address) => format!("{} = load {} {}", dst.code(), width.code(), address.code()),
+            Instruction::Store(width, address, x) => format!("store {} {}, {}", width.code(), address.code(), x.code()),
+            Instruction::CopyMemory(dst, src, size) => format!("copy {}, {}, {}", dst.code(), src.code(), size),
+            Instruction::Call(dst, name, args) => format!("{} = call {}({})", dst.code(), name, Func::values_code(args)),
+        }
+    }
+
+    // 定義する値
+    pub fn result(&self) -> Option<Value> {
+        match self {
+            Instruction::Const(dst, _)
+            | Instruction::Unary(dst, _, _)
+            | Instruction::Binary(dst, _, _, _)
+            | Instruction::SlotAddress(dst, _)
+            | Instruction::GlobalAddress(dst, _)
+            | Instruction::StringAddress(dst, _)
+            | Instruction::Load(dst, _, _)
+            | Instruction::Call(dst, _, _) => Some(*dst),
+            Instruction::Store(_, _, _) | Instruction::CopyMemory(_, _, _) => None,
+        }
+    }
+
+    // 使う値
+    pub fn operands(&self) -> Vec<Value> {
+        match self {
+            Instruction::Const(_, _)
+            | Instruction::SlotAddress(_, _)
+            | Instruction::GlobalAddress(_, _)
+            | Instruction::StringAddress(_, _) => Vec::new(),
+            Instruction::Unary(_, _, x) | Instruction::Load(_, _, x) => vec![*x],
+            Instruction::Binary(_, _, x, y)
+            | Instruction::Store(_, x, y)
+            | Instruction::CopyMemory(x, y, _) => vec![*x, *y],
+            Instruction::Call(_, _, args) => args.clone(),
+        }
+    }
+
+    // 使う値を置き換える
+    pub fn map_operands<F: Fn(Value) -> Value>(&mut self, f: F) {
+        match self {
+            Instruction::Const(_, _)
+            | Instruction::SlotAddress(_, _)
+            | Instruction::GlobalAddress(_, _)
+            | Instruction::StringAddress(_, _) => (),
+            Instruction::Unary(_, _, x) | Instruction::Load(_, _, x) => *x = f(*x),
+            Instruction::Binary(_, _, x, y)
+            | Instruction::Store(_, x, y)
+            | Instruction::CopyMemory(x, y, _) => {
+                *x = f(*x);
+                *y = f(*y);
+            },
+            Instruction::Call(_, _, args) => {
+                for arg in args.iter_mut() {
+                    *arg = f(*arg);
+                }
+            },
+        }
+    }
+}
+
+impl Target {
+    fn code(&self) -> String {
+        if self.args.is_empty() {
+            self.block.code()
+        } else {
+            format!("{}({})", self.block.code(), Func::values_code(&self.args))
+        }
+    }
+}
+
+impl Terminator {
+    pub fn code(&self) -> String {
+        match self {
+            Terminator::Jump(target) => format!("jmp {}", target.code()),
+            Terminator::Branch(condition, then, else_) => format!("br {}, {}, {}", condition.code(), then.code(), else_.code()),
+            Terminator::Return(x) => format!("ret {}", x.code()),
+        }
+    }
+
+    pub fn targets(&self) -> Vec<&Target> {
+        match self {
+            Terminator::Jump(target) => vec![target],
+            Terminator::Branch(_, then, else_) => vec![then, else_],
+            Terminator::Return(_) => Vec::new(),
+        }
+    }
+
+    pub fn targets_mut(&mut self) -> Vec<&mut Target> {
+        match self {
+            Terminator::Jump(target) => vec![target],
+            Terminator::Branch(_, then, else_) => vec![then, else_],
+            Terminator::Return(_) => Vec::new(),
+        }
+    }
+
+    // 飛び先に渡す値も含めて、使う値
+    pub fn operands(&self) -> Vec<Value> {
+        let mut operands = match self {
+            Terminator::Branch(condition, _, _) | Terminator::Return(condition) => vec![*condition],
+            Terminator::Jump(_) => Vec::new(),
+        };
+        for target in self.targets() {
+            operands.extend(target.args.iter().cloned());
+        }
+        operands
+    }
+
+    // 飛び先に渡す値も含めて、使う値を置き換える
+    pub fn map_operands<F: Fn(Value) -> Value>(&mut self, f: F) {
+        match self {
+            Terminator::Branch(condition, _, _) | Terminator::Return(condition) => *condition = f(*condition),
+            Terminator::Jump(_) => (),
+        }
+        for target in self.targets_mut() {
+            for arg in target.args.iter_mut() {
+                *arg = f(*arg);
+            }
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use sourcecode::Code;
+use sourcecode::Span;
+
+use super::program::Slot;
+
+pub enum Location {
+    Local(Slot),
+    Global(String),
+}
+
+pub struct Scope {
+    variables: HashMap<String, Slot>,
+    globals: HashSet<String>,
+    block_stack: Vec<i64>,
+    block_seq: i64,
+}
+
+impl Scope {
+    pub fn new(globals: HashSet<String>) -> Self {
+        Self {
+            variables: HashMap::new(),
+            globals,
+            block_stack: Vec::new(),
+            block_seq: 1,
+        }
+    }
+
+    // ローカル変数が見つからなければグローバル変数とする
+    #[allow(clippy::partialeq_to_none)]
+    pub fn resolve(&self, target: &Code<String>) -> Result<Location, Span> {
+        let id = self.block_stack
+            .iter().rev()
+            .map(|i| format!("{}#{}", &target.value, i))
+            .find(|id| self.variables.get(id) != None)
+            .unwrap_or(format!("{}#{}", &target.value, 0));
+        match self.variables.get(&id) {
+            Some(&slot) => Ok(Location::Local(slot)),
+            None if self.globals.contains(&target.value) => Ok(Location::Global(target.value.clone())),
+            None => Err(target.span),
+        }
+    }
+
+    // 今いるブロックから見えるローカル変数か
+    pub fn is_local(&self, name: &str) -> bool {
+        self.block_stack
+            .iter()
+            .chain(std::iter::once(&0))
+            .any(|i| self.variables.contains_key(&format!("{}#{}", name, i)))
+    }
+
+    // 確保済みの領域を今のブロックの変数として登録する
+    pub fn declare(&mut self, target: &String, slot: Slot) -> Result<(), ()> {
+        match self.variables.insert(self.variable_id(target), slot) {
+            Some(_) => Err(()),
+            None => Ok(()),
+        }
+    }
+
+    fn variable_id(&self, name: &String) -> String {
+        format!("{}#{}", name, self.block_stack.last().unwrap_or(&0))
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    pub fn into_block(&mut self) {
+        self.block_seq += 1;
+        self.block_stack.push(self.block_seq);
+    }
+    pub fn outof_block(&mut self) {
+        self.block_stack.pop();
+    }
+}
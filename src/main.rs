@@ -1,26 +1,46 @@
-use std::cmp::max;
-use std::cmp::min;
 use std::env;
+use std::fs;
 use std::process;
 
 mod general;
-use general::TryReader;
 
 mod sourcecode;
 
-use sourcecode::Position;
-use sourcecode::Span;
+use sourcecode::Source;
+
+mod cli;
+use cli::Options;
+use cli::ErrorFormat;
+use cli::Emit;
+
+mod diagnostic;
+use diagnostic::Diagnostic;
+use diagnostic::code;
+use diagnostic::render_human;
+use diagnostic::render_json;
+use diagnostic::Message;
+use diagnostic::Lang;
 
 mod token;
 use token::tokenize;
 
 mod parse;
 use parse::SyntaxTree;
+use parse::TokenReader;
 use parse::Root;
 
+mod typecheck;
+use typecheck::TypeChecker;
+
+mod ir;
+use ir::Lowerer;
+
 mod compile;
 use compile::Compiler;
 
+mod optimize;
+use optimize::PassManager;
+
 fn main() {
     match run() {
         Exit::Success => process::exit(0),
@@ -32,84 +52,110 @@ fn main() {
 }
 
 fn run() -> Exit {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        return Exit::Failure("引数の個数が正しくありません".to_string());
-    }
-
-    let main_label = if env::var("OS").map(|var| var == "MAC".to_string()).unwrap_or(false) {
-        "_main"
-    } else {
-        "main"
+    let args: Vec<String> = env::args().skip(1).collect();
+    // --lang、環境変数LANGの順に言語を決め、どちらもなければ日本語にする
+    let env_lang = env::var("LANG").ok().and_then(|name| Lang::from_name(&name));
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            let lang = env_lang.unwrap_or(Lang::Ja);
+            return Exit::Failure(format!("{}\n{}", message.text(lang), Message::Usage.text(lang)));
+        },
     };
+    let lang = options.lang.or(env_lang).unwrap_or(Lang::Ja);
 
-    println!(".intel_syntax noprefix");
-    println!("{}", format!(".global {}", main_label));
-    println!("");
-    //println!("{}", format!("{}:", main_label));
+    let src = match Source::read(&options.input) {
+        Ok(src) => src,
+        Err(e) => return Exit::Failure(Message::CannotRead(e.to_string()).text(lang)),
+    };
 
-    let src = args[1].clone();
-    let tokens = match tokenize(&src) {
+    let tokens = match tokenize(&src.content) {
         Ok(tokens) => tokens,
-        Err(pos) => {
-            return Exit::Failure(point_error_position(&src, pos, "トークナイズできません"));
+        Err((span, message)) => {
+            let diagnostic = Diagnostic::error(code::LEXICAL, Some(span), message);
+            return Exit::Failure(report(&src, &[diagnostic], &options.error_format, lang));
         }
     };
 
-    let mut token_reader = TryReader::new(&tokens);
+    let mut token_reader = TokenReader::new(&tokens);
 
-    let root = match Root::parse(&mut token_reader) {
-        Ok(root) => root,
-        Err((Some(span), message)) => {
-            return Exit::Failure(point_error_span(&src, span, message.as_str()))
-        },
-        Err((None, message)) => {
-            return Exit::Failure(message);
+    // 読み飛ばして回復した誤りも含めて、すべてまとめて報告する
+    let parsed = Root::parse(&mut token_reader);
+    let mut diagnostics = token_reader.diagnostics();
+    if let Err((span, message)) = &parsed {
+        diagnostics.push(Diagnostic::error(code::SYNTAX, *span, message.clone()));
+    }
+    if diagnostics.iter().any(|diagnostic| diagnostic.is_error()) {
+        return Exit::Failure(report(&src, &diagnostics, &options.error_format, lang));
+    }
+    // 警告だけならコンパイルを続ける
+    if !diagnostics.is_empty() {
+        eprintln!("{}", report(&src, &diagnostics, &options.error_format, lang));
+    }
+    let root = parsed.ok().unwrap();
+
+    let types = match TypeChecker::check(&root) {
+        Ok(types) => types,
+        Err(errors) => return Exit::Failure(report(&src, &errors, &options.error_format, lang)),
+    };
+
+    let program = match Lowerer::lower(&root, &types) {
+        Ok(program) => program,
+        Err(errors) => return Exit::Failure(report(&src, &errors, &options.error_format, lang)),
+    };
+
+    let manager = match options.passes {
+        Some(passes) => PassManager::new(passes),
+        None => PassManager::for_level(options.opt_level),
+    }.print_after(options.print_after).time_passes(options.time_passes);
+
+    let program = manager.run_ir(program);
+
+    let output = match options.emit {
+        Emit::Ir => program.code(),
+        Emit::Assembly => {
+            let mut compiler = Compiler::compile(&program);
+            manager.run_assembly(&mut compiler);
+            assembly(&compiler)
         },
     };
 
-    match Compiler::compile(&root) {
-        Ok(compiler) => {
-            println!("{}", compiler.assembly_string());
+    match options.output {
+        Some(path) => {
+            if let Err(e) = fs::write(&path, output) {
+                return Exit::Failure(Message::CannotWrite(path, e.to_string()).text(lang));
+            }
         },
-        Err((span, message)) => {
-            return Exit::Failure(point_error_span(&src, span, message.as_str()))
-        }
+        None => print!("{}", output),
     }
     Exit::Success
 }
 
+#[allow(clippy::cmp_owned)]
+fn assembly(compiler: &Compiler) -> String {
+    let main_label = if env::var("OS").map(|var| var == "MAC".to_string()).unwrap_or(false) {
+        "_main"
+    } else {
+        "main"
+    };
 
-fn point_error_position(src: &String, position: Position, message: &str) -> String {
-    let mut err = src.as_str().split("\n").nth(position.line).unwrap().to_string();
-    err.push('\n');
-    err.push('\n');
-    err.push('\n');
-    err.push('\n');
-    err.push_str(format!("{}^{}", " ".to_string().repeat(position.pos).as_str(), message).as_str());
-    err
+    let mut assembly = String::new();
+    assembly.push_str(".intel_syntax noprefix\n");
+    assembly.push_str(&format!(".global {}\n", main_label));
+    assembly.push('\n');
+    assembly.push_str(&compiler.assembly_string());
+    assembly.push('\n');
+    assembly
 }
 
-fn point_error_span(src: &String, span: Span, message: &str) -> String {
-    let mut err = String::new();
-    let Span{start, end} = span;
-    let lines: Vec::<&str> = src.as_str().split("\n").collect();
-    for i in start.line..end.line+1 {
-        let line = lines[i];
-        err.push_str(line);
-        err.push('\n');
-        let line_start = Position{ line: i, pos: 0 };
-        let line_end = Position{ line: i, pos: line.len() };
-        let indicator_span = Span{ start: max(start, line_start), end: min(end, line_end) };
-        let indicator_length = indicator_span.end.pos - indicator_span.start.pos;
-        err.push_str(format!("{}{}",
-            " ".to_string().repeat(indicator_span.start.pos).as_str(),
-            "^".to_string().repeat(indicator_length).as_str(),
-        ).as_str());
-
-    }
-    err.push_str(message);
-    err
+fn report(src: &Source, diagnostics: &[Diagnostic], format: &ErrorFormat, lang: Lang) -> String {
+    diagnostics.iter()
+        .map(|diagnostic| match format {
+            ErrorFormat::Human => render_human(src, diagnostic, lang),
+            ErrorFormat::Json => render_json(src, diagnostic, lang),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 enum Exit {
@@ -0,0 +1,116 @@
+use std::time::Instant;
+
+use cli::OptLevel;
+
+use ir::Program;
+use ir::ConstantFolder;
+use ir::DeadCodeEliminator;
+use ir::Inliner;
+
+use compile::Compiler;
+use compile::Peephole;
+
+use super::Pass;
+
+// 最適化の段階を順にかける。
+// 中間表現にかける段階をすべて終えてから、アセンブリにかける段階に進む
+pub struct PassManager {
+    passes: Vec<Pass>,
+    print_after: Option<Pass>,
+    time_passes: bool,
+}
+
+impl PassManager {
+    pub fn new(passes: Vec<Pass>) -> Self {
+        Self{passes, print_after: None, time_passes: false}
+    }
+
+    // -O0では何もかけない
+    pub fn for_level(level: OptLevel) -> Self {
+        let passes = match level {
+            OptLevel::O0 => vec![],
+            OptLevel::O1 => vec![Pass::Fold, Pass::DeadCode, Pass::Peephole],
+            // 展開した関数の中も畳み込めるよう、もう一度畳み込む
+            OptLevel::O2 => vec![Pass::Fold, Pass::Inline, Pass::Fold, Pass::DeadCode, Pass::Peephole],
+        };
+        Self::new(passes)
+    }
+
+    // 指定した段階をかけるたびに、その結果を標準エラー出力に書き出す
+    pub fn print_after(self, pass: Option<Pass>) -> Self {
+        Self{print_after: pass, ..self}
+    }
+
+    // 段階ごとにかかった時間を標準エラー出力に書き出す
+    pub fn time_passes(self, time_passes: bool) -> Self {
+        Self{time_passes, ..self}
+    }
+
+    pub fn run_ir(&self, mut program: Program) -> Program {
+        for &pass in self.passes.iter().filter(|pass| pass.is_ir()) {
+            let start = Instant::now();
+            program = match pass {
+                Pass::Fold => ConstantFolder::fold(program),
+                Pass::DeadCode => DeadCodeEliminator::eliminate(program),
+                Pass::Inline => Inliner::inline(program),
+                Pass::Peephole => unreachable!(),
+            };
+            self.report(pass, start, || program.code());
+        }
+        program
+    }
+
+    pub fn run_assembly(&self, compiler: &mut Compiler) {
+        for &pass in self.passes.iter().filter(|pass| !pass.is_ir()) {
+            let start = Instant::now();
+            compiler.optimize(&Peephole::standard());
+            self.report(pass, start, || compiler.assembly_string());
+        }
+    }
+
+    fn report<F: FnOnce() -> String>(&self, pass: Pass, start: Instant, code: F) {
+        if self.time_passes {
+            eprintln!("{}: {:.3}ms", pass.name(), start.elapsed().as_secs_f64() * 1000.0);
+        }
+        if self.print_after == Some(pass) {
+            eprintln!("--- {} ---\n{}", pass.name(), code());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ir::lower;
+    use ir::Instruction;
+    use ir::Terminator;
+
+    #[test]
+    fn test_for_level() {
+        assert!(PassManager::for_level(OptLevel::O0).passes.is_empty());
+        assert_eq!(PassManager::for_level(OptLevel::O1).passes, vec![Pass::Fold, Pass::DeadCode, Pass::Peephole]);
+        assert!(PassManager::for_level(OptLevel::O2).passes.contains(&Pass::Inline));
+    }
+
+    #[test]
+    fn test_run_ir() {
+        let src = "func square(x) x * x
+            func main() square(3) + 1";
+
+        let program = PassManager::for_level(OptLevel::O2).run_ir(lower(src));
+        let main = &program.funcs[1];
+
+        // 展開して畳み込むと、定数を返すだけになる
+        assert_eq!(main.blocks.len(), 1);
+        match main.blocks[0].instructions.as_slice() {
+            [Instruction::Const(value, 10)] => assert_eq!(main.blocks[0].terminator, Terminator::Return(*value)),
+            instructions => panic!("{:?}", instructions),
+        }
+
+        // -O0では呼び出しを残す
+        let program = PassManager::for_level(OptLevel::O0).run_ir(lower(src));
+
+        assert!(program.funcs[1].blocks[0].instructions.iter().any(|instruction| matches!(instruction, Instruction::Call(_, _, _))));
+    }
+}
@@ -0,0 +1,5 @@
+mod pass;
+mod manager;
+
+pub use self::pass::Pass;
+pub use self::manager::PassManager;
@@ -0,0 +1,33 @@
+// 最適化の段階。のぞき穴最適化だけはアセンブリに、ほかは中間表現にかける
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pass {
+    Fold,
+    DeadCode,
+    Inline,
+    Peephole,
+}
+
+impl Pass {
+    pub fn from_name(name: &str) -> Option<Pass> {
+        match name {
+            "fold" => Some(Pass::Fold),
+            "dce" => Some(Pass::DeadCode),
+            "inline" => Some(Pass::Inline),
+            "peephole" => Some(Pass::Peephole),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pass::Fold => "fold",
+            Pass::DeadCode => "dce",
+            Pass::Inline => "inline",
+            Pass::Peephole => "peephole",
+        }
+    }
+
+    pub fn is_ir(&self) -> bool {
+        !matches!(self, Pass::Peephole)
+    }
+}
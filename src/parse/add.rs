@@ -1,15 +1,15 @@
 use std::collections::HashSet;
 use std::iter::FromIterator;
 
-use general::TryReader;
-
 use sourcecode::Span;
 use sourcecode::Code;
 
+use diagnostic::Message;
+
 use token::Operator;
-use token::Token;
 
 use parse::SyntaxTree;
+use parse::TokenReader;
 use parse::BinaryOperation;
 use parse::Multiply;
 
@@ -28,8 +28,9 @@ impl Add {
 }
 
 impl SyntaxTree for Add {
-    fn parse(mut token_reader: &mut TryReader<Code<Token>>)
-    -> Result<Add, (Option<Span>, String)> {
+    #[allow(clippy::needless_borrow, clippy::useless_conversion)]
+    fn parse(mut token_reader: &mut TokenReader)
+    -> Result<Add, (Option<Span>, Message)> {
         let operators = HashSet::from_iter(vec![Operator::Add, Operator::Sub].into_iter());
         BinaryOperation::parse(&mut token_reader, &operators)
         .map(|binary_operation| Add {binary_operation})
@@ -51,7 +52,7 @@ mod tests {
 
         let tokens = tokenize(&src.to_string()).unwrap();
 
-        let mut token_reader = TryReader::new(&tokens);
+        let mut token_reader = TokenReader::new(&tokens);
 
         let add = Add::parse(&mut token_reader).unwrap();
         let mut tail = add.tail();
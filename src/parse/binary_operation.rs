@@ -1,15 +1,17 @@
 use std::collections::HashSet;
 
 use general::SemiGroup;
-use general::TryReader;
 
 use sourcecode::Code;
 use sourcecode::Span;
 
+use diagnostic::Message;
+
 use token::Token;
 use token::Operator;
 
 use parse::SyntaxTree;
+use parse::TokenReader;
 
 pub enum BinaryOperation<Element: SyntaxTree> {
     Single(Element),
@@ -21,8 +23,9 @@ pub enum BinaryOperation<Element: SyntaxTree> {
 }
 
 impl <Element: SyntaxTree> BinaryOperation<Element> {
-    pub fn parse(mut token_reader: &mut TryReader<Code<Token>>, operators: &HashSet<Operator>)
-    -> Result<Self, (Option<Span>, String)> {
+    #[allow(clippy::needless_borrow, clippy::question_mark)]
+    pub fn parse(mut token_reader: &mut TokenReader, operators: &HashSet<Operator>)
+    -> Result<Self, (Option<Span>, Message)> {
         let left = match Element::parse(&mut token_reader) {
             Ok(element) => element,
             Err(err) => return Err(err),
@@ -46,6 +49,7 @@ impl <Element: SyntaxTree> BinaryOperation<Element> {
         }
     }
 
+    #[allow(clippy::needless_borrow)]
     pub fn head(&self) -> &Element {
         match self {
             Self::Single(element) => &element,
@@ -57,7 +61,7 @@ impl <Element: SyntaxTree> BinaryOperation<Element> {
         let mut tail = Vec::new();
         let mut node = self;
         while let Self::Binary { left: _, right, operator } = node {
-            tail.push((operator.clone(), right.head()));
+            tail.push((operator, right.head()));
             node = right;
         }
         tail.into_iter()
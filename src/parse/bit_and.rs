@@ -0,0 +1,46 @@
+use std::collections::HashSet;
+use std::iter::FromIterator;
+
+use sourcecode::Code;
+use sourcecode::Span;
+
+use diagnostic::Message;
+
+use token::Operator;
+
+use parse::SyntaxTree;
+use parse::TokenReader;
+use parse::BinaryOperation;
+use parse::Equality;
+
+pub struct BitAnd {
+    binary_operation: BinaryOperation<Equality>,
+}
+
+impl BitAnd {
+    pub fn head(&self) -> &Equality {
+        self.binary_operation.head()
+    }
+
+    pub fn tail(&self) -> impl Iterator<Item = (&Code<Operator>, &Equality)> {
+        self.binary_operation.tail()
+    }
+
+    fn operators() -> HashSet<Operator> {
+        HashSet::from_iter(vec![
+            Operator::Ampersand,
+        ])
+    }
+}
+
+impl SyntaxTree for BitAnd {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<BitAnd, (Option<Span>, Message)> {
+        BinaryOperation::parse(token_reader, &Self::operators())
+        .map(|binary_operation| BitAnd{ binary_operation })
+    }
+
+    fn span(&self) -> Span {
+        self.binary_operation.span()
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+use std::iter::FromIterator;
+
+use sourcecode::Code;
+use sourcecode::Span;
+
+use diagnostic::Message;
+
+use token::Operator;
+
+use parse::SyntaxTree;
+use parse::TokenReader;
+use parse::BinaryOperation;
+use parse::BitXor;
+
+pub struct BitOr {
+    binary_operation: BinaryOperation<BitXor>,
+}
+
+impl BitOr {
+    pub fn head(&self) -> &BitXor {
+        self.binary_operation.head()
+    }
+
+    pub fn tail(&self) -> impl Iterator<Item = (&Code<Operator>, &BitXor)> {
+        self.binary_operation.tail()
+    }
+
+    fn operators() -> HashSet<Operator> {
+        HashSet::from_iter(vec![
+            Operator::BitOr,
+        ])
+    }
+}
+
+impl SyntaxTree for BitOr {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<BitOr, (Option<Span>, Message)> {
+        BinaryOperation::parse(token_reader, &Self::operators())
+        .map(|binary_operation| BitOr{ binary_operation })
+    }
+
+    fn span(&self) -> Span {
+        self.binary_operation.span()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use token::tokenize;
+
+    #[test]
+    fn test_parse_bit_or() {
+        let src = "a | b ^ c & d == e";
+
+        let tokens = tokenize(&src.to_string()).unwrap();
+
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let bit_or = BitOr::parse(&mut token_reader).unwrap();
+        let (operator, bit_xor) = bit_or.tail().next().unwrap();
+        assert_eq!(operator.value, Operator::BitOr);
+
+        let (operator, bit_and) = bit_xor.tail().next().unwrap();
+        assert_eq!(operator.value, Operator::BitXor);
+
+        let (operator, equality) = bit_and.tail().next().unwrap();
+        assert_eq!(operator.value, Operator::Ampersand);
+        assert_eq!(equality.span(), Span::new(0, 12, 6));
+    }
+}
@@ -0,0 +1,46 @@
+use std::collections::HashSet;
+use std::iter::FromIterator;
+
+use sourcecode::Code;
+use sourcecode::Span;
+
+use diagnostic::Message;
+
+use token::Operator;
+
+use parse::SyntaxTree;
+use parse::TokenReader;
+use parse::BinaryOperation;
+use parse::BitAnd;
+
+pub struct BitXor {
+    binary_operation: BinaryOperation<BitAnd>,
+}
+
+impl BitXor {
+    pub fn head(&self) -> &BitAnd {
+        self.binary_operation.head()
+    }
+
+    pub fn tail(&self) -> impl Iterator<Item = (&Code<Operator>, &BitAnd)> {
+        self.binary_operation.tail()
+    }
+
+    fn operators() -> HashSet<Operator> {
+        HashSet::from_iter(vec![
+            Operator::BitXor,
+        ])
+    }
+}
+
+impl SyntaxTree for BitXor {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<BitXor, (Option<Span>, Message)> {
+        BinaryOperation::parse(token_reader, &Self::operators())
+        .map(|binary_operation| BitXor{ binary_operation })
+    }
+
+    fn span(&self) -> Span {
+        self.binary_operation.span()
+    }
+}
@@ -1,15 +1,15 @@
 use std::collections::HashSet;
 use std::iter::FromIterator;
 
-use general::TryReader;
-
 use sourcecode::Code;
 use sourcecode::Span;
 
+use diagnostic::Message;
+
 use token::Operator;
-use token::Token;
 
 use parse::SyntaxTree;
+use parse::TokenReader;
 use parse::BinaryOperation;
 use parse::Relational;
 
@@ -26,6 +26,7 @@ impl Equality {
         self.binary_operation.tail()
     }
 
+    #[allow(clippy::useless_conversion)]
     fn operators() -> HashSet<Operator> {
         HashSet::from_iter(vec![
             Operator::Equal,
@@ -35,8 +36,9 @@ impl Equality {
 }
 
 impl SyntaxTree for Equality {
-    fn parse(mut token_reader: &mut TryReader<Code<Token>>)
-    -> Result<Equality, (Option<Span>, String)> {
+    #[allow(clippy::needless_borrow)]
+    fn parse(mut token_reader: &mut TokenReader)
+    -> Result<Equality, (Option<Span>, Message)> {
         BinaryOperation::parse(&mut token_reader, &Self::operators())
         .map(|binary_operation| Equality{ binary_operation })
     }
@@ -57,7 +59,7 @@ mod tests {
 
         let tokens = tokenize(&src.to_string()).unwrap();
 
-        let mut token_reader = TryReader::new(&tokens);
+        let mut token_reader = TokenReader::new(&tokens);
 
         let add = Equality::parse(&mut token_reader).unwrap();
         let mut tail = add.tail();
@@ -71,7 +73,7 @@ mod tests {
 
         let tokens = tokenize(&src.to_string()).unwrap();
 
-        let mut token_reader = TryReader::new(&tokens);
+        let mut token_reader = TokenReader::new(&tokens);
 
         let add = Equality::parse(&mut token_reader).unwrap();
         let mut tail = add.tail();
@@ -1,8 +1,10 @@
-use general::TryReader;
 use::general::SemiGroup;
 
 use sourcecode::Span;
-use sourcecode::Code;
+
+use diagnostic::Diagnostic;
+use diagnostic::code;
+use diagnostic::Message;
 
 use token::Token;
 use token::BracketSide;
@@ -10,20 +12,36 @@ use token::Bracket;
 use token::ReservedWord;
 
 use parse::SyntaxTree;
+use parse::TokenReader;
 use parse::Statement;
-use parse::Equality;
+use parse::LogicalOr;
 
 pub enum Expression {
     PureExpression(PureExpression),
     IfExpression(IfExpression),
+    WhileExpression(WhileExpression),
     BlockExpression(BlockExpression),
+    Break(Span),
+    Continue(Span),
 }
 
 impl SyntaxTree for Expression {
-    fn parse(mut token_reader: &mut TryReader<Code<Token>>)
-    -> Result<Expression, (Option<Span>, String)> {
-        match token_reader.try_(|reader| IfExpression::parse(reader)) {
-            Ok((_, expr)) => return Ok(Self::IfExpression(expr)),
+    #[allow(clippy::needless_borrow, clippy::single_match)]
+    fn parse(mut token_reader: &mut TokenReader)
+    -> Result<Expression, (Option<Span>, Message)> {
+        // if と while で始まる式は種類が決まるので、その式としての誤りを返す
+        match token_reader.peek().map(|token| &token.value) {
+            Some(Token::ReservedWord(ReservedWord::If)) => return IfExpression::parse(token_reader).map(Self::IfExpression),
+            Some(Token::ReservedWord(ReservedWord::While)) => return WhileExpression::parse(token_reader).map(Self::WhileExpression),
+            _ => (),
+        }
+
+        match token_reader.try_next(|token| match token.value {
+            Token::ReservedWord(ReservedWord::Break) => Ok(Self::Break(token.span)),
+            Token::ReservedWord(ReservedWord::Continue) => Ok(Self::Continue(token.span)),
+            _ => Err(()),
+        }) {
+            Ok(expr) => return Ok(expr),
             _ => (),
         }
 
@@ -40,24 +58,27 @@ impl SyntaxTree for Expression {
         match &self {
             Self::PureExpression(expr) => expr.span(),
             Self::IfExpression(expr) => expr.span(),
+            Self::WhileExpression(expr) => expr.span(),
             Self::BlockExpression(expr) => expr.span(),
+            Self::Break(span) => *span,
+            Self::Continue(span) => *span,
         }
     }
 }
 
 pub struct PureExpression {
-    pub equality: Equality,
+    pub logical_or: LogicalOr,
 }
 
 impl SyntaxTree for PureExpression {
-    fn parse(mut token_reader: &mut TryReader<Code<Token>>)
-    -> Result<PureExpression, (Option<Span>, String)> {
-        Equality::parse(&mut token_reader)
-        .map(|equality| Self {equality})
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<PureExpression, (Option<Span>, Message)> {
+        LogicalOr::parse(token_reader)
+        .map(|logical_or| Self {logical_or})
     }
 
     fn span(&self) -> Span {
-        self.equality.span()
+        self.logical_or.span()
     }
 }
 
@@ -68,14 +89,15 @@ pub struct IfExpression {
 }
 
 impl SyntaxTree for IfExpression {
-    fn parse(mut token_reader: &mut TryReader<Code<Token>>)
-    -> Result<IfExpression, (Option<Span>, String)> {
+    #[allow(clippy::needless_borrow, clippy::question_mark)]
+    fn parse(mut token_reader: &mut TokenReader)
+    -> Result<IfExpression, (Option<Span>, Message)> {
         match token_reader.next() {
             Some(token) => match token.value {
                 Token::ReservedWord(ReservedWord::If) => (),
-                _ => return Err((Some(token.span), "ifを期待していました".to_string())),
+                _ => return Err((Some(token.span), Message::Expected("if"))),
             },
-            _ => return Err((None, "ifを期待していました".to_string())),
+            _ => return Err((None, Message::Expected("if"))),
         };
         let condition = match Expression::parse(&mut token_reader) {
             Ok(expression) => expression,
@@ -85,9 +107,9 @@ impl SyntaxTree for IfExpression {
         match token_reader.next() {
             Some(token) => match token.value {
                 Token::ReservedWord(ReservedWord::Then) => (),
-                _ => return Err((Some(token.span), "thenを期待していました".to_string())),
+                _ => return Err((Some(token.span), Message::Expected("then"))),
             },
-            _ => return Err((None, "thenを期待していました".to_string())),
+            _ => return Err((None, Message::Expected("then"))),
         };
         let then = match Expression::parse(&mut token_reader) {
             Ok(expression) => expression,
@@ -97,9 +119,9 @@ impl SyntaxTree for IfExpression {
         match token_reader.next() {
             Some(token) => match token.value {
                 Token::ReservedWord(ReservedWord::Else) => (),
-                _ => return Err((Some(token.span), "elseを期待していました".to_string())),
+                _ => return Err((Some(token.span), Message::Expected("else"))),
             },
-            _ => return Err((None, "elseを期待していました".to_string())),
+            _ => return Err((None, Message::Expected("else"))),
         };
         let else_ = match Expression::parse(&mut token_reader) {
             Ok(expression) => expression,
@@ -120,16 +142,87 @@ impl SyntaxTree for IfExpression {
     }
 }
 
+pub struct WhileExpression {
+    pub condition: Box<Expression>,
+    pub body: Box<Expression>,
+    while_span: Span,
+}
+
+impl SyntaxTree for WhileExpression {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<WhileExpression, (Option<Span>, Message)> {
+        let while_span = match token_reader.next() {
+            Some(token) => match token.value {
+                Token::ReservedWord(ReservedWord::While) => token.span,
+                _ => return Err((Some(token.span), Message::Expected("while"))),
+            },
+            _ => return Err((None, Message::Expected("while"))),
+        };
+        let condition = Expression::parse(token_reader)?;
+        token_reader.drop_while(|token| token.value == Token::LineBreak);
+        match token_reader.next() {
+            Some(token) => match token.value {
+                Token::ReservedWord(ReservedWord::Do) => (),
+                _ => return Err((Some(token.span), Message::Expected("do"))),
+            },
+            _ => return Err((None, Message::Expected("do"))),
+        };
+        let body = Expression::parse(token_reader)?;
+        Ok(Self{
+            condition: Box::new(condition),
+            body: Box::new(body),
+            while_span,
+        })
+    }
+
+    fn span(&self) -> Span {
+        self.while_span.plus(&self.body.span())
+    }
+}
+
 pub struct BlockExpression {
     pub statements: Vec::<Statement>,
-    pub outcome: Box<Expression>,
+    // 値となる式がないブロックは0を値とする
+    pub outcome: Option<Box<Expression>>,
     open: Span,
     close: Span,
 }
 
+impl BlockExpression {
+    // return の直後の文だけを指して警告する
+    fn warn_unreachable(token_reader: &TokenReader, statements: &[Statement], span: Span) {
+        if let Some(Statement::Return(return_)) = statements.last() {
+            token_reader.warn(
+                Diagnostic::warning(code::UNREACHABLE, Some(span), Message::Unreachable)
+                .with_secondary(return_.span(), Message::ReturnsHere)
+            );
+        }
+    }
+
+    // 中括弧の対応を見ながら行末まで読み飛ばす。このブロックを閉じる } と次の関数の手前では止まる
+    // 丸括弧や角括弧は閉じ忘れると行末で止まれなくなるので数えない
+    fn skip_line(token_reader: &mut TokenReader) {
+        let mut depth = 0;
+        while let Some(token) = token_reader.peek() {
+            match token.value {
+                Token::LineBreak if depth == 0 => {
+                    token_reader.next();
+                    return;
+                },
+                Token::Bracket(BracketSide::Right(Bracket::Curly)) if depth == 0 => return,
+                Token::ReservedWord(ReservedWord::Func) => return,
+                Token::Bracket(BracketSide::Left(Bracket::Curly)) => depth += 1,
+                Token::Bracket(BracketSide::Right(Bracket::Curly)) => depth -= 1,
+                _ => (),
+            }
+            token_reader.next();
+        }
+    }
+}
+
 impl SyntaxTree for BlockExpression {
-    fn parse(token_reader: &mut TryReader<Code<Token>>)
-    -> Result<Self, (Option<Span>, String)> {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<Self, (Option<Span>, Message)> {
         let open = match token_reader.try_next(|token| {
             match token.value {
                 Token::Bracket(BracketSide::Left(Bracket::Curly)) => Ok(token.span),
@@ -137,34 +230,50 @@ impl SyntaxTree for BlockExpression {
             }
         }) {
             Ok(span) => span,
-            Err(e) => return Err((e, "{ を期待していました".to_string())),
+            Err(e) => return Err((e, Message::Expected("{"))),
         };
 
-        token_reader.drop_while(|token| token.value == Token::LineBreak);
-
         let mut statements = Vec::new();
-        while let Ok((_, statement)) = token_reader.try_(|reader| Statement::parse(reader)) {
-            statements.push(statement)
+        loop {
+            token_reader.drop_while(|token| token.value == Token::LineBreak);
+            match token_reader.peek() {
+                Some(token) if token.value == Token::Bracket(BracketSide::Right(Bracket::Curly)) => break,
+                None => break,
+                _ => (),
+            }
+            // 行ごとに一度だけ読む。読み直すと入れ子の深さに対して指数的に遅くなる
+            match token_reader.try_(Statement::parse) {
+                Ok((_, statement)) => {
+                    Self::warn_unreachable(token_reader, &statements, statement.span());
+                    statements.push(statement);
+                },
+                // 読めない行は誤りとして記録し、次の行から読み直す
+                Err(e) => {
+                    token_reader.report(e);
+                    Self::skip_line(token_reader);
+                },
+            }
         }
 
-        token_reader.drop_while(|token| token.value == Token::LineBreak);
-
-        let outcome = Box::new(match token_reader.try_(|reader| Expression::parse(reader)) {
-            Ok((_, expr)) => expr,
-            Err(e) => return Err(e),
-        });
-
-        token_reader.drop_while(|token| token.value == Token::LineBreak);
+        // 最後の式文をブロックの値とする
+        let outcome = match statements.pop() {
+            Some(Statement::Expression(expr)) => Some(Box::new(expr)),
+            Some(statement) => {
+                statements.push(statement);
+                None
+            },
+            None => None,
+        };
 
         let close = match token_reader.try_next(|token| {
             match token.value {
                 Token::Bracket(BracketSide::Right(Bracket::Curly)) => Ok(token.span),
-                _ => Err((Some(token.span), "} を期待していました".to_string())),
+                _ => Err((Some(token.span), Message::Expected("}"))),
             }
         }) {
             Ok(span) => span,
             Err(Some(e)) => return Err(e),
-            _ => return Err((None, "ブロックを期待しいていました".to_string())),
+            _ => return Err((None, Message::ExpectedBlock)),
         };
 
         Ok(Self {
@@ -176,8 +285,100 @@ impl SyntaxTree for BlockExpression {
     }
 
     fn span(&self) -> Span {
-        self.statements.iter().fold(self.open, |acc, s| acc.plus(&s.span()))
-            .plus(&self.outcome.span())
-            .plus(&self.close)
+        let span = self.statements.iter().fold(self.open, |acc, s| acc.plus(&s.span()));
+        match &self.outcome {
+            Some(outcome) => span.plus(&outcome.span()).plus(&self.close),
+            None => span.plus(&self.close),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use token::tokenize;
+
+    #[test]
+    fn test_parse_while() {
+        let src = "while i < 10 do {
+            i := i + 1
+            if i == 5 then break else continue
+        }";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let expression = Expression::parse(&mut token_reader).unwrap();
+
+        if let Expression::WhileExpression(while_) = expression {
+            if let Expression::BlockExpression(block) = *while_.body {
+                assert_eq!(block.statements.len(), 1);
+                if let Some(&Expression::IfExpression(_)) = block.outcome.as_ref().map(|o| o.as_ref()) {
+                } else {
+                    panic!("ブロックの値がifになっていません。")
+                }
+            } else {
+                panic!("本体がブロックになっていません。")
+            }
+        } else {
+            panic!("whileになっていません。")
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_block_with_expression_statement() {
+        let src = "{
+            f(1)
+            2
+        }";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let block = BlockExpression::parse(&mut token_reader).unwrap();
+
+        assert_eq!(block.statements.len(), 1);
+        if let Statement::Expression(_) = block.statements[0] {
+        } else {
+            panic!("式文になっていません。")
+        }
+    }
+
+    #[test]
+    fn test_parse_block_without_outcome() {
+        let src = "{
+            let x := 1
+            x := 2
+        }";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let block = BlockExpression::parse(&mut token_reader).unwrap();
+
+        assert_eq!(block.statements.len(), 2);
+        assert!(block.outcome.is_none());
+    }
+
+    #[test]
+    fn test_parse_block_recovery() {
+        let src = "{
+            let := 1
+            x := (1 + 2
+            if x then {
+                y := *
+            } else 0
+            x
+        }";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let block = BlockExpression::parse(&mut token_reader).unwrap();
+
+        assert!(block.outcome.is_some());
+        let spans: Vec<Option<Span>> = token_reader.diagnostics().iter().map(|d| d.primary).collect();
+        assert_eq!(spans, vec![
+            Some(Span::new(1, 16, 2)),
+            Some(Span::new(2, 23, 1)),
+            Some(Span::new(4, 22, 1)),
+        ]);
+    }
+}
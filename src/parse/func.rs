@@ -1,64 +1,84 @@
-use general::TryReader;
-
 use sourcecode::Span;
 use sourcecode::Code;
 
+use diagnostic::Message;
+
 use token::Token;
 use token::Bracket;
 use token::BracketSide;
 use token::ReservedWord;
 
 use parse::SyntaxTree;
+use parse::TokenReader;
 use parse::Expression;
+use parse::TypeExpression;
 
 pub struct Func {
     pub name: String,
-    pub args: Vec<String>,
+    pub args: Vec<Argument>,
+    pub return_type: Option<TypeExpression>,
     pub body: Expression,
+    name_span: Span,
     span: Span,
 }
 
+impl Func {
+    pub fn name_span(&self) -> Span {
+        self.name_span
+    }
+}
+
+pub struct Argument {
+    pub name: Code<String>,
+    pub type_annotation: Option<TypeExpression>,
+}
+
 impl SyntaxTree for Func {
-    fn parse(token_reader: &mut TryReader<Code<Token>>) -> Result<Self, (Option<Span>, String)> {
+    #[allow(clippy::question_mark, clippy::redundant_field_names, clippy::redundant_pattern_matching)]
+    fn parse(token_reader: &mut TokenReader) -> Result<Self, (Option<Span>, Message)> {
         let start = if let Some(token) = token_reader.next() {
             match &token.value {
                 Token::ReservedWord(ReservedWord::Func) => token.span.start,
-                _ => return Err((Some(token.span), "funcを期待していました".to_string()))
+                _ => return Err((Some(token.span), Message::Expected("func")))
             }
         } else {
-            return Err((None, "funcを期待していました".to_string()));
+            return Err((None, Message::Expected("func")));
         };
 
-        let name = if let Some(token) = token_reader.next() {
+        let (name, name_span) = if let Some(token) = token_reader.next() {
             match &token.value {
-                Token::Identifier(name) => name.clone(),
-                _ => return Err((Some(token.span), "識別子を期待していました".to_string()))
+                Token::Identifier(name) => (name.clone(), token.span),
+                _ => return Err((Some(token.span), Message::ExpectedIdentifier))
             }
         } else {
-            return Err((None, "識別子を期待していました".to_string()));
+            return Err((None, Message::ExpectedIdentifier));
         };
 
         if let Some(token) = token_reader.next() {
             match &token.value {
                 Token::Bracket(BracketSide::Left(Bracket::Round)) => name.clone(),
-                _ => return Err((Some(token.span), "(を期待していました".to_string()))
+                _ => return Err((Some(token.span), Message::Expected("(")))
             }
         } else {
-            return Err((None, "(を期待していました".to_string()));
+            return Err((None, Message::Expected("(")));
         };
 
         let mut args = Vec::new();
-        for _ in 0..6 {
+        loop {
             let next = token_reader.try_next(|token| match &token.value {
-                Token::Identifier(name) => Ok(name.clone()),
+                Token::Identifier(name) => Ok(token.map_const(name.clone())),
                 _ => Err(()),
             });
-            let arg = if let Ok(name) = next {
+            let name = if let Ok(name) = next {
                 name
             } else {
                 break;
             };
-            args.push(arg);
+            let type_annotation = match TypeExpression::parse_annotation(token_reader) {
+                Ok(annotation) => annotation,
+                Err(e) => return Err(e),
+            };
+            args.push(Argument { name, type_annotation });
             if let Err(_) = token_reader.try_(
                 |reader| match reader.next().map(|c| &c.value) {
                     Some(Token::Comma) => Ok(()),
@@ -72,10 +92,15 @@ impl SyntaxTree for Func {
         if let Some(token) = token_reader.next() {
             match &token.value {
                 Token::Bracket(BracketSide::Right(Bracket::Round)) => name.clone(),
-                _ => return Err((Some(token.span), ")を期待していました".to_string()))
+                _ => return Err((Some(token.span), Message::Expected(")")))
             }
         } else {
-            return Err((None, ")を期待していました".to_string()));
+            return Err((None, Message::Expected(")")));
+        };
+
+        let return_type = match TypeExpression::parse_annotation(token_reader) {
+            Ok(annotation) => annotation,
+            Err(e) => return Err(e),
         };
 
         let body = match Expression::parse(token_reader) {
@@ -91,11 +116,14 @@ impl SyntaxTree for Func {
         Ok(Self {
             name,
             args,
+            return_type,
             body,
+            name_span,
             span,
         })
     }
 
+    #[allow(clippy::clone_on_copy)]
     fn span(&self) -> Span {
         self.span.clone()
     }
@@ -113,25 +141,56 @@ mod tests {
             a + b
         }";
         let tokens = tokenize(&src.to_string()).unwrap();
-        let mut token_reader = TryReader::new(&tokens);
+        let mut token_reader = TokenReader::new(&tokens);
 
         let func = Func::parse(&mut token_reader).unwrap();
 
         assert_eq!(func.name, "foo".to_string());
+        assert_eq!(func.name_span(), Span::new(0, 5, 3));
         assert_eq!(func.args.len(), 2);
-        assert_eq!(func.args[0], "a".to_string());
-        assert_eq!(func.args[1], "b".to_string());
+        assert_eq!(func.args[0].name.value, "a".to_string());
+        assert_eq!(func.args[1].name.value, "b".to_string());
     }
 
     #[test]
     fn test_parse_zero_arg() {
         let src = "func main() 0";
         let tokens = tokenize(&src.to_string()).unwrap();
-        let mut token_reader = TryReader::new(&tokens);
+        let mut token_reader = TokenReader::new(&tokens);
 
         let func = Func::parse(&mut token_reader).unwrap();
 
         assert_eq!(func.name, "main".to_string());
         assert_eq!(func.args.len(), 0);
     }
+
+    #[test]
+    fn test_parse_many_args() {
+        let src = "func foo(a, b, c, d, e, f, g, h) 0";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let func = Func::parse(&mut token_reader).unwrap();
+
+        assert_eq!(func.args.len(), 8);
+        assert_eq!(func.args[7].name.value, "h".to_string());
+    }
+
+    #[test]
+    fn test_parse_annotated() {
+        let src = "func foo(a: int, b): bool a == b";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let func = Func::parse(&mut token_reader).unwrap();
+
+        assert_eq!(func.args.len(), 2);
+        assert!(func.args[0].type_annotation.is_some());
+        assert!(func.args[1].type_annotation.is_none());
+        if let Some(TypeExpression::Named(name)) = func.return_type {
+            assert_eq!(name.value, "bool".to_string());
+        } else {
+            panic!("戻り値の型がありません。")
+        }
+    }
 }
\ No newline at end of file
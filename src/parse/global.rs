@@ -0,0 +1,115 @@
+use general::SemiGroup;
+
+use sourcecode::Span;
+use sourcecode::Code;
+
+use diagnostic::Message;
+
+use token::Operator;
+use token::Token;
+use token::ReservedWord;
+
+use parse::SyntaxTree;
+use parse::TokenReader;
+use parse::Expression;
+use parse::TypeExpression;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GlobalKind {
+    Variable,
+    Constant,
+}
+
+// トップレベルの let / const
+pub struct Global {
+    pub kind: GlobalKind,
+    pub name: Code<String>,
+    pub type_annotation: Option<TypeExpression>,
+    pub content: Option<Expression>,
+    keyword_span: Span,
+}
+
+impl Global {
+    pub fn starts_with(token: &Token) -> bool {
+        matches!(token, Token::ReservedWord(ReservedWord::Let) | Token::ReservedWord(ReservedWord::Const))
+    }
+}
+
+impl SyntaxTree for Global {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<Global, (Option<Span>, Message)> {
+        let (kind, keyword_span) = match token_reader.next() {
+            Some(token) => match token.value {
+                Token::ReservedWord(ReservedWord::Let) => (GlobalKind::Variable, token.span),
+                Token::ReservedWord(ReservedWord::Const) => (GlobalKind::Constant, token.span),
+                _ => return Err((Some(token.span), Message::ExpectedGlobal)),
+            },
+            None => return Err((None, Message::ExpectedGlobal)),
+        };
+        let name = match token_reader.next() {
+            Some(token) => match &token.value {
+                Token::Identifier(name) => token.map_const(name.clone()),
+                _ => return Err((Some(token.span), Message::ExpectedIdentifier)),
+            },
+            None => return Err((None, Message::ExpectedIdentifier)),
+        };
+        let type_annotation = TypeExpression::parse_annotation(token_reader)?;
+        let assign = token_reader.try_next(|token| match token.value {
+            Token::Operator(Operator::Assign) => Ok(()),
+            _ => Err(token.span),
+        });
+        let content = match (assign, kind) {
+            (Ok(_), _) => match Expression::parse(token_reader) {
+                Ok(expr) => Some(expr),
+                Err(e) => return Err(e),
+            },
+            (Err(span), GlobalKind::Constant) => return Err((span, Message::ConstantWithoutValue)),
+            (Err(_), GlobalKind::Variable) => None,
+        };
+        Ok(Global { kind, name, type_annotation, content, keyword_span })
+    }
+
+    fn span(&self) -> Span {
+        match (&self.content, &self.type_annotation) {
+            (Some(content), _) => self.keyword_span.plus(&content.span()),
+            (None, Some(annotation)) => self.keyword_span.plus(&annotation.span()),
+            (None, None) => self.keyword_span.plus(&self.name.span),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use token::tokenize;
+
+    #[test]
+    fn test_parse_global() {
+        let src = "const N: int := 10";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let global = Global::parse(&mut token_reader).unwrap();
+
+        assert_eq!(global.kind, GlobalKind::Constant);
+        assert_eq!(global.name.value, "N".to_string());
+        assert!(global.type_annotation.is_some());
+        assert!(global.content.is_some());
+        assert_eq!(global.span(), Span::new(0, 0, 18));
+
+        let src = "let buffer: [int; 4]";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let global = Global::parse(&mut token_reader).unwrap();
+
+        assert_eq!(global.kind, GlobalKind::Variable);
+        assert!(global.content.is_none());
+
+        let src = "const N: int";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        assert!(Global::parse(&mut token_reader).is_err());
+    }
+}
@@ -0,0 +1,46 @@
+use std::collections::HashSet;
+use std::iter::FromIterator;
+
+use sourcecode::Code;
+use sourcecode::Span;
+
+use diagnostic::Message;
+
+use token::Operator;
+
+use parse::SyntaxTree;
+use parse::TokenReader;
+use parse::BinaryOperation;
+use parse::LogicalNot;
+
+pub struct LogicalAnd {
+    binary_operation: BinaryOperation<LogicalNot>,
+}
+
+impl LogicalAnd {
+    pub fn head(&self) -> &LogicalNot {
+        self.binary_operation.head()
+    }
+
+    pub fn tail(&self) -> impl Iterator<Item = (&Code<Operator>, &LogicalNot)> {
+        self.binary_operation.tail()
+    }
+
+    fn operators() -> HashSet<Operator> {
+        HashSet::from_iter(vec![
+            Operator::And,
+        ])
+    }
+}
+
+impl SyntaxTree for LogicalAnd {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<LogicalAnd, (Option<Span>, Message)> {
+        BinaryOperation::parse(token_reader, &Self::operators())
+        .map(|binary_operation| LogicalAnd{ binary_operation })
+    }
+
+    fn span(&self) -> Span {
+        self.binary_operation.span()
+    }
+}
@@ -0,0 +1,69 @@
+use general::SemiGroup;
+
+use sourcecode::Span;
+
+use diagnostic::Message;
+
+use token::Operator;
+use token::Token;
+
+use parse::SyntaxTree;
+use parse::TokenReader;
+use parse::BitOr;
+
+// ! はビット演算より弱く、&& より強く結びつく
+pub enum LogicalNot {
+    Not(Box<LogicalNot>, Span),
+    BitOr(Box<BitOr>),
+}
+
+impl SyntaxTree for LogicalNot {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<LogicalNot, (Option<Span>, Message)> {
+        let not = token_reader.try_next(|token| match token.value {
+            Token::Operator(Operator::Not) => Ok(token.span),
+            _ => Err(()),
+        });
+        match not {
+            Ok(span) => LogicalNot::parse(token_reader).map(|operand| {
+                let s = span.plus(&operand.span());
+                LogicalNot::Not(Box::new(operand), s)
+            }),
+            Err(_) => BitOr::parse(token_reader).map(|bit_or| LogicalNot::BitOr(Box::new(bit_or))),
+        }
+    }
+
+    fn span(&self) -> Span {
+        match self {
+            LogicalNot::Not(_, span) => *span,
+            LogicalNot::BitOr(bit_or) => bit_or.span(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use token::tokenize;
+
+    #[test]
+    fn test_parse_not() {
+        let src = "!!a == b";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let not = LogicalNot::parse(&mut token_reader).unwrap();
+
+        assert_eq!(not.span(), Span::new(0, 0, 8));
+        match not {
+            LogicalNot::Not(inner, _) => match *inner {
+                LogicalNot::Not(inner, _) => match *inner {
+                    LogicalNot::BitOr(bit_or) => assert_eq!(bit_or.span(), Span::new(0, 2, 6)),
+                    _ => panic!("否定が多すぎます。"),
+                },
+                _ => panic!("否定になっていません。"),
+            },
+            _ => panic!("否定になっていません。"),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashSet;
+use std::iter::FromIterator;
+
+use sourcecode::Code;
+use sourcecode::Span;
+
+use diagnostic::Message;
+
+use token::Operator;
+
+use parse::SyntaxTree;
+use parse::TokenReader;
+use parse::BinaryOperation;
+use parse::LogicalAnd;
+
+pub struct LogicalOr {
+    binary_operation: BinaryOperation<LogicalAnd>,
+}
+
+impl LogicalOr {
+    pub fn head(&self) -> &LogicalAnd {
+        self.binary_operation.head()
+    }
+
+    pub fn tail(&self) -> impl Iterator<Item = (&Code<Operator>, &LogicalAnd)> {
+        self.binary_operation.tail()
+    }
+
+    fn operators() -> HashSet<Operator> {
+        HashSet::from_iter(vec![
+            Operator::Or,
+        ])
+    }
+}
+
+impl SyntaxTree for LogicalOr {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<LogicalOr, (Option<Span>, Message)> {
+        BinaryOperation::parse(token_reader, &Self::operators())
+        .map(|binary_operation| LogicalOr{ binary_operation })
+    }
+
+    fn span(&self) -> Span {
+        self.binary_operation.span()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use token::tokenize;
+
+    #[test]
+    fn test_parse_or() {
+        let src = "a == 1 || b && c || d";
+
+        let tokens = tokenize(&src.to_string()).unwrap();
+
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let or = LogicalOr::parse(&mut token_reader).unwrap();
+        let tail: Vec<_> = or.tail().collect();
+
+        assert_eq!(tail.len(), 2);
+        assert_eq!(tail[0].0.value, Operator::Or);
+        assert_eq!(tail[0].1.tail().next().unwrap().0.value, Operator::And);
+        assert_eq!(or.span(), Span::new(0, 0, 21));
+    }
+}
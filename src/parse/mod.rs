@@ -1,29 +1,52 @@
 mod syntaxtree;
+mod token_reader;
 mod binary_operation;
 mod root;
 mod func;
+mod global;
+mod struct_declaration;
 mod statement;
 mod expression;
+mod logical_or;
+mod logical_and;
+mod logical_not;
+mod bit_or;
+mod bit_xor;
+mod bit_and;
 mod equality;
 mod relational;
+mod shift;
 mod add;
 mod multiply;
 mod unary;
 mod primary;
+mod type_expression;
 pub use self::syntaxtree::SyntaxTree;
+pub use self::token_reader::TokenReader;
 pub use self::binary_operation::BinaryOperation;
 pub use self::root::Root;
 pub use self::func::Func;
+pub use self::global::Global;
+pub use self::global::GlobalKind;
+pub use self::struct_declaration::StructDeclaration;
 pub use self::statement::Statement;
-pub use self::statement::Return;
 pub use self::expression::Expression;
 pub use self::expression::IfExpression;
-pub use self::expression::PureExpression;
+pub use self::expression::WhileExpression;
 pub use self::expression::BlockExpression;
+pub use self::logical_or::LogicalOr;
+pub use self::logical_and::LogicalAnd;
+pub use self::logical_not::LogicalNot;
+pub use self::bit_or::BitOr;
+pub use self::bit_xor::BitXor;
+pub use self::bit_and::BitAnd;
 pub use self::equality::Equality;
 pub use self::relational::Relational;
+pub use self::shift::Shift;
 pub use self::add::Add;
 pub use self::multiply::Multiply;
 pub use self::unary::Unary;
 pub use self::primary::Primary;
-pub use self::primary::FnCall;
\ No newline at end of file
+pub use self::primary::FnCall;
+pub use self::primary::StructLiteral;
+pub use self::type_expression::TypeExpression;
\ No newline at end of file
@@ -1,15 +1,15 @@
 use std::collections::HashSet;
 use std::iter::FromIterator;
 
-use general::TryReader;
-
 use sourcecode::Code;
 use sourcecode::Span;
 
-use token::Token;
+use diagnostic::Message;
+
 use token::Operator;
 
 use parse::SyntaxTree;
+use parse::TokenReader;
 use parse::BinaryOperation;
 use parse::Unary;
 
@@ -28,9 +28,10 @@ impl Multiply {
 }
 
 impl SyntaxTree for Multiply {
-    fn parse(mut token_reader: &mut TryReader<Code<Token>>)
-    -> Result<Multiply, (Option<Span>, String)> {
-        let operators = HashSet::from_iter(vec![Operator::Mul, Operator::Div].into_iter());
+    #[allow(clippy::needless_borrow)]
+    fn parse(mut token_reader: &mut TokenReader)
+    -> Result<Multiply, (Option<Span>, Message)> {
+        let operators = HashSet::from_iter(vec![Operator::Mul, Operator::Div, Operator::Mod]);
         BinaryOperation::parse(&mut token_reader, &operators)
         .map(|binary_operation| Multiply {binary_operation})
     }
@@ -51,7 +52,7 @@ mod tests {
 
         let tokens = tokenize(&src.to_string()).unwrap();
 
-        let mut token_reader = TryReader::new(&tokens);
+        let mut token_reader = TokenReader::new(&tokens);
 
         let multiply = Multiply::parse(&mut token_reader).unwrap();
         let mut tail = multiply.tail();
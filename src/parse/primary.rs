@@ -1,66 +1,214 @@
-use general::TryReader;
+use general::SemiGroup;
 
 use sourcecode::Code;
 use sourcecode::Span;
 use sourcecode::Position;
 
+use diagnostic::Message;
+
 use token::BracketSide;
 use token::Bracket;
 use token::Token;
+use token::ReservedWord;
 
 use parse::SyntaxTree;
+use parse::TokenReader;
 use parse::Expression;
 
 pub enum Primary {
     Integer(Code<i64>),
+    Boolean(Code<bool>),
+    Character(Code<u8>),
+    String(Code<String>),
     Identifier(Code<String>),
     Expression(Box<Expression>),
     FnCall(FnCall),
+    Index(Box<Primary>, Box<Expression>, Span),
+    Field(Box<Primary>, Code<String>),
+    StructLiteral(StructLiteral),
 }
 
 impl Primary {
-    fn parse_round_bracket(token_reader: &mut TryReader<Code<Token>>)
-    -> Result<Primary, (Option<Span>, String)> {
+    #[allow(clippy::needless_borrow, clippy::single_match)]
+    fn parse_atom(mut token_reader: &mut TokenReader)
+    -> Result<Primary, (Option<Span>, Message)> {
+        if StructLiteral::starts(token_reader) {
+            return StructLiteral::parse(token_reader).map(Primary::StructLiteral);
+        }
+        match token_reader.try_(FnCall::parse) {
+            Ok((_, fn_call)) => return Ok(Self::FnCall(fn_call)),
+            _ => ()
+        }
+        let token = match token_reader.next() {
+            Some(token) => token,
+            None => return Err((None, Message::ExpectedExpression)),
+        };
+        match &token.value {
+            Token::Number(number) => Ok(Primary::Integer(token.map_const(*number))),
+            Token::Character(c) => Ok(Primary::Character(token.map_const(*c))),
+            Token::String(content) => Ok(Primary::String(token.map_const(content.clone()))),
+            Token::ReservedWord(ReservedWord::True) => Ok(Primary::Boolean(token.map_const(true))),
+            Token::ReservedWord(ReservedWord::False) => Ok(Primary::Boolean(token.map_const(false))),
+            Token::Identifier(name) => Ok(Primary::Identifier(token.map_const(name.clone()))),
+            Token::Bracket(BracketSide::Left(Bracket::Round)) => Self::parse_round_bracket(&mut token_reader),
+            _ => Err((Some(token.span), Message::ExpectedPrimary)),
+        }
+    }
+
+    // "." の後のフィールド名を読む
+    fn parse_field(base: Primary, token_reader: &mut TokenReader)
+    -> Result<Primary, (Option<Span>, Message)> {
+        match token_reader.next() {
+            Some(token) => match &token.value {
+                Token::Identifier(name) => Ok(Primary::Field(Box::new(base), token.map_const(name.clone()))),
+                _ => Err((Some(token.span), Message::ExpectedFieldName)),
+            },
+            None => Err((None, Message::ExpectedFieldName)),
+        }
+    }
+
+    // "[" の後の "添字]" を読む
+    fn parse_index(base: Primary, token_reader: &mut TokenReader)
+    -> Result<Primary, (Option<Span>, Message)> {
+        let index = Expression::parse(token_reader)?;
+        match token_reader.next() {
+            Some(token) => match token.value {
+                Token::Bracket(BracketSide::Right(Bracket::Square)) => {
+                    let span = base.span().plus(&token.span);
+                    Ok(Primary::Index(Box::new(base), Box::new(index), span))
+                },
+                _ => Err((Some(token.span), Message::Expected("]"))),
+            },
+            None => Err((None, Message::Expected("]"))),
+        }
+    }
+
+    #[allow(clippy::question_mark)]
+    fn parse_round_bracket(token_reader: &mut TokenReader)
+    -> Result<Primary, (Option<Span>, Message)> {
         let expression = match Expression::parse(token_reader) {
             Ok(exp) => exp,
             Err(err) => return Err(err),
         };
         let maybe_left_round_bracket = match token_reader.next() {
             Some(token) => token,
-            None => return Err((None, String::from("\")\"を期待していましたが、トークンがありませんでした。"))),
+            None => return Err((None, Message::Expected(")"))),
         };
         match &maybe_left_round_bracket.value {
             &Token::Bracket(BracketSide::Right(Bracket::Round)) => Ok(Primary::Expression(Box::new(expression))),
-            _ => Err((Some(maybe_left_round_bracket.span), String::from("\")\"を期待しています。"))),
+            _ => Err((Some(maybe_left_round_bracket.span), Message::Expected(")"))),
         }
     }
 }
 
 impl SyntaxTree for Primary {
-    fn parse(mut token_reader: &mut TryReader<Code<Token>>)
-    -> Result<Primary, (Option<Span>, String)> {
-        match token_reader.try_(FnCall::parse) {
-            Ok((_, fn_call)) => return Ok(Self::FnCall(fn_call)),
-            _ => ()
-        }
-        let token = match token_reader.next() {
-            Some(token) => token,
-            None => return Err((None, String::from("式を期待していましたが、トークンがありませんでした。"))),
-        };
-        match &token.value {
-            Token::Number(number) => Ok(Primary::Integer(token.map_const(*number))),
-            Token::Identifier(name) => Ok(Primary::Identifier(token.map_const(name.clone()))),
-            Token::Bracket(BracketSide::Left(Bracket::Round)) => Self::parse_round_bracket(&mut token_reader),
-            _ => Err((Some(token.span), String::from("数字または識別子または\"(\"を期待しています。"))),
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<Primary, (Option<Span>, Message)> {
+        let mut primary = Self::parse_atom(token_reader)?;
+        // 添字とフィールドは左から順に適用する
+        while let Ok(postfix) = token_reader.try_next(|token| match token.value {
+            Token::Bracket(BracketSide::Left(Bracket::Square)) | Token::Dot => Ok(token.value.clone()),
+            _ => Err(()),
+        }) {
+            let result = if postfix == Token::Dot {
+                Self::parse_field(primary, token_reader)
+            } else {
+                Self::parse_index(primary, token_reader)
+            };
+            primary = result?;
         }
+        Ok(primary)
     }
 
     fn span(&self) -> Span {
         match self {
             Primary::Integer(c) => c.span,
+            Primary::Boolean(c) => c.span,
+            Primary::Character(c) => c.span,
+            Primary::String(c) => c.span,
             Primary::Identifier(c) => c.span,
             Primary::Expression(e) => e.span(),
             Primary::FnCall(f) => f.span(),
+            Primary::Index(_, _, span) => *span,
+            Primary::Field(base, field) => base.span().plus(&field.span),
+            Primary::StructLiteral(literal) => literal.span(),
+        }
+    }
+}
+
+// Point { x: 1, y: 2 }
+pub struct StructLiteral {
+    pub name: Code<String>,
+    pub fields: Vec<(Code<String>, Expression)>,
+    end: Position,
+}
+
+impl StructLiteral {
+    // "識別子 { 識別子 :" で始まっていれば構造体リテラルとみなす
+    fn starts(token_reader: &TokenReader) -> bool {
+        token_reader.lookahead(|reader| {
+            if !matches!(reader.next().map(|t| &t.value), Some(Token::Identifier(_))) {
+                return false;
+            }
+            if reader.next().map(|t| &t.value) != Some(&Token::Bracket(BracketSide::Left(Bracket::Curly))) {
+                return false;
+            }
+            reader.drop_while(|token| token.value == Token::LineBreak);
+            matches!(reader.next().map(|t| &t.value), Some(Token::Identifier(_)))
+                && reader.next().map(|t| &t.value) == Some(&Token::Colon)
+        })
+    }
+}
+
+impl SyntaxTree for StructLiteral {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<Self, (Option<Span>, Message)> {
+        let name = match token_reader.next() {
+            Some(token) => match &token.value {
+                Token::Identifier(name) => token.map_const(name.clone()),
+                _ => return Err((Some(token.span), Message::ExpectedIdentifier)),
+            },
+            None => return Err((None, Message::ExpectedIdentifier)),
+        };
+        match token_reader.next() {
+            Some(token) if token.value == Token::Bracket(BracketSide::Left(Bracket::Curly)) => (),
+            Some(token) => return Err((Some(token.span), Message::Expected("{"))),
+            None => return Err((None, Message::Expected("{"))),
+        };
+        let mut fields = Vec::new();
+        loop {
+            token_reader.drop_while(|token| token.value == Token::LineBreak);
+            let field = match token_reader.try_next(|token| match &token.value {
+                Token::Identifier(name) => Ok(token.map_const(name.clone())),
+                _ => Err(()),
+            }) {
+                Ok(field) => field,
+                Err(_) => break,
+            };
+            match token_reader.next() {
+                Some(token) if token.value == Token::Colon => (),
+                Some(token) => return Err((Some(token.span), Message::Expected(":"))),
+                None => return Err((None, Message::Expected(":"))),
+            };
+            let value = Expression::parse(token_reader)?;
+            fields.push((field, value));
+            if token_reader.try_next(|token| if token.value == Token::Comma { Ok(()) } else { Err(()) }).is_err() {
+                break;
+            }
+        }
+        token_reader.drop_while(|token| token.value == Token::LineBreak);
+        let end = match token_reader.next() {
+            Some(token) if token.value == Token::Bracket(BracketSide::Right(Bracket::Curly)) => token.span.end,
+            Some(token) => return Err((Some(token.span), Message::Expected("}"))),
+            None => return Err((None, Message::Expected("}"))),
+        };
+        Ok(Self { name, fields, end })
+    }
+
+    fn span(&self) -> Span {
+        Span {
+            start: self.name.span.start,
+            end: self.end,
         }
     }
 }
@@ -72,23 +220,24 @@ pub struct FnCall {
 }
 
 impl SyntaxTree for FnCall {
-    fn parse(token_reader: &mut TryReader<Code<Token>>)
-    -> Result<Self, (Option<Span>, String)> {
+    #[allow(clippy::redundant_pattern_matching)]
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<Self, (Option<Span>, Message)> {
         let func = match token_reader.next() {
             Some(token) => {
                 match &token.value {
                     Token::Identifier(idfr) => token.map_const(idfr.clone()),
-                    _ => return Err((Some(token.span), "識別子を期待していました".to_string())),
+                    _ => return Err((Some(token.span), Message::ExpectedIdentifier)),
                 }
             },
-            None => return Err((None, "識別子を期待していました".to_string()))
+            None => return Err((None, Message::ExpectedIdentifier))
         };
         match token_reader.next().map(|t| t.value.clone()) {
             Some(Token::Bracket(BracketSide::Left(Bracket::Round))) => (),
-            _ => return Err((None, "(を期待していました。".to_string())),
+            _ => return Err((None, Message::Expected("("))),
         };
         let mut args = Vec::new();
-        for _ in 0..6 {
+        loop {
             let arg = token_reader.try_(Expression::parse);
             if let Ok((_, expr)) = arg {
                 args.push(expr);
@@ -106,10 +255,10 @@ impl SyntaxTree for FnCall {
             Some(token) => {
                 match token.value {
                     Token::Bracket(BracketSide::Right(Bracket::Round)) => token.span.start,
-                    _ => return Err((Some(token.span), ")を期待していました".to_string()))
+                    _ => return Err((Some(token.span), Message::Expected(")")))
                 }
             },
-            _ => return Err((None, ")を期待していました".to_string()))
+            _ => return Err((None, Message::Expected(")")))
         };
         Ok(Self {
             func,
@@ -141,7 +290,7 @@ mod tests {
                 span: Span::new(0, 0, 1),
             },
         ];
-        let mut token_reader = TryReader::new(&tokens);
+        let mut token_reader = TokenReader::new(&tokens);
 
         let primary = Primary::parse(&mut token_reader).unwrap();
 
@@ -158,7 +307,7 @@ mod tests {
 
         let tokens = tokenize(&src.to_string()).unwrap();
 
-        let mut token_reader = TryReader::new(&tokens);
+        let mut token_reader = TokenReader::new(&tokens);
 
         let primary = Primary::parse(&mut token_reader).unwrap();
 
@@ -174,7 +323,7 @@ mod tests {
 
         let tokens = tokenize(&src.to_string()).unwrap();
 
-        let mut token_reader = TryReader::new(&tokens);
+        let mut token_reader = TokenReader::new(&tokens);
 
         let fn_call = FnCall::parse(&mut token_reader).unwrap();
 
@@ -185,7 +334,7 @@ mod tests {
 
         let tokens = tokenize(&src.to_string()).unwrap();
 
-        let mut token_reader = TryReader::new(&tokens);
+        let mut token_reader = TokenReader::new(&tokens);
 
         let fn_call = FnCall::parse(&mut token_reader).unwrap();
 
@@ -196,8 +345,77 @@ mod tests {
 
         let tokens = tokenize(&src.to_string()).unwrap();
 
-        let mut token_reader = TryReader::new(&tokens);
+        let mut token_reader = TokenReader::new(&tokens);
 
         Primary::parse(&mut token_reader).unwrap();
+
+        let src = "baz(1, 2, 3, 4, 5, 6, 7, 8, 9)";
+
+        let tokens = tokenize(&src.to_string()).unwrap();
+
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let fn_call = FnCall::parse(&mut token_reader).unwrap();
+
+        assert_eq!(fn_call.args.len(), 9);
+    }
+
+    #[test]
+    fn test_parse_struct_literal() {
+        let src = "Point {
+            x: 1,
+            y: a.b[0].c
+        }.y";
+
+        let tokens = tokenize(&src.to_string()).unwrap();
+
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let primary = Primary::parse(&mut token_reader).unwrap();
+
+        if let Primary::Field(base, field) = primary {
+            assert_eq!(field.value, "y".to_string());
+            if let Primary::StructLiteral(literal) = *base {
+                assert_eq!(literal.name.value, "Point".to_string());
+                assert_eq!(literal.fields.len(), 2);
+            } else {
+                panic!("構造体リテラルになっていません。")
+            }
+        } else {
+            panic!("フィールドになっていません。")
+        }
+
+        let src = "x { 1 }";
+
+        let tokens = tokenize(&src.to_string()).unwrap();
+
+        let mut token_reader = TokenReader::new(&tokens);
+
+        if let Primary::Identifier(_) = Primary::parse(&mut token_reader).unwrap() {
+        } else {
+            panic!("識別子になっていません。")
+        }
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let src = "a[i + 1][2]";
+
+        let tokens = tokenize(&src.to_string()).unwrap();
+
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let primary = Primary::parse(&mut token_reader).unwrap();
+
+        assert_eq!(primary.span(), Span::new(0, 0, 11));
+        if let Primary::Index(base, _, _) = primary {
+            if let Primary::Index(_, _, span) = *base {
+                assert_eq!(span, Span::new(0, 0, 8));
+            } else {
+                panic!("添字が左から適用されていません。")
+            }
+        } else {
+            panic!("添字になっていません。")
+        }
     }
 }
\ No newline at end of file
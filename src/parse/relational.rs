@@ -1,31 +1,32 @@
 use std::collections::HashSet;
 use std::iter::FromIterator;
 
-use general::TryReader;
-
 use sourcecode::Code;
 use sourcecode::Span;
 
+use diagnostic::Message;
+
 use token::Operator;
-use token::Token;
 
 use parse::SyntaxTree;
+use parse::TokenReader;
 use parse::BinaryOperation;
-use parse::Add;
+use parse::Shift;
 
 pub struct Relational {
-    binary_operation: BinaryOperation<Add>,
+    binary_operation: BinaryOperation<Shift>,
 }
 
 impl Relational {
-    pub fn head(&self) -> &Add {
+    pub fn head(&self) -> &Shift {
         self.binary_operation.head()
     }
 
-    pub fn tail(&self) -> impl Iterator<Item = (&Code<Operator>, &Add)> {
+    pub fn tail(&self) -> impl Iterator<Item = (&Code<Operator>, &Shift)> {
         self.binary_operation.tail()
     }
 
+    #[allow(clippy::useless_conversion)]
     fn operators() -> HashSet<Operator> {
         HashSet::from_iter(vec![
             Operator::Less,
@@ -37,8 +38,9 @@ impl Relational {
 }
 
 impl SyntaxTree for Relational {
-    fn parse(mut token_reader: &mut TryReader<Code<Token>>)
-    -> Result<Relational, (Option<Span>, String)> {
+    #[allow(clippy::needless_borrow)]
+    fn parse(mut token_reader: &mut TokenReader)
+    -> Result<Relational, (Option<Span>, Message)> {
         BinaryOperation::parse(&mut token_reader, &Self::operators())
         .map(|binary_operation| Relational {binary_operation})
     }
@@ -57,7 +59,7 @@ mod tests {
     fn test_parse_relational() {
         let src = "3 < 5 <= 1";
         let tokens = tokenize(&src.to_string()).unwrap();
-        let mut token_reader = TryReader::new(&tokens);
+        let mut token_reader = TokenReader::new(&tokens);
 
         let relational = Relational::parse(&mut token_reader).unwrap();
         let mut tail = relational.tail();
@@ -1,40 +1,76 @@
 use general::FreeMonoid;
+use general::Monoid;
 use general::SemiGroup;
-use general::TryReader;
 
 use token::Token;
+use token::ReservedWord;
 
-use sourcecode::Code;
 use sourcecode::Span;
 
+use diagnostic::Message;
+
 use parse::SyntaxTree;
+use parse::TokenReader;
 use parse::Func;
+use parse::Global;
+use parse::StructDeclaration;
 
 pub struct Root {
+    pub structs: Vec<StructDeclaration>,
+    pub globals: Vec<Global>,
     pub funcs: Vec<Func>,
 }
 
+impl Root {
+    // 誤りのあった定義を読み飛ばし、次の定義の始まりまで進む
+    // func と struct は位置によらず、let と const は行頭にあるものを定義の始まりとみなす
+    fn skip_definition(token_reader: &mut TokenReader) {
+        token_reader.next();
+        while let Some(token) = token_reader.peek() {
+            match token.value {
+                Token::ReservedWord(ReservedWord::Func) | Token::ReservedWord(ReservedWord::Struct) => return,
+                Token::ReservedWord(ReservedWord::Let) | Token::ReservedWord(ReservedWord::Const) if token.span.start.pos == 0 => return,
+                _ => token_reader.next(),
+            };
+        }
+    }
+}
 
+// 構文エラーは token_reader に記録して読み進めるので、
+// 呼び出し側は token_reader.diagnostics() も確かめなければならない
 impl SyntaxTree for Root {
-    fn parse(mut token_reader: &mut TryReader<Code<Token>>)
-    -> Result<Root, (Option<Span>, String)> {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<Root, (Option<Span>, Message)> {
+        let mut structs = Vec::new();
+        let mut globals = Vec::new();
         let mut funcs = Vec::new();
-        while token_reader.has_next() {
-            match Func::parse(&mut token_reader) {
-                Ok(func) => funcs.push(func),
-                Err(err) => return Err(err),
+        token_reader.drop_while(|token| token.value == Token::LineBreak);
+        while let Some(token) = token_reader.peek() {
+            let result = if StructDeclaration::starts_with(&token.value) {
+                token_reader.try_(StructDeclaration::parse).map(|(_, declaration)| structs.push(declaration))
+            } else if Global::starts_with(&token.value) {
+                token_reader.try_(Global::parse).map(|(_, global)| globals.push(global))
+            } else {
+                token_reader.try_(Func::parse).map(|(_, func)| funcs.push(func))
+            };
+            if let Err(err) = result {
+                token_reader.report(err);
+                Self::skip_definition(token_reader);
             }
             token_reader.drop_while(|token| token.value == Token::LineBreak);
         }
-        Ok(Root{funcs})
+        Ok(Root{structs, globals, funcs})
     }
 
+    #[allow(clippy::clone_on_copy)]
     fn span(&self) -> Span {
-        self.funcs
+        self.structs
             .iter()
-            .map(|stmt| stmt.span())
+            .map(|declaration| declaration.span())
+            .chain(self.globals.iter().map(|global| global.span()))
+            .chain(self.funcs.iter().map(|func| func.span()))
             .map(FreeMonoid::Some)
-            .fold(FreeMonoid::Zero, |acc, x| acc.plus(&x))
+            .fold(FreeMonoid::zero(), |acc, x| acc.plus(&x))
             .get()
             .unwrap()
             .clone()
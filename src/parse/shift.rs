@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+use std::iter::FromIterator;
+
+use sourcecode::Code;
+use sourcecode::Span;
+
+use diagnostic::Message;
+
+use token::Operator;
+
+use parse::SyntaxTree;
+use parse::TokenReader;
+use parse::BinaryOperation;
+use parse::Add;
+
+pub struct Shift {
+    binary_operation: BinaryOperation<Add>,
+}
+
+impl Shift {
+    pub fn head(&self) -> &Add {
+        self.binary_operation.head()
+    }
+
+    pub fn tail(&self) -> impl Iterator<Item = (&Code<Operator>, &Add)> {
+        self.binary_operation.tail()
+    }
+
+    fn operators() -> HashSet<Operator> {
+        HashSet::from_iter(vec![
+            Operator::ShiftLeft,
+            Operator::ShiftRight,
+        ])
+    }
+}
+
+impl SyntaxTree for Shift {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<Shift, (Option<Span>, Message)> {
+        BinaryOperation::parse(token_reader, &Self::operators())
+        .map(|binary_operation| Shift{ binary_operation })
+    }
+
+    fn span(&self) -> Span {
+        self.binary_operation.span()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use token::tokenize;
+
+    #[test]
+    fn test_parse_shift() {
+        let src = "1 << 2 + 3 >> 1";
+
+        let tokens = tokenize(&src.to_string()).unwrap();
+
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let shift = Shift::parse(&mut token_reader).unwrap();
+        let tail: Vec<_> = shift.tail().collect();
+
+        assert_eq!(tail.len(), 2);
+        assert_eq!(tail[0].0.value, Operator::ShiftLeft);
+        assert_eq!(tail[0].1.span(), Span::new(0, 5, 5));
+        assert_eq!(tail[1].0.value, Operator::ShiftRight);
+    }
+}
@@ -1,52 +1,118 @@
 use general::SemiGroup;
-use general::TryReader;
 
 use sourcecode::Span;
 use sourcecode::Code;
 
+use diagnostic::Message;
+
+use token::Bracket;
+use token::BracketSide;
 use token::Operator;
 use token::Token;
 use token::ReservedWord;
 
 use parse::SyntaxTree;
+use parse::TokenReader;
 use parse::Expression;
+use parse::TypeExpression;
+use parse::Unary;
 
 pub enum Statement {
     Assignment(Assignment),
+    Reassignment(Reassignment),
     Return(Return),
+    Expression(Expression),
 }
 
 impl SyntaxTree for Statement {
-    fn parse(mut token_reader: &mut TryReader<Code<Token>>)
-    -> Result<Statement, (Option<Span>, String)> {
-        Assignment::parse(&mut token_reader)
-        .map(Statement::Assignment)
-        .or_else(|_| Return::parse(&mut token_reader).map(Statement::Return))
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<Statement, (Option<Span>, Message)> {
+        // let と return で始まる文は種類が決まるので、その文としての誤りを返す
+        token_reader.drop_while(|token| token.value == Token::LineBreak);
+        match token_reader.peek().map(|token| &token.value) {
+            Some(Token::ReservedWord(ReservedWord::Let)) => return Assignment::parse(token_reader).map(Statement::Assignment),
+            Some(Token::ReservedWord(ReservedWord::Return)) => return Return::parse(token_reader).map(Statement::Return),
+            _ => (),
+        }
+        // 左辺を式として読んでから読み直すと、入れ子の深さに対して指数的に遅くなるので先に決める
+        if Self::is_reassignment(token_reader) {
+            return Reassignment::parse(token_reader).map(Statement::Reassignment);
+        }
+        Self::parse_expression(token_reader).map(Statement::Expression)
     }
 
     fn span(&self) -> Span {
         match self {
             Statement::Assignment(assignment) => assignment.span(),
+            Statement::Reassignment(reassignment) => reassignment.span(),
             Statement::Return(return_) => return_.span(),
+            Statement::Expression(expression) => expression.span(),
+        }
+    }
+}
+
+impl Statement {
+    // 行末までに括弧の外で := があれば再代入の文とみなす
+    fn is_reassignment(token_reader: &TokenReader) -> bool {
+        token_reader.lookahead(|reader| {
+            let mut depth = 0;
+            while let Some(token) = reader.next() {
+                match token.value {
+                    Token::Operator(Operator::Assign) if depth == 0 => return true,
+                    Token::LineBreak if depth == 0 => return false,
+                    Token::ReservedWord(ReservedWord::Func) => return false,
+                    Token::Bracket(BracketSide::Left(_)) => depth += 1,
+                    Token::Bracket(BracketSide::Right(_)) if depth == 0 => return false,
+                    Token::Bracket(BracketSide::Right(_)) => depth -= 1,
+                    _ => (),
+                }
+            }
+            false
+        })
+    }
+
+    // 式文は改行で終わっていなければならない。ブロックの最後の式なら直後の } で終わってもよい
+    fn parse_expression(token_reader: &mut TokenReader)
+    -> Result<Expression, (Option<Span>, Message)> {
+        token_reader.drop_while(|token| token.value == Token::LineBreak);
+        let expression = Expression::parse(token_reader)?;
+        if let Some(token) = token_reader.peek() {
+            if token.value == Token::Bracket(BracketSide::Right(Bracket::Curly)) {
+                return Ok(expression);
+            }
+        }
+        match token_reader.try_(|reader| match reader.next() {
+            Some(token) if token.value == Token::LineBreak => Ok(()),
+            Some(token) => Err((Some(token.span), Message::ExpectedLineBreak)),
+            None => Err((None, Message::ExpectedLineBreak)),
+        }) {
+            Ok(_) => Ok(expression),
+            Err(e) => Err(e),
         }
     }
 }
 
 pub struct Assignment {
     identifier: Code<String>,
-    content: Expression,
+    type_annotation: Option<TypeExpression>,
+    content: Option<Expression>,
 }
 
 impl Assignment {
     pub fn identifier(&self) -> &Code<String> {
         &self.identifier
     }
-    pub fn content(&self) -> &Expression {
-        &self.content
+    pub fn type_annotation(&self) -> &Option<TypeExpression> {
+        &self.type_annotation
+    }
+    // 初期値は省略できる
+    pub fn content(&self) -> Option<&Expression> {
+        self.content.as_ref()
     }
 
-    fn parse(token_reader: &mut TryReader<Code<Token>>)
-    -> Result<Assignment, (Option<Span>, String)> {
+    #[allow(clippy::question_mark)]
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<Assignment, (Option<Span>, Message)> {
         token_reader.drop_while(|token| token.value == Token::LineBreak);
         match token_reader.try_next(|token| {
             match token.value {
@@ -55,7 +121,7 @@ impl Assignment {
             }
         }) {
             Ok(_) => (),
-            Err(err) => return Err((err.and_then(|x| x), String::from("letを期待していました")))
+            Err(err) => return Err((err.and_then(|x| x), Message::Expected("let")))
         };
         let identifier = match token_reader.try_next(|token| {
             match &token.value {
@@ -64,27 +130,70 @@ impl Assignment {
             }
         }) {
             Ok(name) => name,
-            Err(span) => return Err((span, "識別子を期待していました。".to_string()))
+            Err(span) => return Err((span, Message::ExpectedIdentifier))
         };
-        match token_reader.try_next(|token| {
+        let type_annotation = match TypeExpression::parse_annotation(token_reader) {
+            Ok(annotation) => annotation,
+            Err(e) => return Err(e),
+        };
+        if token_reader.try_next(|token| {
             match token.value {
                 Token::Operator(Operator::Assign) => Ok(()),
-                _ => Err(token.span)
+                _ => Err(())
             }
-        }) {
-            Ok(_) => (),
-            Err(err) => return Err((err, String::from("代入演算子を期待していました"))),
+        }).is_err() {
+            return Ok(Self {identifier, type_annotation, content: None});
         };
         let content = match Expression::parse(token_reader) {
             Ok(expr) => expr,
             Err(e) => return Err(e),
         };
 
-        Ok(Self {identifier, content})
+        Ok(Self {identifier, type_annotation, content: Some(content)})
+    }
+
+    fn span(&self) -> Span {
+        match (&self.content, &self.type_annotation) {
+            (Some(content), _) => self.identifier.span.plus(&content.span()),
+            (None, Some(annotation)) => self.identifier.span.plus(&annotation.span()),
+            (None, None) => self.identifier.span,
+        }
+    }
+}
+
+pub struct Reassignment {
+    target: Unary,
+    content: Expression,
+}
+
+impl Reassignment {
+    pub fn target(&self) -> &Unary {
+        &self.target
+    }
+    pub fn content(&self) -> &Expression {
+        &self.content
+    }
+
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<Reassignment, (Option<Span>, Message)> {
+        token_reader.drop_while(|token| token.value == Token::LineBreak);
+        let target = Unary::parse(token_reader)?;
+        match token_reader.try_next(|token| {
+            match token.value {
+                Token::Operator(Operator::Assign) => Ok(()),
+                _ => Err(token.span)
+            }
+        }) {
+            Ok(_) => (),
+            Err(err) => return Err((err, Message::ExpectedAssignmentOperator)),
+        };
+        let content = Expression::parse(token_reader)?;
+
+        Ok(Self {target, content})
     }
 
     fn span(&self) -> Span {
-        self.identifier.span.plus(&self.content.span())
+        self.target.span().plus(&self.content.span())
     }
 }
 
@@ -94,12 +203,13 @@ pub struct Return {
 }
 
 impl Return {
+    #[allow(clippy::needless_return)]
     pub fn content(&self) -> &Expression {
         return &self.content
     }
 
-    fn parse(token_reader: &mut TryReader<Code<Token>>)
-    -> Result<Return, (Option<Span>, String)> {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<Return, (Option<Span>, Message)> {
         token_reader.drop_while(|token| token.value == Token::LineBreak);
         match token_reader.try_next(|token| {
             match token.value {
@@ -108,7 +218,7 @@ impl Return {
             }
         }) {
             Ok(ret_span) => Ok(ret_span),
-            Err(err) => Err((err, String::from("returnを期待していました")))
+            Err(err) => Err((err, Message::Expected("return")))
         }
         .and_then(|ret_span| match Expression::parse(token_reader) {
             Ok(content) => Ok(Return{content, return_span: ret_span}),
@@ -116,7 +226,110 @@ impl Return {
         })
     }
 
-    fn span(&self) -> Span {
+    pub fn span(&self) -> Span {
         self.return_span.plus(&self.content.span())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use token::tokenize;
+
+    #[test]
+    fn test_parse_reassignment() {
+        let src = "x := x + 1";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let statement = Statement::parse(&mut token_reader).unwrap();
+
+        if let Statement::Reassignment(reassignment) = statement {
+            assert_eq!(reassignment.target().span(), Span::new(0, 0, 1));
+        } else {
+            panic!("再代入になっていません。")
+        }
+
+        let src = "*p := 1";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let statement = Statement::parse(&mut token_reader).unwrap();
+
+        if let Statement::Reassignment(reassignment) = statement {
+            if let Unary::Dereference(_, _) = reassignment.target() {
+            } else {
+                panic!("参照外しへの代入になっていません。")
+            }
+        } else {
+            panic!("再代入になっていません。")
+        }
+    }
+
+    #[test]
+    fn test_parse_nested_blocks() {
+        // 入れ子ごとに読み直していると終わらない深さ
+        let depth = 20;
+        let src = format!("{}1\n{}", "({\n".repeat(depth), "})\n".repeat(depth));
+        let tokens = tokenize(&src).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let statement = Statement::parse(&mut token_reader).unwrap();
+
+        if let Statement::Expression(_) = statement {
+        } else {
+            panic!("式文になっていません。")
+        }
+    }
+
+    #[test]
+    fn test_parse_nested_one_line_blocks() {
+        // ブロックの最後の式を読み直していると終わらない深さ
+        let depth = 30;
+        let srcs = [
+            format!("{}1{}\n", "{ ".repeat(depth), " }".repeat(depth)),
+            format!("{}1{}\n", "if true then { ".repeat(depth), " } else 0".repeat(depth)),
+        ];
+        for src in srcs.iter() {
+            let tokens = tokenize(src).unwrap();
+            let mut token_reader = TokenReader::new(&tokens);
+
+            let statement = Statement::parse(&mut token_reader).unwrap();
+
+            if let Statement::Expression(_) = statement {
+            } else {
+                panic!("式文になっていません。")
+            }
+            assert!(token_reader.diagnostics().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_parse_assignment() {
+        let src = "let x := 1";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let statement = Statement::parse(&mut token_reader).unwrap();
+
+        if let Statement::Assignment(assignment) = statement {
+            assert_eq!(assignment.identifier().value, "x".to_string());
+            assert!(assignment.type_annotation().is_none());
+        } else {
+            panic!("宣言になっていません。")
+        }
+
+        let src = "let y: bool := true";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let statement = Statement::parse(&mut token_reader).unwrap();
+
+        if let Statement::Assignment(assignment) = statement {
+            assert!(assignment.type_annotation().is_some());
+        } else {
+            panic!("宣言になっていません。")
+        }
+    }
+}
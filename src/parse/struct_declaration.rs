@@ -0,0 +1,123 @@
+use sourcecode::Span;
+use sourcecode::Code;
+use sourcecode::Position;
+
+use diagnostic::Message;
+
+use token::Token;
+use token::Bracket;
+use token::BracketSide;
+use token::ReservedWord;
+
+use parse::SyntaxTree;
+use parse::TokenReader;
+use parse::TypeExpression;
+
+// struct Point { x: int, y: int }
+pub struct StructDeclaration {
+    pub name: Code<String>,
+    pub fields: Vec<Field>,
+    start: Position,
+    end: Position,
+}
+
+pub struct Field {
+    pub name: Code<String>,
+    pub type_expression: TypeExpression,
+}
+
+impl StructDeclaration {
+    pub fn starts_with(token: &Token) -> bool {
+        *token == Token::ReservedWord(ReservedWord::Struct)
+    }
+}
+
+impl SyntaxTree for StructDeclaration {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<StructDeclaration, (Option<Span>, Message)> {
+        let start = match token_reader.next() {
+            Some(token) if Self::starts_with(&token.value) => token.span.start,
+            Some(token) => return Err((Some(token.span), Message::Expected("struct"))),
+            None => return Err((None, Message::Expected("struct"))),
+        };
+        let name = match token_reader.next() {
+            Some(token) => match &token.value {
+                Token::Identifier(name) => token.map_const(name.clone()),
+                _ => return Err((Some(token.span), Message::ExpectedIdentifier)),
+            },
+            None => return Err((None, Message::ExpectedIdentifier)),
+        };
+        match token_reader.next() {
+            Some(token) if token.value == Token::Bracket(BracketSide::Left(Bracket::Curly)) => (),
+            Some(token) => return Err((Some(token.span), Message::Expected("{"))),
+            None => return Err((None, Message::Expected("{"))),
+        };
+        let mut fields = Vec::new();
+        loop {
+            token_reader.drop_while(|token| token.value == Token::LineBreak);
+            let field_name = match token_reader.try_next(|token| match &token.value {
+                Token::Identifier(name) => Ok(token.map_const(name.clone())),
+                _ => Err(()),
+            }) {
+                Ok(name) => name,
+                Err(_) => break,
+            };
+            match token_reader.next() {
+                Some(token) if token.value == Token::Colon => (),
+                Some(token) => return Err((Some(token.span), Message::Expected(":"))),
+                None => return Err((None, Message::Expected(":"))),
+            };
+            let type_expression = TypeExpression::parse(token_reader)?;
+            fields.push(Field { name: field_name, type_expression });
+            // フィールドはカンマまたは改行で区切る
+            let separated = token_reader.try_next(|token| match token.value {
+                Token::Comma | Token::LineBreak => Ok(()),
+                _ => Err(()),
+            });
+            if separated.is_err() {
+                break;
+            }
+        }
+        token_reader.drop_while(|token| token.value == Token::LineBreak);
+        let end = match token_reader.next() {
+            Some(token) if token.value == Token::Bracket(BracketSide::Right(Bracket::Curly)) => token.span.end,
+            Some(token) => return Err((Some(token.span), Message::Expected("}"))),
+            None => return Err((None, Message::Expected("}"))),
+        };
+        Ok(StructDeclaration { name, fields, start, end })
+    }
+
+    fn span(&self) -> Span {
+        Span {
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use token::tokenize;
+
+    #[test]
+    fn test_parse_struct_declaration() {
+        let src = "struct Point {
+            x: int,
+            y: int
+            next: *Point
+        }";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let declaration = StructDeclaration::parse(&mut token_reader).unwrap();
+
+        assert_eq!(declaration.name.value, "Point".to_string());
+        assert_eq!(declaration.fields.len(), 3);
+        assert_eq!(declaration.fields[2].name.value, "next".to_string());
+        assert_eq!(declaration.span(), Span {
+            start: Position{ line: 0, pos: 0 },
+            end: Position{ line: 4, pos: 9 },
+        });
+    }
+}
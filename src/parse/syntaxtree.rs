@@ -1,11 +1,11 @@
-use general::TryReader;
+use parse::TokenReader;
 
-use token::Token;
 
 use sourcecode::Span;
-use sourcecode::Code;
+
+use diagnostic::Message;
 
 pub trait SyntaxTree: Sized {
-    fn parse(token_reader: &mut TryReader<Code<Token>>) -> Result<Self, (Option<Span>, String)>;
+    fn parse(token_reader: &mut TokenReader) -> Result<Self, (Option<Span>, Message)>;
     fn span(&self) -> Span;
 }
\ No newline at end of file
@@ -0,0 +1,95 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use general::TryReader;
+
+use diagnostic::Diagnostic;
+use diagnostic::code;
+use diagnostic::Message;
+
+use sourcecode::Code;
+use sourcecode::Span;
+
+use token::Token;
+
+// 読み直しのために複製したリーダーの間で共有する
+type Diagnostics = Rc<RefCell<Vec<Diagnostic>>>;
+
+// トークン列を読み進めながら、読み飛ばして回復できた構文エラーと警告を集める
+pub struct TokenReader<'l> {
+    reader: TryReader<'l, Code<Token>>,
+    diagnostics: Diagnostics,
+}
+
+impl<'l> TokenReader<'l> {
+    pub fn new(tokens: &'l Vec<Code<Token>>) -> TokenReader<'l> {
+        TokenReader {
+            reader: TryReader::new(tokens),
+            diagnostics: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    pub fn next(&mut self) -> Option<&'l Code<Token>> {
+        self.reader.next()
+    }
+
+    pub fn peek(&self) -> Option<&'l Code<Token>> {
+        self.reader.peek()
+    }
+
+    pub fn lookahead<R, F>(&self, f: F) -> R
+    where F: FnOnce(&mut TokenReader<'l>) -> R {
+        f(&mut self.fork())
+    }
+
+    pub fn try_<R, S, F>(&mut self, f: F) -> Result<(usize, R), S>
+    where F: FnOnce(&mut TokenReader<'l>) -> Result<R, S> {
+        let mut fork = self.fork();
+        match f(&mut fork) {
+            Ok(result) => {
+                let consumed = fork.reader.position() - self.reader.position();
+                self.reader = fork.reader;
+                Ok((consumed, result))
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn try_next<R, S, F>(&mut self, f: F) -> Result<R, Option<S>>
+    where F: FnOnce(&Code<Token>) -> Result<R, S> {
+        self.reader.try_next(f)
+    }
+
+    pub fn drop_while<F>(&mut self, f: F)
+    where F: Fn(&Code<Token>) -> bool {
+        self.reader.drop_while(f)
+    }
+
+    fn fork(&self) -> TokenReader<'l> {
+        TokenReader {
+            reader: self.reader.clone(),
+            diagnostics: self.diagnostics.clone(),
+        }
+    }
+
+    // 同じ箇所を読み直すことがあるので、同じ診断は一度だけ記録する
+    pub fn report(&self, error: (Option<Span>, Message)) {
+        let (span, message) = error;
+        self.push(Diagnostic::error(code::SYNTAX, span, message));
+    }
+
+    pub fn warn(&self, warning: Diagnostic) {
+        self.push(warning);
+    }
+
+    fn push(&self, diagnostic: Diagnostic) {
+        let mut diagnostics = self.diagnostics.borrow_mut();
+        if !diagnostics.contains(&diagnostic) {
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.borrow().clone()
+    }
+}
@@ -0,0 +1,160 @@
+use general::SemiGroup;
+
+use sourcecode::Code;
+use sourcecode::Span;
+
+use diagnostic::Message;
+
+use token::Token;
+use token::Operator;
+use token::Bracket;
+use token::BracketSide;
+
+use parse::SyntaxTree;
+use parse::TokenReader;
+
+pub enum TypeExpression {
+    Named(Code<String>),
+    Pointer(Box<TypeExpression>, Span),
+    Array(Box<TypeExpression>, Code<i64>, Span),
+}
+
+impl TypeExpression {
+    // ": 型" があれば読む
+    pub fn parse_annotation(token_reader: &mut TokenReader)
+    -> Result<Option<TypeExpression>, (Option<Span>, Message)> {
+        match token_reader.try_next(|token| if token.value == Token::Colon { Ok(()) } else { Err(()) }) {
+            Ok(_) => TypeExpression::parse(token_reader).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+impl TypeExpression {
+    // "[" の後の "型; 長さ]" を読む
+    fn parse_array(token_reader: &mut TokenReader, open: Span)
+    -> Result<TypeExpression, (Option<Span>, Message)> {
+        let element = TypeExpression::parse(token_reader)?;
+        match token_reader.next() {
+            Some(token) if token.value == Token::Semicolon => (),
+            Some(token) => return Err((Some(token.span), Message::Expected(";"))),
+            None => return Err((None, Message::Expected(";"))),
+        };
+        let length = match token_reader.next() {
+            Some(token) => match token.value {
+                Token::Number(n) => token.map_const(n),
+                _ => return Err((Some(token.span), Message::ExpectedArrayLength)),
+            },
+            None => return Err((None, Message::ExpectedArrayLength)),
+        };
+        match token_reader.next() {
+            Some(token) => match token.value {
+                Token::Bracket(BracketSide::Right(Bracket::Square)) => {
+                    let span = open.plus(&token.span);
+                    Ok(TypeExpression::Array(Box::new(element), length, span))
+                },
+                _ => Err((Some(token.span), Message::Expected("]"))),
+            },
+            None => Err((None, Message::Expected("]"))),
+        }
+    }
+}
+
+impl SyntaxTree for TypeExpression {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<TypeExpression, (Option<Span>, Message)> {
+        match token_reader.next() {
+            Some(token) => match &token.value {
+                Token::Identifier(name) => Ok(TypeExpression::Named(token.map_const(name.clone()))),
+                Token::Operator(Operator::Mul) => TypeExpression::parse(token_reader).map(|pointee| {
+                    let span = token.span.plus(&pointee.span());
+                    TypeExpression::Pointer(Box::new(pointee), span)
+                }),
+                Token::Bracket(BracketSide::Left(Bracket::Square)) => TypeExpression::parse_array(token_reader, token.span),
+                _ => Err((Some(token.span), Message::ExpectedType)),
+            },
+            None => Err((None, Message::ExpectedType)),
+        }
+    }
+
+    fn span(&self) -> Span {
+        match self {
+            TypeExpression::Named(name) => name.span,
+            TypeExpression::Pointer(_, span) => *span,
+            TypeExpression::Array(_, _, span) => *span,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use token::tokenize;
+
+    #[test]
+    fn test_parse_annotation() {
+        let src = ": bool";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let annotation = TypeExpression::parse_annotation(&mut token_reader).unwrap();
+
+        if let Some(TypeExpression::Named(name)) = annotation {
+            assert_eq!(name.value, "bool".to_string());
+        } else {
+            panic!("型注釈になっていません。")
+        }
+    }
+
+    #[test]
+    fn test_parse_pointer() {
+        let src = "**int";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let type_expression = TypeExpression::parse(&mut token_reader).unwrap();
+
+        assert_eq!(type_expression.span(), Span::new(0, 0, 5));
+        if let TypeExpression::Pointer(pointee, _) = type_expression {
+            if let TypeExpression::Pointer(_, _) = *pointee {
+            } else {
+                panic!("ポインタのポインタになっていません。")
+            }
+        } else {
+            panic!("ポインタになっていません。")
+        }
+    }
+
+    #[test]
+    fn test_parse_array() {
+        let src = "[*int; 10]";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let type_expression = TypeExpression::parse(&mut token_reader).unwrap();
+
+        assert_eq!(type_expression.span(), Span::new(0, 0, 10));
+        if let TypeExpression::Array(element, length, _) = type_expression {
+            assert_eq!(length.value, 10);
+            if let TypeExpression::Pointer(_, _) = *element {
+            } else {
+                panic!("要素がポインタになっていません。")
+            }
+        } else {
+            panic!("配列になっていません。")
+        }
+    }
+
+    #[test]
+    fn test_parse_no_annotation() {
+        let src = ":= 1";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let annotation = TypeExpression::parse_annotation(&mut token_reader).unwrap();
+
+        assert!(annotation.is_none());
+        assert!(token_reader.peek().is_some());
+    }
+}
@@ -1,24 +1,47 @@
 use general::SemiGroup;
-use general::TryReader;
 
 use token::Token;
 
 use sourcecode::Code;
 use sourcecode::Span;
 
+use diagnostic::Message;
+
 use token::Operator;
 
 use parse::SyntaxTree;
+use parse::TokenReader;
 use parse::Primary;
 
 pub enum Unary {
     Positive(Primary, Span),
     Negative(Primary, Span),
+    Address(Box<Unary>, Span),
+    Dereference(Box<Unary>, Span),
+    BitNot(Box<Unary>, Span),
+}
+
+impl Unary {
+    // 符号のついていない項
+    pub fn primary(&self) -> Option<&Primary> {
+        match self {
+            Unary::Positive(primary, span) if *span == primary.span() => Some(primary),
+            _ => None,
+        }
+    }
+
+    // 符号のついていない識別子
+    pub fn identifier(&self) -> Option<&Code<String>> {
+        match self.primary() {
+            Some(Primary::Identifier(name)) => Some(name),
+            _ => None,
+        }
+    }
 }
 
 impl SyntaxTree for Unary {
-    fn parse(token_reader: &mut TryReader<Code<Token>>)
-    -> Result<Unary, (Option<Span>, String)> {
+    fn parse(token_reader: &mut TokenReader)
+    -> Result<Unary, (Option<Span>, Message)> {
         let operator = token_reader.try_(|reader| {
             let maybe_token = reader.next();
             match maybe_token {
@@ -27,6 +50,9 @@ impl SyntaxTree for Unary {
                     match token.value {
                         Token::Operator(Operator::Add) => Ok((Operator::Add, token.span)),
                         Token::Operator(Operator::Sub) => Ok((Operator::Sub, token.span)),
+                        Token::Operator(Operator::Ampersand) => Ok((Operator::Ampersand, token.span)),
+                        Token::Operator(Operator::Mul) => Ok((Operator::Mul, token.span)),
+                        Token::Operator(Operator::BitNot) => Ok((Operator::BitNot, token.span)),
                         _ => Err(()),
                     }
                 }
@@ -41,6 +67,18 @@ impl SyntaxTree for Unary {
                 let s = span.plus(&primary.span());
                 Unary::Negative(primary, s)
             }),
+            Ok((Operator::Ampersand, span)) => Unary::parse(token_reader).map(|unary| {
+                let s = span.plus(&unary.span());
+                Unary::Address(Box::new(unary), s)
+            }),
+            Ok((Operator::Mul, span)) => Unary::parse(token_reader).map(|unary| {
+                let s = span.plus(&unary.span());
+                Unary::Dereference(Box::new(unary), s)
+            }),
+            Ok((Operator::BitNot, span)) => Unary::parse(token_reader).map(|unary| {
+                let s = span.plus(&unary.span());
+                Unary::BitNot(Box::new(unary), s)
+            }),
             _ =>  Primary::parse(token_reader).map(|primary| {
                 let span = primary.span();
                 Unary::Positive(primary, span)
@@ -48,10 +86,14 @@ impl SyntaxTree for Unary {
         }
     }
 
+    #[allow(clippy::clone_on_copy)]
     fn span(&self) -> Span {
         match self {
             Unary::Positive(_, span) => span.clone(),
             Unary::Negative(_, span) => span.clone(),
+            Unary::Address(_, span) => span.clone(),
+            Unary::Dereference(_, span) => span.clone(),
+            Unary::BitNot(_, span) => span.clone(),
         }
     }
 }
@@ -65,7 +107,7 @@ mod tests {
     fn test_parse_positive() {
         let src = "+3";
         let tokens = tokenize(&src.to_string()).unwrap();
-        let mut token_reader = TryReader::new(&tokens);
+        let mut token_reader = TokenReader::new(&tokens);
 
         let unary = Unary::parse(&mut token_reader).unwrap();
 
@@ -80,7 +122,7 @@ mod tests {
         // 6
         let src = "6";
         let tokens = tokenize(&src.to_string()).unwrap();
-        let mut token_reader = TryReader::new(&tokens);
+        let mut token_reader = TokenReader::new(&tokens);
 
         let unary = Unary::parse(&mut token_reader).unwrap();
 
@@ -94,7 +136,7 @@ mod tests {
     fn test_parse_negative() {
         let src = "-5";
         let tokens = tokenize(&src.to_string()).unwrap();
-        let mut token_reader = TryReader::new(&tokens);
+        let mut token_reader = TokenReader::new(&tokens);
 
         let unary = Unary::parse(&mut token_reader).unwrap();
 
@@ -103,4 +145,22 @@ mod tests {
             panic!("正になっています。")
         }
     }
-}
\ No newline at end of file
+    #[test]
+    fn test_parse_address_and_dereference() {
+        let src = "*&x";
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+
+        let unary = Unary::parse(&mut token_reader).unwrap();
+
+        if let Unary::Dereference(inner, span) = unary {
+            assert_eq!(span, Span::new(0, 0, 3));
+            if let Unary::Address(_, _) = *inner {
+            } else {
+                panic!("アドレスになっていません。")
+            }
+        } else {
+            panic!("参照外しになっていません。")
+        }
+    }
+}
@@ -4,4 +4,7 @@ pub use self::position::Position;
 
 mod span;
 pub use self::span::Span;
-pub use self::span::Code;
\ No newline at end of file
+pub use self::span::Code;
+
+mod source;
+pub use self::source::Source;
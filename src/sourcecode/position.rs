@@ -1,4 +1,4 @@
-#[derive(Debug, PartialEq, Clone, Copy, Eq, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Clone, Copy, Eq, PartialOrd, Ord, Hash)]
 pub struct Position {
     pub line: usize,
     pub pos: usize,
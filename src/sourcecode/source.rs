@@ -0,0 +1,101 @@
+use std::cmp::max;
+use std::cmp::min;
+use std::fs;
+use std::io;
+use std::io::Read;
+
+use cli::Input;
+
+use sourcecode::Position;
+use sourcecode::Span;
+
+// 列(Position.pos)は行頭からの文字数で数える
+pub struct Source {
+    pub name: String,
+    pub content: String,
+}
+
+impl Source {
+    // 改行はLFにそろえる
+    pub fn new(name: String, content: String) -> Source {
+        Source { name, content: content.replace("\r\n", "\n") }
+    }
+
+    pub fn read(input: &Input) -> io::Result<Source> {
+        match input {
+            Input::Stdin => {
+                let mut content = String::new();
+                io::stdin().read_to_string(&mut content)?;
+                Ok(Source::new("<stdin>".to_string(), content))
+            },
+            Input::File(path) => {
+                fs::read_to_string(path).map(|content| Source::new(path.clone(), content))
+            },
+        }
+    }
+
+    pub fn location(&self, position: Position) -> String {
+        format!("{}:{}:{}", self.name, position.line + 1, position.pos + 1)
+    }
+
+    pub fn point_span(&self, span: Span, message: &str) -> String {
+        format!("{}\n{}", self.location(span.start), self.snippet(span, message))
+    }
+
+    // spanを含む行を並べ、その下に^を引いて最後にmessageを添える
+    pub fn snippet(&self, span: Span, message: &str) -> String {
+        let Span{start, end} = span;
+        let mut lines = Vec::new();
+        for i in start.line..end.line+1 {
+            let line = self.line(i);
+            let line_start = Position{ line: i, pos: 0 };
+            // 最後の行は行末の改行も指せるよう、行の長さで切らない
+            let line_end = if i == end.line { end } else { Position{ line: i, pos: line.chars().count() } };
+            lines.push(line.to_string());
+            lines.push(Self::indicator(line, max(start, line_start).pos, min(end, line_end).pos));
+        }
+        format!("{}{}", lines.join("\n"), message)
+    }
+
+    fn line(&self, index: usize) -> &str {
+        self.content.split('\n').nth(index).unwrap_or("")
+    }
+
+    // start文字目からend文字目までに^を引く。タブは残して表示上の位置を合わせる
+    fn indicator(line: &str, start: usize, end: usize) -> String {
+        let padding = line.chars()
+            .chain(std::iter::repeat(' '))
+            .take(start)
+            .map(|c| if c == '\t' { '\t' } else { ' ' });
+        padding.chain(std::iter::repeat_n('^', end.saturating_sub(start))).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_span() {
+        let src = Source::new("a.src".to_string(), "func main() {\r\n\tlet 変数 := x\r\n}".to_string());
+
+        assert_eq!(src.content, "func main() {\n\tlet 変数 := x\n}".to_string());
+        assert_eq!(
+            src.point_span(Span::new(1, 5, 2), "エラー"),
+            "a.src:2:6\n\tlet 変数 := x\n\t    ^^エラー".to_string(),
+        );
+        assert_eq!(
+            src.point_span(Span::new(1, 11, 1), "エラー"),
+            "a.src:2:12\n\tlet 変数 := x\n\t          ^エラー".to_string(),
+        );
+
+        let span = Span {
+            start: Position{ line: 0, pos: 12 },
+            end: Position{ line: 1, pos: 3 },
+        };
+        assert_eq!(
+            src.point_span(span, "エラー"),
+            "a.src:1:13\nfunc main() {\n            ^\n\tlet 変数 := x\n^^^エラー".to_string(),
+        );
+    }
+}
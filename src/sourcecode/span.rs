@@ -5,7 +5,7 @@ use::general::SemiGroup;
 
 use sourcecode::Position;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
 pub struct Span {
     pub start: Position,
     pub end: Position,
@@ -1,7 +1,7 @@
+#[allow(clippy::module_inception)]
 mod token;
 mod tokenize;
 pub use self::token::Token;
-pub use self::token::Dictionary;
 pub use self::token::Operator;
 pub use self::token::Bracket;
 pub use self::token::BracketSide;
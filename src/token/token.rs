@@ -6,10 +6,15 @@ pub enum Token {
     Operator(Operator),
     ReservedWord(ReservedWord),
     Number(i64),
+    String(String),
+    Character(u8),
     Bracket(BracketSide),
     Identifier(String),
     LineBreak,
     Comma,
+    Colon,
+    Semicolon,
+    Dot,
 }
 
 impl Token {
@@ -46,6 +51,36 @@ impl Token {
     pub const fn assign() -> Token {
         Token::Operator(Operator::Assign)
     }
+    pub const fn ampersand() -> Token {
+        Token::Operator(Operator::Ampersand)
+    }
+    pub const fn and() -> Token {
+        Token::Operator(Operator::And)
+    }
+    pub const fn or() -> Token {
+        Token::Operator(Operator::Or)
+    }
+    pub const fn not() -> Token {
+        Token::Operator(Operator::Not)
+    }
+    pub const fn rem() -> Token {
+        Token::Operator(Operator::Mod)
+    }
+    pub const fn bit_or() -> Token {
+        Token::Operator(Operator::BitOr)
+    }
+    pub const fn bit_xor() -> Token {
+        Token::Operator(Operator::BitXor)
+    }
+    pub const fn bit_not() -> Token {
+        Token::Operator(Operator::BitNot)
+    }
+    pub const fn shl() -> Token {
+        Token::Operator(Operator::ShiftLeft)
+    }
+    pub const fn shr() -> Token {
+        Token::Operator(Operator::ShiftRight)
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Copy, Hash, Eq)]
@@ -54,6 +89,7 @@ pub enum Operator {
     Sub,
     Mul,
     Div,
+    Mod,
     Equal,
     NotEqual,
     Less,
@@ -61,6 +97,15 @@ pub enum Operator {
     LessEq,
     GreaterEq,
     Assign,
+    Ampersand,
+    And,
+    Or,
+    Not,
+    BitOr,
+    BitXor,
+    BitNot,
+    ShiftLeft,
+    ShiftRight,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy, Hash, Eq)]
@@ -71,6 +116,40 @@ pub enum ReservedWord {
     Then,
     Else,
     Func,
+    While,
+    Do,
+    Break,
+    Continue,
+    Const,
+    Struct,
+    True,
+    False,
+}
+
+// 予約語とその綴り。字句解析はこの表だけを見る
+const RESERVED_WORDS: [(&str, ReservedWord); 14] = [
+    ("let", ReservedWord::Let),
+    ("return", ReservedWord::Return),
+    ("if", ReservedWord::If),
+    ("then", ReservedWord::Then),
+    ("else", ReservedWord::Else),
+    ("func", ReservedWord::Func),
+    ("while", ReservedWord::While),
+    ("do", ReservedWord::Do),
+    ("break", ReservedWord::Break),
+    ("continue", ReservedWord::Continue),
+    ("const", ReservedWord::Const),
+    ("struct", ReservedWord::Struct),
+    ("true", ReservedWord::True),
+    ("false", ReservedWord::False),
+];
+
+impl ReservedWord {
+    pub fn from_word(word: &str) -> Option<ReservedWord> {
+        RESERVED_WORDS.iter()
+            .find(|(spelling, _)| *spelling == word)
+            .map(|(_, reserved_word)| *reserved_word)
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -83,6 +162,7 @@ pub enum BracketSide {
 pub enum Bracket {
     Round,
     Curly,
+    Square,
 }
 
 pub struct Dictionary {
@@ -110,6 +190,7 @@ impl Dictionary {
         &self.terminal
     }
 
+    #[allow(clippy::ptr_arg)]
     fn insert(&mut self, s: &String, t: &Token) {
         // csがmutなのが微妙なのでHaskell風のListを使いたい
         fn recursive_insert(dict: &mut Dictionary, cs: &mut Chars, t: &Token) {
@@ -132,6 +213,7 @@ impl Dictionary {
         string_to_token.insert(String::from("-"), Token::sub());
         string_to_token.insert(String::from("*"), Token::mul());
         string_to_token.insert(String::from("/"), Token::div());
+        string_to_token.insert(String::from("%"), Token::rem());
         string_to_token.insert(String::from("<"), Token::lt());
         string_to_token.insert(String::from("<="), Token::le());
         string_to_token.insert(String::from(">"), Token::gt());
@@ -142,7 +224,21 @@ impl Dictionary {
         string_to_token.insert(String::from(")"), Token::Bracket(BracketSide::Right(Bracket::Round)));
         string_to_token.insert(String::from("{"), Token::Bracket(BracketSide::Left(Bracket::Curly)));
         string_to_token.insert(String::from("}"), Token::Bracket(BracketSide::Right(Bracket::Curly)));
+        string_to_token.insert(String::from("["), Token::Bracket(BracketSide::Left(Bracket::Square)));
+        string_to_token.insert(String::from("]"), Token::Bracket(BracketSide::Right(Bracket::Square)));
         string_to_token.insert(String::from(":="), Token::assign());
+        string_to_token.insert(String::from(":"), Token::Colon);
+        string_to_token.insert(String::from("."), Token::Dot);
+        string_to_token.insert(String::from(";"), Token::Semicolon);
+        string_to_token.insert(String::from("&"), Token::ampersand());
+        string_to_token.insert(String::from("&&"), Token::and());
+        string_to_token.insert(String::from("||"), Token::or());
+        string_to_token.insert(String::from("!"), Token::not());
+        string_to_token.insert(String::from("|"), Token::bit_or());
+        string_to_token.insert(String::from("^"), Token::bit_xor());
+        string_to_token.insert(String::from("~"), Token::bit_not());
+        string_to_token.insert(String::from("<<"), Token::shl());
+        string_to_token.insert(String::from(">>"), Token::shr());
 
         Dictionary::of(&string_to_token)
     }
@@ -160,5 +256,21 @@ mod tests {
 
         let le = dictionary.next.get(&'<').and_then(|d| d.next.get(&'=')).and_then(|d| d.terminal.as_ref());
         assert_eq!(Some(&Token::le()), le);
+
+        let colon = dictionary.next.get(&':');
+        assert_eq!(Some(&Token::Colon), colon.and_then(|d| d.terminal.as_ref()));
+        assert_eq!(Some(&Token::assign()), colon.and_then(|d| d.next.get(&'=')).and_then(|d| d.terminal.as_ref()));
+
+        let not = dictionary.next.get(&'!');
+        assert_eq!(Some(&Token::not()), not.and_then(|d| d.terminal.as_ref()));
+        assert_eq!(Some(&Token::neq()), not.and_then(|d| d.next.get(&'=')).and_then(|d| d.terminal.as_ref()));
+    }
+
+    #[test]
+    fn test_reserved_words() {
+        for (spelling, reserved_word) in RESERVED_WORDS.iter() {
+            assert_eq!(ReservedWord::from_word(spelling), Some(*reserved_word));
+        }
+        assert_eq!(ReservedWord::from_word("lets"), None);
     }
 }
\ No newline at end of file
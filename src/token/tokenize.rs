@@ -1,16 +1,16 @@
-use std::i64;
-
 use general::TryReader;
 
-use sourcecode::Position;
 use sourcecode::Code;
 use sourcecode::Span;
 
+use diagnostic::Message;
+
 use token::token::Token;
 use token::token::ReservedWord;
 use token::token::Dictionary;
 
-pub fn tokenize(s: &String) -> Result<Vec<Code<Token>>, Position> {
+#[allow(clippy::needless_borrow, clippy::ptr_arg, clippy::redundant_pattern_matching)]
+pub fn tokenize(s: &String) -> Result<Vec<Code<Token>>, (Span, Message)> {
     let dictionary = Dictionary::default();
     let cs = &s.chars().collect();
     let mut reader = TryReader::new(cs);
@@ -18,10 +18,30 @@ pub fn tokenize(s: &String) -> Result<Vec<Code<Token>>, Position> {
     let mut line = 0;
     let mut pos = 0;
     while reader.has_next() {
-        if let Ok((consume, _)) = reader.try_(|mut r| character(&mut r, ' ')) {
+        // 改行以外の空白(タブやCRLFのCRを含む)は読み飛ばす
+        if let Ok((consume, _)) = reader.try_(blank) {
+            pos += consume;
+            continue;
+        }
+        if let Ok((consume, _)) = reader.try_(line_comment) {
             pos += consume;
             continue;
         }
+        match reader.try_(block_comment) {
+            Ok((_, comment)) => {
+                for c in comment.chars() {
+                    if c == '\n' {
+                        line += 1;
+                        pos = 0;
+                    } else {
+                        pos += 1;
+                    }
+                }
+                continue;
+            },
+            Err(Some(_)) => return Err((Span::new(line, pos, 2), Message::UnterminatedComment)),
+            Err(None) => (),
+        }
         if let Ok((consume, _)) = reader.try_(|mut r| character(&mut r, ',')) {
             let span = Span::new(line, pos, consume);
             tokens.push(Code {
@@ -41,8 +61,33 @@ pub fn tokenize(s: &String) -> Result<Vec<Code<Token>>, Position> {
             line += 1;
             continue;
         }
-        if let Ok((consume, n)) = reader.try_(number) {
+        if let Some(&quote) = reader.peek().filter(|&&c| c == '"' || c == '\'') {
+            let (consume, content) = match reader.try_(|r| quoted(r, quote)) {
+                Ok(result) => result,
+                Err(_) => return Err((Span::new(line, pos, 1), Message::UnterminatedQuote)),
+            };
             let span = Span::new(line, pos, consume);
+            let value = if quote == '"' {
+                Token::String(content)
+            } else {
+                match content.as_bytes() {
+                    &[c] => Token::Character(c),
+                    _ => return Err((span, Message::InvalidCharacterLiteral)),
+                }
+            };
+            tokens.push(Code {
+                value,
+                span,
+            });
+            pos += consume;
+            continue;
+        }
+        if let Ok((consume, result)) = reader.try_(number) {
+            let span = Span::new(line, pos, consume);
+            let n = match result {
+                Ok(n) => n,
+                Err(message) => return Err((span, message)),
+            };
             tokens.push(Code {
                 value: Token::Number(n),
                 span,
@@ -52,14 +97,9 @@ pub fn tokenize(s: &String) -> Result<Vec<Code<Token>>, Position> {
         }
         if let Ok((consume, w)) = reader.try_(word) {
             let span = Span::new(line, pos, consume);
-            let token = match w.as_str() {
-                "return" => Token::ReservedWord(ReservedWord::Return),
-                "let" => Token::ReservedWord(ReservedWord::Let),
-                "if" => Token::ReservedWord(ReservedWord::If),
-                "then" => Token::ReservedWord(ReservedWord::Then),
-                "else" => Token::ReservedWord(ReservedWord::Else),
-                "func" => Token::ReservedWord(ReservedWord::Func),
-                wd => Token::Identifier(wd.to_string())
+            let token = match ReservedWord::from_word(&w) {
+                Some(reserved_word) => Token::ReservedWord(reserved_word),
+                None => Token::Identifier(w),
             };
             tokens.push(Code {
                 value: token,
@@ -77,11 +117,12 @@ pub fn tokenize(s: &String) -> Result<Vec<Code<Token>>, Position> {
             pos += consume;
             continue;
         }
-        return Err(Position{ line, pos })
+        return Err((Span::new(line, pos, 1), Message::UnknownToken))
     }
     Ok(tokens)
 }
 
+#[allow(clippy::map_clone, clippy::needless_borrow)]
 fn operator(reader: &mut TryReader<char>, dict: &Dictionary) -> Result<Token, Option<Token>> {
     reader.try_(|mut r| {
         r.next()
@@ -100,40 +141,125 @@ fn operator(reader: &mut TryReader<char>, dict: &Dictionary) -> Result<Token, Op
     .map(|(_, t)| t)
 }
 
+fn blank(reader: &mut TryReader<char>) -> Result<(), ()> {
+    match reader.next() {
+        Some(&c) if c.is_whitespace() && c != '\n' => Ok(()),
+        _ => Err(()),
+    }
+}
+
 fn character(reader: &mut TryReader<char>, target: char) -> Result<(), Option<()>> {
     reader.next()
         .ok_or(None)
         .and_then(|&c| if c==target { Ok(()) } else { Err(None) })
 }
 
-fn word(reader: &mut TryReader<char>) -> Result<String, Option<String>> {
-    reader.try_(|r| {
-        match r.next() {
-            Some(&c) if c.is_alphanumeric() => {
-                match word(r) {
-                    Ok(s) => Ok(String::from(format!("{}{}", c, s))),
-                    _ => Ok(c.to_string()),
-                }
+// 行末までのコメント。改行は読まない
+fn line_comment(reader: &mut TryReader<char>) -> Result<(), ()> {
+    if reader.next() != Some(&'/') || reader.next() != Some(&'/') {
+        return Err(());
+    }
+    reader.drop_while(|&c| c != '\n');
+    Ok(())
+}
+
+// /* から */ までのコメント。入れ子にはならず、最初の */ で閉じる
+// 始まっていなければ Err(None)、閉じられていなければ Err(Some(()))
+fn block_comment(reader: &mut TryReader<char>) -> Result<String, Option<()>> {
+    if reader.next() != Some(&'/') || reader.next() != Some(&'*') {
+        return Err(None);
+    }
+    let mut comment = "/*".to_string();
+    loop {
+        match reader.next() {
+            Some('*') if reader.peek() == Some(&'/') => {
+                reader.next();
+                comment.push_str("*/");
+                return Ok(comment);
             },
-            _ => Err(None),
+            Some(&c) => comment.push(c),
+            None => return Err(Some(())),
         }
-    }).map(|(_, r)| r)
+    }
 }
 
-fn number(reader: &mut TryReader<char>) -> Result<i64, Option<i64>> {
-    match reader.next() {
-        None => Err(None),
-        Some(c) => {
-            c.to_digit(10).map(|n| n as i64).map(|n| {
-                match reader.try_(number) {
-                    Ok((b, m)) => n * (10 as i64).pow(b as u32) + m,
-                    Err(_) => n,
-                }
-            }).ok_or(None)
+// 引用符で囲まれた文字列を読み、エスケープシーケンスを展開する
+fn quoted(reader: &mut TryReader<char>, quote: char) -> Result<String, ()> {
+    if reader.next() != Some(&quote) {
+        return Err(());
+    }
+    let mut content = String::new();
+    loop {
+        match reader.next() {
+            Some(&c) if c == quote => return Ok(content),
+            Some('\\') => match reader.next() {
+                Some('n') => content.push('\n'),
+                Some('t') => content.push('\t'),
+                Some('r') => content.push('\r'),
+                Some('0') => content.push('\0'),
+                Some('\\') => content.push('\\'),
+                Some('"') => content.push('"'),
+                Some('\'') => content.push('\''),
+                _ => return Err(()),
+            },
+            Some('\n') | None => return Err(()),
+            Some(&c) => content.push(c),
         }
     }
 }
 
+// 識別子と予約語は [A-Za-z_][A-Za-z0-9_]*
+fn word(reader: &mut TryReader<char>) -> Result<String, ()> {
+    let mut w = match reader.next() {
+        Some(&c) if c.is_ascii_alphabetic() || c == '_' => c.to_string(),
+        _ => return Err(()),
+    };
+    while let Ok(c) = reader.try_next(|&c| if is_identifier_char(c) { Ok(c) } else { Err(()) }) {
+        w.push(c);
+    }
+    Ok(w)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// 数字で始まる英数字と _ の並びを数値リテラルとして読む
+// 数字で始まらなければ Err(())、読めたが値として正しくなければ Ok(Err(理由))
+fn number(reader: &mut TryReader<char>) -> Result<Result<i64, Message>, ()> {
+    match reader.peek() {
+        Some(c) if c.is_ascii_digit() => (),
+        _ => return Err(()),
+    }
+    let mut literal = String::new();
+    while let Ok(c) = reader.try_next(|&c| if is_identifier_char(c) { Ok(c) } else { Err(()) }) {
+        literal.push(c);
+    }
+    Ok(integer(&literal))
+}
+
+// 0x, 0b, 0o の接頭辞で基数を選び、_ は区切りとして読み飛ばす
+fn integer(literal: &str) -> Result<i64, Message> {
+    let (radix, digits) = match literal.get(0..2) {
+        Some("0x") => (16, &literal[2..]),
+        Some("0b") => (2, &literal[2..]),
+        Some("0o") => (8, &literal[2..]),
+        _ => (10, literal),
+    };
+    let mut value: Option<i64> = None;
+    for c in digits.chars().filter(|&c| c != '_') {
+        let digit = match c.to_digit(radix) {
+            Some(digit) => digit as i64,
+            None => return Err(Message::InvalidDigit(c)),
+        };
+        value = match value.unwrap_or(0).checked_mul(radix as i64).and_then(|v| v.checked_add(digit)) {
+            Some(v) => Some(v),
+            None => return Err(Message::IntegerTooLarge),
+        };
+    }
+    value.ok_or(Message::MissingDigits)
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -170,6 +296,87 @@ mod tests {
         assert_eq!(findable_tokens.len(), 7);
     }
 
+    #[test]
+    fn test_tokenize_string() {
+        let src = String::from("puts(\"a\\tb\\n\") == '\\n'");
+
+        let findable_tokens = tokenize(&src).unwrap();
+
+        assert_eq!(findable_tokens[2].value, Token::String("a\tb\n".to_string()));
+        assert_eq!(findable_tokens[2].span, Span::new(0, 5, 8));
+
+        assert_eq!(findable_tokens[5].value, Token::Character(b'\n'));
+        assert_eq!(findable_tokens[5].span, Span::new(0, 18, 4));
+
+        assert_eq!(tokenize(&String::from("1 + \"abc")).err().unwrap().0, Span::new(0, 4, 1));
+        assert!(tokenize(&String::from("'ab'")).is_err());
+        assert!(tokenize(&String::from("\"\\q\"")).is_err());
+    }
+
+    #[test]
+    fn test_tokenize_comment() {
+        let src = String::from("1 // one\n/* two\n * lines */ 2 /* three */ / 3");
+
+        let findable_tokens = tokenize(&src).unwrap();
+
+        assert_eq!(findable_tokens[0].value, Token::Number(1));
+        assert_eq!(findable_tokens[1].value, Token::LineBreak);
+        assert_eq!(findable_tokens[1].span, Span::new(0, 8, 1));
+
+        assert_eq!(findable_tokens[2].value, Token::Number(2));
+        assert_eq!(findable_tokens[2].span, Span::new(2, 12, 1));
+
+        assert_eq!(findable_tokens[3].value, Token::div());
+        assert_eq!(findable_tokens[3].span, Span::new(2, 26, 1));
+
+        assert_eq!(findable_tokens[4].value, Token::Number(3));
+        assert_eq!(findable_tokens.len(), 5);
+
+        // 入れ子にはならないので、最初の */ でコメントが終わる
+        let rest: Vec<Token> = tokenize(&String::from("/* /* */ */")).unwrap().into_iter().map(|token| token.value).collect();
+        assert_eq!(rest, vec![Token::mul(), Token::div()]);
+
+        let (span, _) = tokenize(&String::from("1\n  /* open\n\n")).err().unwrap();
+        assert_eq!(span, Span::new(1, 2, 2));
+    }
+
+    #[test]
+    fn test_tokenize_whitespace() {
+        let src = String::from("let\tx := 1\r\n\tname +\u{3000}x");
+
+        let findable_tokens = tokenize(&src).unwrap();
+
+        assert_eq!(findable_tokens[1].value, Token::Identifier("x".to_string()));
+        assert_eq!(findable_tokens[1].span, Span::new(0, 4, 1));
+
+        assert_eq!(findable_tokens[4].value, Token::LineBreak);
+        assert_eq!(findable_tokens[4].span, Span::new(0, 11, 1));
+
+        assert_eq!(findable_tokens[5].value, Token::Identifier("name".to_string()));
+        assert_eq!(findable_tokens[5].span, Span::new(1, 1, 4));
+
+        assert_eq!(findable_tokens[7].value, Token::Identifier("x".to_string()));
+        assert_eq!(findable_tokens[7].span, Span::new(1, 8, 1));
+    }
+
+    #[test]
+    fn test_tokenize_identifier() {
+        let src = String::from("let _snake_case2 := x1 + return_value");
+
+        let findable_tokens = tokenize(&src).unwrap();
+
+        assert_eq!(findable_tokens[0].value, Token::ReservedWord(ReservedWord::Let));
+        assert_eq!(findable_tokens[1].value, Token::Identifier("_snake_case2".to_string()));
+        assert_eq!(findable_tokens[1].span, Span::new(0, 4, 12));
+        assert_eq!(findable_tokens[3].value, Token::Identifier("x1".to_string()));
+        assert_eq!(findable_tokens[5].value, Token::Identifier("return_value".to_string()));
+
+        let (span, _) = tokenize(&String::from("let y := 1x")).err().unwrap();
+        assert_eq!(span, Span::new(0, 9, 2));
+        assert!(tokenize(&String::from("1_2__")).is_ok());
+        assert!(tokenize(&String::from("名前")).is_err());
+    }
+
     #[test]
     fn test_tokenize_relational() {
         let src = String::from("1 <= 3");
@@ -186,6 +393,22 @@ mod tests {
         assert_eq!(findable_tokens[2].span, Span::new(0, 5, 1));
     }
 
+    #[test]
+    fn test_tokenize_bitwise() {
+        let src = String::from("a<<2|~b>>1^c%4&d||e");
+
+        let tokens: Vec<Token> = tokenize(&src).unwrap().into_iter().map(|token| token.value).collect();
+
+        assert_eq!(tokens[1], Token::shl());
+        assert_eq!(tokens[3], Token::bit_or());
+        assert_eq!(tokens[4], Token::bit_not());
+        assert_eq!(tokens[6], Token::shr());
+        assert_eq!(tokens[8], Token::bit_xor());
+        assert_eq!(tokens[10], Token::rem());
+        assert_eq!(tokens[12], Token::ampersand());
+        assert_eq!(tokens[14], Token::or());
+    }
+
     #[test]
     fn test_tokenize_no_space() {
         let src = String::from("1+2");
@@ -206,7 +429,18 @@ mod tests {
     fn test_tokenize_number() {
         let src = "2".chars().collect();
         let mut reader = TryReader::new(&src);
-        assert_eq!(number(&mut reader), Ok(2));
+        assert_eq!(number(&mut reader), Ok(Ok(2)));
+
+        assert_eq!(integer("1_000_000"), Ok(1000000));
+        assert_eq!(integer("0xff"), Ok(255));
+        assert_eq!(integer("0xDead_Beef"), Ok(0xdeadbeef));
+        assert_eq!(integer("0b1010"), Ok(10));
+        assert_eq!(integer("0o17"), Ok(15));
+        assert_eq!(integer("9223372036854775807"), Ok(i64::MAX));
+        assert!(integer("9223372036854775808").is_err());
+        assert!(integer("0x").is_err());
+        assert!(integer("0b102").is_err());
+        assert!(integer("1x").is_err());
     }
 
     #[test]
@@ -217,4 +451,16 @@ mod tests {
         assert_eq!(tokens[0].value, Token::Identifier("foo".to_string()));
         assert_eq!(tokens[1].value, Token::Bracket(BracketSide::Left(Bracket::Round)));
     }
+
+    #[test]
+    fn test_tokenize_annotation() {
+        let src = "let x: int := 1".to_string();
+        let tokens = tokenize(&src).unwrap();
+
+        assert_eq!(tokens[1].value, Token::Identifier("x".to_string()));
+        assert_eq!(tokens[2].value, Token::Colon);
+        assert_eq!(tokens[3].value, Token::Identifier("int".to_string()));
+        assert_eq!(tokens[4].value, Token::assign());
+        assert_eq!(tokens[4].span, Span::new(0, 11, 2));
+    }
 }
\ No newline at end of file
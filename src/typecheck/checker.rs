@@ -0,0 +1,1244 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use general::SemiGroup;
+
+use sourcecode::Code;
+use sourcecode::Span;
+
+use token::Operator;
+
+use diagnostic::Diagnostic;
+use diagnostic::code;
+use diagnostic::Message;
+
+use parse::SyntaxTree;
+use parse::Root;
+use parse::Func;
+use parse::Global;
+use parse::GlobalKind;
+use parse::Statement;
+use parse::Expression;
+use parse::IfExpression;
+use parse::WhileExpression;
+use parse::BlockExpression;
+use parse::LogicalOr;
+use parse::LogicalAnd;
+use parse::LogicalNot;
+use parse::BitOr;
+use parse::BitXor;
+use parse::BitAnd;
+use parse::Equality;
+use parse::Relational;
+use parse::Shift;
+use parse::Add;
+use parse::Multiply;
+use parse::Unary;
+use parse::Primary;
+use parse::FnCall;
+use parse::StructDeclaration;
+use parse::StructLiteral;
+use parse::TypeExpression;
+
+use super::types::Type;
+use super::types::TypeTable;
+use super::layout::Layouts;
+
+struct Signature {
+    args: Vec<Type>,
+    return_type: Type,
+}
+
+pub struct TypeChecker {
+    structs: HashSet<String>,
+    layouts: Layouts,
+    functions: HashMap<String, Signature>,
+    globals: HashMap<String, Type>,
+    constants: HashSet<String>,
+    variables: Vec<HashMap<String, Type>>,
+    substitution: Vec<Option<Type>>,
+    return_type: Type,
+    expressions: HashMap<Span, Type>,
+    declarations: HashMap<Span, Type>,
+    // ポインタに読み替えた配列の式と、読み替える前の型
+    decayed: HashMap<Span, Type>,
+    narrowed: HashSet<Span>,
+    // 検査を続けられる誤りはここに集めて、最後にまとめて返す
+    errors: Vec<Diagnostic>,
+}
+
+impl TypeChecker {
+    pub fn check(root: &Root) -> Result<TypeTable, Vec<Diagnostic>> {
+        let mut checker = Self {
+            structs: HashSet::new(),
+            layouts: Layouts::new(),
+            functions: HashMap::new(),
+            globals: HashMap::new(),
+            constants: HashSet::new(),
+            variables: Vec::new(),
+            substitution: Vec::new(),
+            return_type: Type::Int,
+            expressions: HashMap::new(),
+            declarations: HashMap::new(),
+            decayed: HashMap::new(),
+            narrowed: HashSet::new(),
+            errors: Vec::new(),
+        };
+        // 構造体の配置が決まらないと何も検査できない
+        if let Err(e) = checker.declare_structs(&root.structs) {
+            return Err(vec![*e]);
+        }
+        let mut declared = Vec::new();
+        for func in &root.funcs {
+            let first = root.funcs.iter().find(|f| f.name == func.name).unwrap();
+            if first.name_span() != func.name_span() {
+                checker.errors.push(Self::duplicate(func.name_span(), first.name_span(), Message::DuplicateFunction));
+                declared.push(false);
+                continue;
+            }
+            match checker.declare_func(func) {
+                Ok(()) => declared.push(true),
+                Err(e) => {
+                    checker.errors.push(Self::type_error(e));
+                    declared.push(false);
+                },
+            }
+        }
+        // グローバル変数と定数は宣言した順に、それより前のものだけを参照できる
+        for global in &root.globals {
+            let first = root.globals.iter().find(|g| g.name.value == global.name.value).unwrap();
+            if first.name.span != global.name.span {
+                checker.errors.push(Self::duplicate(global.name.span, first.name.span, Message::DuplicateGlobal));
+                continue;
+            }
+            if let Err(e) = checker.check_global(global) {
+                checker.errors.push(Self::type_error(e));
+                // 誤りのあった変数も定義されたものとして扱い、参照先で重ねて報告しない
+                if !checker.globals.contains_key(&global.name.value) {
+                    let t = checker.new_variable();
+                    checker.globals.insert(global.name.value.clone(), t);
+                }
+            }
+        }
+        for (func, declared) in root.funcs.iter().zip(declared) {
+            if !declared {
+                continue;
+            }
+            if let Err(e) = checker.check_func(func) {
+                checker.errors.push(Self::type_error(e));
+            }
+        }
+        if !checker.errors.is_empty() {
+            let mut errors = checker.errors;
+            errors.sort_by_key(|diagnostic| diagnostic.primary.map(|span| span.start));
+            return Err(errors);
+        }
+        let expressions = checker.expressions.iter()
+            .map(|(span, t)| (*span, checker.finalize(t)))
+            .collect();
+        let declarations = checker.declarations.iter()
+            .map(|(span, t)| (*span, checker.finalize(t)))
+            .collect();
+        let decayed = checker.decayed.keys().cloned().collect();
+        Ok(TypeTable::new(expressions, declarations, decayed, checker.narrowed, checker.layouts))
+    }
+
+    fn type_error(error: (Span, Message)) -> Diagnostic {
+        let (span, message) = error;
+        Diagnostic::error(code::TYPE, Some(span), message)
+    }
+
+    fn duplicate(span: Span, first: Span, message: Message) -> Diagnostic {
+        Diagnostic::error(code::DUPLICATE_DEFINITION, Some(span), message)
+        .with_secondary(first, Message::FirstDefinition)
+    }
+
+    // 検査は続けられるので、報告だけしておく
+    fn report_struct_by_value(&mut self, span: Span, message: Message) {
+        self.errors.push(
+            Diagnostic::error(code::STRUCT_BY_VALUE, Some(span), message)
+            .with_note(Message::UsePointer)
+        );
+    }
+
+    // 先に名前を登録しておき、フィールドからは前後どちらの構造体も参照できるようにする
+    fn declare_structs(&mut self, structs: &[StructDeclaration]) -> Result<(), Box<Diagnostic>> {
+        for declaration in structs {
+            let name = &declaration.name;
+            if Type::from_name(&name.value).is_some() {
+                return Err(Box::new(Diagnostic::error(code::DUPLICATE_DEFINITION, Some(name.span), Message::BuiltinTypeName)));
+            }
+            if !self.structs.insert(name.value.clone()) {
+                let first = structs.iter().find(|d| d.name.value == name.value).unwrap();
+                return Err(Box::new(Self::duplicate(name.span, first.name.span, Message::DuplicateType)));
+            }
+            for field in &declaration.fields {
+                let first = declaration.fields.iter().find(|f| f.name.value == field.name.value).unwrap();
+                if first.name.span != field.name.span {
+                    return Err(Box::new(Self::duplicate(field.name.span, first.name.span, Message::DuplicateField)));
+                }
+            }
+        }
+        for declaration in structs {
+            if let Err(e) = self.layout_struct(declaration, structs, &mut Vec::new()) {
+                return Err(Box::new(Self::type_error(e)));
+            }
+        }
+        Ok(())
+    }
+
+    // 値として含む構造体を先に配置する
+    fn layout_struct(&mut self, declaration: &StructDeclaration, structs: &[StructDeclaration], visiting: &mut Vec<String>)
+    -> Result<(), (Span, Message)> {
+        let name = &declaration.name.value;
+        if self.layouts.get(name).is_some() {
+            return Ok(());
+        }
+        if visiting.contains(name) {
+            return Err((declaration.name.span, Message::RecursiveStruct));
+        }
+        visiting.push(name.clone());
+        let mut fields: Vec<(String, Type)> = Vec::new();
+        for field in &declaration.fields {
+            let t = self.resolve_type_expression(&field.type_expression)?;
+            if let Some(inner) = Self::embedded_struct(&t) {
+                let inner_declaration = structs.iter().find(|d| d.name.value == *inner).unwrap();
+                self.layout_struct(inner_declaration, structs, visiting)?;
+            }
+            fields.push((field.name.value.clone(), t));
+        }
+        visiting.pop();
+        self.layouts.define(name, fields);
+        Ok(())
+    }
+
+    // ポインタを介さずに含む構造体
+    fn embedded_struct(t: &Type) -> Option<&String> {
+        match t {
+            Type::Struct(name) => Some(name),
+            Type::Array(element, _) => Self::embedded_struct(element),
+            _ => None,
+        }
+    }
+
+    fn is_struct(&self, t: &Type) -> bool {
+        matches!(self.resolve(t), Type::Struct(_))
+    }
+
+    fn declare_func(&mut self, func: &Func) -> Result<(), (Span, Message)> {
+        let mut args = Vec::new();
+        for arg in &func.args {
+            match self.annotated_type(&arg.type_annotation) {
+                // 配列の引数はポインタとして受け取る
+                Ok(Type::Array(element, _)) => args.push(Type::Pointer(element)),
+                Ok(t @ Type::Struct(_)) => {
+                    self.report_struct_by_value(arg.name.span, Message::StructByValueArgument);
+                    args.push(t);
+                },
+                Ok(t) => args.push(t),
+                Err(e) => return Err(e),
+            }
+        }
+        let return_type = match self.annotated_type(&func.return_type) {
+            Ok(t @ Type::Struct(_)) => {
+                self.report_struct_by_value(func.name_span(), Message::StructByValueReturn);
+                t
+            },
+            Ok(t) => t,
+            Err(e) => return Err(e),
+        };
+        self.functions.insert(func.name.clone(), Signature { args, return_type });
+        Ok(())
+    }
+
+    fn check_global(&mut self, global: &Global) -> Result<(), (Span, Message)> {
+        let t = self.annotated_type(&global.type_annotation)?;
+        self.variables = vec![HashMap::new()];
+        match &global.content {
+            Some(content) => {
+                let content_type = self.check_expression(content)?;
+                self.expect(&content_type, &t, content.span())?;
+            },
+            None => if global.type_annotation.is_none() {
+                return Err((global.name.span, Message::MissingTypeAnnotation));
+            },
+        }
+        if global.kind == GlobalKind::Constant {
+            self.constants.insert(global.name.value.clone());
+        }
+        self.globals.insert(global.name.value.clone(), t.clone());
+        self.declarations.insert(global.name.span, t);
+        Ok(())
+    }
+
+    fn check_func(&mut self, func: &Func) -> Result<(), (Span, Message)> {
+        let (args, return_type) = match self.functions.get(&func.name) {
+            Some(signature) => (signature.args.clone(), signature.return_type.clone()),
+            None => return Err((func.span(), Message::FunctionNotDeclared)),
+        };
+        self.return_type = return_type.clone();
+        self.variables = vec![HashMap::new()];
+        for (arg, t) in func.args.iter().zip(args) {
+            self.declare_variable(&arg.name, t)?;
+        }
+        let body_type = self.check_expression(&func.body)?;
+        self.expect(&body_type, &return_type, func.body.span())?;
+        // 注釈された戻り値の型は宣言のときに報告している
+        if func.return_type.is_none() && self.is_struct(&return_type) {
+            self.report_struct_by_value(func.body.span(), Message::StructByValueReturn);
+        }
+        Ok(())
+    }
+
+    // 型注釈がなければ推論する
+    fn annotated_type(&mut self, annotation: &Option<TypeExpression>) -> Result<Type, (Span, Message)> {
+        match annotation {
+            Some(type_expression) => self.resolve_type_expression(type_expression),
+            None => Ok(self.new_variable()),
+        }
+    }
+
+    fn resolve_type_expression(&self, type_expression: &TypeExpression) -> Result<Type, (Span, Message)> {
+        match type_expression {
+            TypeExpression::Named(name) => match Type::from_name(&name.value) {
+                Some(t) => Ok(t),
+                None if self.structs.contains(&name.value) => Ok(Type::Struct(name.value.clone())),
+                None => Err((name.span, Message::UndefinedType)),
+            },
+            TypeExpression::Pointer(pointee, _) => self.resolve_type_expression(pointee)
+                .map(|t| Type::Pointer(Box::new(t))),
+            TypeExpression::Array(element, length, _) => {
+                if length.value <= 0 {
+                    return Err((length.span, Message::ArrayLengthNotPositive));
+                }
+                self.resolve_type_expression(element)
+                    .map(|t| Type::Array(Box::new(t), length.value))
+            },
+        }
+    }
+
+    fn new_variable(&mut self) -> Type {
+        self.substitution.push(None);
+        Type::Variable(self.substitution.len() - 1)
+    }
+
+    fn resolve(&self, t: &Type) -> Type {
+        match t {
+            Type::Variable(i) => match &self.substitution[*i] {
+                Some(u) => self.resolve(u),
+                None => t.clone(),
+            },
+            _ => t.clone(),
+        }
+    }
+
+    // 最後まで決まらなかった型はintとする
+    fn finalize(&self, t: &Type) -> Type {
+        match self.resolve(t) {
+            Type::Variable(_) => Type::Int,
+            Type::Pointer(pointee) => Type::Pointer(Box::new(self.finalize(&pointee))),
+            Type::Array(element, length) => Type::Array(Box::new(self.finalize(&element)), length),
+            t => t,
+        }
+    }
+
+    fn occurs(&self, i: usize, t: &Type) -> bool {
+        match self.resolve(t) {
+            Type::Variable(j) => i == j,
+            Type::Pointer(pointee) => self.occurs(i, &pointee),
+            Type::Array(element, _) => self.occurs(i, &element),
+            _ => false,
+        }
+    }
+
+    fn unify(&mut self, a: &Type, b: &Type) -> bool {
+        match (self.resolve(a), self.resolve(b)) {
+            (Type::Variable(i), Type::Variable(j)) if i == j => true,
+            (Type::Variable(i), t) | (t, Type::Variable(i)) => {
+                if self.occurs(i, &t) {
+                    return false;
+                }
+                self.substitution[i] = Some(t);
+                true
+            },
+            (Type::Pointer(a), Type::Pointer(b)) => self.unify(&a, &b),
+            (Type::Array(a, n), Type::Array(b, m)) => n == m && self.unify(&a, &b),
+            (a, b) => a == b,
+        }
+    }
+
+    fn record(&mut self, span: Span, t: Type) -> Type {
+        self.expressions.insert(span, t.clone());
+        t
+    }
+
+    // 配列の値は先頭要素へのポインタとして扱う
+    fn decay(&mut self, span: Span, t: Type) -> Type {
+        match self.resolve(&t) {
+            Type::Array(element, _) => {
+                self.decayed.insert(span, t);
+                Type::Pointer(element)
+            },
+            _ => t,
+        }
+    }
+
+    // intとcharは互いに暗黙に変換できる
+    fn convert(&mut self, actual: &Type, expected: &Type, span: Span) -> bool {
+        match (self.resolve(actual), self.resolve(expected)) {
+            (Type::Char, Type::Int) => true,
+            (Type::Int, Type::Char) => {
+                self.narrowed.insert(span);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    // 算術演算ではcharもintとして扱う
+    fn is_integer(&mut self, t: &Type) -> bool {
+        self.resolve(t) == Type::Char || self.unify(t, &Type::Int)
+    }
+
+    fn expect(&mut self, actual: &Type, expected: &Type, span: Span) -> Result<(), (Span, Message)> {
+        if self.unify(actual, expected) || self.convert(actual, expected, span) {
+            Ok(())
+        } else {
+            Err((span, Message::TypeMismatch(
+                self.resolve(expected).name(),
+                self.resolve(actual).name(),
+            )))
+        }
+    }
+
+    fn declare_variable(&mut self, name: &Code<String>, t: Type) -> Result<(), (Span, Message)> {
+        let block = self.variables.last_mut().unwrap();
+        if block.contains_key(&name.value) {
+            return Err((name.span, Message::Redeclared));
+        }
+        block.insert(name.value.clone(), t.clone());
+        self.declarations.insert(name.span, t);
+        Ok(())
+    }
+
+    // ローカル変数が見つからなければグローバル変数を探す
+    fn lookup_variable(&self, name: &Code<String>) -> Option<Type> {
+        self.variables.iter().rev()
+            .find_map(|block| block.get(&name.value))
+            .or_else(|| self.globals.get(&name.value))
+            .cloned()
+    }
+
+    fn is_constant(&self, name: &Code<String>) -> bool {
+        self.variables.iter().all(|block| !block.contains_key(&name.value))
+            && self.constants.contains(&name.value)
+    }
+
+    fn check_statement(&mut self, statement: &Statement) -> Result<(), (Span, Message)> {
+        match statement {
+            Statement::Assignment(asn) => {
+                let variable_type = self.annotated_type(asn.type_annotation())?;
+                match asn.content() {
+                    Some(content) => {
+                        let content_type = self.check_expression(content)?;
+                        self.expect(&content_type, &variable_type, content.span())?;
+                    },
+                    None => if asn.type_annotation().is_none() {
+                        return Err((asn.identifier().span, Message::MissingTypeAnnotation));
+                    },
+                }
+                self.declare_variable(asn.identifier(), variable_type)
+            },
+            Statement::Reassignment(asn) => {
+                let target = asn.target();
+                if let Some(name) = target.identifier() {
+                    // 未定義の変数への代入は報告だけして、右辺の検査は続ける
+                    if self.lookup_variable(name).is_none() {
+                        self.errors.push(Diagnostic::error(code::UNDEFINED_SYMBOL, Some(name.span), Message::AssignToUndefined));
+                        return self.check_expression(asn.content()).map(|_| ());
+                    }
+                    if self.is_constant(name) {
+                        return Err((name.span, Message::AssignToConstant));
+                    }
+                }
+                if !Self::is_lvalue(target) {
+                    return Err((target.span(), Message::NotAssignable));
+                }
+                let variable_type = self.check_unary(target)?;
+                if self.decayed.contains_key(&target.span()) {
+                    return Err((target.span(), Message::AssignToArray));
+                }
+                let content_type = self.check_expression(asn.content())?;
+                self.expect(&content_type, &variable_type, asn.content().span())
+            },
+            Statement::Return(ret) => {
+                let content_type = self.check_expression(ret.content())?;
+                let return_type = self.return_type.clone();
+                self.expect(&content_type, &return_type, ret.content().span())
+            },
+            Statement::Expression(expr) => self.check_expression(expr).map(|_| ()),
+        }
+    }
+
+    fn check_expression(&mut self, expression: &Expression) -> Result<Type, (Span, Message)> {
+        match expression {
+            Expression::PureExpression(expr) => self.check_logical_or(&expr.logical_or),
+            Expression::IfExpression(expr) => self.check_if_expression(expr),
+            Expression::WhileExpression(expr) => self.check_while_expression(expr),
+            Expression::BlockExpression(expr) => self.check_block_expression(expr),
+            // 制御が戻ってこないのでどの型としても扱える
+            Expression::Break(_) => Ok(self.new_variable()),
+            Expression::Continue(_) => Ok(self.new_variable()),
+        }
+    }
+
+    fn check_condition(&mut self, condition: &Expression) -> Result<(), (Span, Message)> {
+        match self.check_expression(condition) {
+            Ok(t) => self.expect(&t, &Type::Bool, condition.span()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn check_if_expression(&mut self, expr: &IfExpression) -> Result<Type, (Span, Message)> {
+        self.check_condition(&expr.condition)?;
+        let then_type = self.check_expression(&expr.then)?;
+        let else_type = self.check_expression(&expr.else_)?;
+        match self.expect(&else_type, &then_type, expr.else_.span()) {
+            Ok(_) => Ok(then_type),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn check_while_expression(&mut self, expr: &WhileExpression) -> Result<Type, (Span, Message)> {
+        self.check_condition(&expr.condition)?;
+        match self.check_expression(&expr.body) {
+            Ok(_) => Ok(Type::Int),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn check_block_expression(&mut self, expr: &BlockExpression) -> Result<Type, (Span, Message)> {
+        self.variables.push(HashMap::new());
+        let result = self.check_block_contents(expr);
+        self.variables.pop();
+        result
+    }
+
+    // 誤りのあった文は報告だけして、続く文の検査を続ける
+    fn check_block_contents(&mut self, expr: &BlockExpression) -> Result<Type, (Span, Message)> {
+        for stmt in &expr.statements {
+            if let Err(e) = self.check_statement(stmt) {
+                self.errors.push(Self::type_error(e));
+                // 宣言できなかった変数も定義されたものとして扱い、参照先で重ねて報告しない
+                if let Statement::Assignment(asn) = stmt {
+                    if !self.variables.last().unwrap().contains_key(&asn.identifier().value) {
+                        let t = self.new_variable();
+                        self.declare_variable(asn.identifier(), t)?;
+                    }
+                }
+            }
+        }
+        match &expr.outcome {
+            Some(outcome) => match self.check_expression(outcome) {
+                Ok(t) => Ok(t),
+                Err(e) => {
+                    self.errors.push(Self::type_error(e));
+                    Ok(self.new_variable())
+                },
+            },
+            None => Ok(Type::Int),
+        }
+    }
+
+    fn check_logical_or(&mut self, logical_or: &LogicalOr) -> Result<Type, (Span, Message)> {
+        let head = self.check_logical_and(logical_or.head())?;
+        if logical_or.tail().next().is_none() {
+            return Ok(self.record(logical_or.span(), head));
+        }
+        self.expect(&head, &Type::Bool, logical_or.head().span())?;
+        for (_, logical_and) in logical_or.tail() {
+            let right = self.check_logical_and(logical_and)?;
+            self.expect(&right, &Type::Bool, logical_and.span())?;
+        }
+        Ok(self.record(logical_or.span(), Type::Bool))
+    }
+
+    fn check_logical_and(&mut self, logical_and: &LogicalAnd) -> Result<Type, (Span, Message)> {
+        let head = self.check_logical_not(logical_and.head())?;
+        if logical_and.tail().next().is_none() {
+            return Ok(self.record(logical_and.span(), head));
+        }
+        self.expect(&head, &Type::Bool, logical_and.head().span())?;
+        for (_, logical_not) in logical_and.tail() {
+            let right = self.check_logical_not(logical_not)?;
+            self.expect(&right, &Type::Bool, logical_not.span())?;
+        }
+        Ok(self.record(logical_and.span(), Type::Bool))
+    }
+
+    fn check_logical_not(&mut self, logical_not: &LogicalNot) -> Result<Type, (Span, Message)> {
+        match logical_not {
+            LogicalNot::Not(operand, span) => {
+                let t = self.check_logical_not(operand)?;
+                match self.expect(&t, &Type::Bool, operand.span()) {
+                    Ok(_) => Ok(self.record(*span, Type::Bool)),
+                    Err(e) => Err(e),
+                }
+            },
+            LogicalNot::BitOr(bit_or) => self.check_bit_or(bit_or),
+        }
+    }
+
+    fn check_bit_or(&mut self, bit_or: &BitOr) -> Result<Type, (Span, Message)> {
+        let head = self.check_bit_xor(bit_or.head())?;
+        if bit_or.tail().next().is_none() {
+            return Ok(self.record(bit_or.span(), head));
+        }
+        self.expect(&head, &Type::Int, bit_or.head().span())?;
+        for (_, bit_xor) in bit_or.tail() {
+            let right = self.check_bit_xor(bit_xor)?;
+            self.expect(&right, &Type::Int, bit_xor.span())?;
+        }
+        Ok(self.record(bit_or.span(), Type::Int))
+    }
+
+    fn check_bit_xor(&mut self, bit_xor: &BitXor) -> Result<Type, (Span, Message)> {
+        let head = self.check_bit_and(bit_xor.head())?;
+        if bit_xor.tail().next().is_none() {
+            return Ok(self.record(bit_xor.span(), head));
+        }
+        self.expect(&head, &Type::Int, bit_xor.head().span())?;
+        for (_, bit_and) in bit_xor.tail() {
+            let right = self.check_bit_and(bit_and)?;
+            self.expect(&right, &Type::Int, bit_and.span())?;
+        }
+        Ok(self.record(bit_xor.span(), Type::Int))
+    }
+
+    fn check_bit_and(&mut self, bit_and: &BitAnd) -> Result<Type, (Span, Message)> {
+        let head = self.check_equality(bit_and.head())?;
+        if bit_and.tail().next().is_none() {
+            return Ok(self.record(bit_and.span(), head));
+        }
+        self.expect(&head, &Type::Int, bit_and.head().span())?;
+        for (_, equality) in bit_and.tail() {
+            let right = self.check_equality(equality)?;
+            self.expect(&right, &Type::Int, equality.span())?;
+        }
+        Ok(self.record(bit_and.span(), Type::Int))
+    }
+
+    fn check_equality(&mut self, equality: &Equality) -> Result<Type, (Span, Message)> {
+        let mut left = self.check_relational(equality.head())?;
+        let mut left_span = equality.head().span();
+        for (operator, relational) in equality.tail() {
+            let right = self.check_relational(relational)?;
+            let comparable = self.unify(&left, &right)
+                || (self.is_integer(&left) && self.is_integer(&right));
+            if comparable && self.is_struct(&left) {
+                return Err((left_span.plus(&relational.span()), Message::StructNotComparable));
+            }
+            if !comparable {
+                return Err((left_span.plus(&relational.span()), Message::NotComparable(
+                    self.resolve(&left).name(),
+                    self.resolve(&right).name(),
+                )));
+            }
+            left = Type::Bool;
+            left_span = left_span.plus(&operator.span).plus(&relational.span());
+        }
+        Ok(self.record(equality.span(), left))
+    }
+
+    fn check_relational(&mut self, relational: &Relational) -> Result<Type, (Span, Message)> {
+        let head = self.check_shift(relational.head())?;
+        if relational.tail().next().is_none() {
+            return Ok(self.record(relational.span(), head));
+        }
+        let mut left = head;
+        let mut left_span = relational.head().span();
+        for (operator, shift) in relational.tail() {
+            self.expect(&left, &Type::Int, left_span)?;
+            let right = self.check_shift(shift)?;
+            self.expect(&right, &Type::Int, shift.span())?;
+            left = Type::Bool;
+            left_span = left_span.plus(&operator.span).plus(&shift.span());
+        }
+        Ok(self.record(relational.span(), left))
+    }
+
+    fn check_shift(&mut self, shift: &Shift) -> Result<Type, (Span, Message)> {
+        let head = self.check_add(shift.head())?;
+        if shift.tail().next().is_none() {
+            return Ok(self.record(shift.span(), head));
+        }
+        self.expect(&head, &Type::Int, shift.head().span())?;
+        for (_, add) in shift.tail() {
+            let right = self.check_add(add)?;
+            self.expect(&right, &Type::Int, add.span())?;
+        }
+        Ok(self.record(shift.span(), Type::Int))
+    }
+
+    // ポインタ ± int、int + ポインタ、ポインタ - ポインタ も許す
+    fn add_result(&mut self, operator: Operator, left: &Type, right: &Type) -> Option<Type> {
+        match (self.resolve(left), self.resolve(right), operator) {
+            (Type::Pointer(a), Type::Pointer(b), Operator::Sub) => {
+                if self.unify(&a, &b) { Some(Type::Int) } else { None }
+            },
+            (Type::Pointer(a), r, _) => {
+                if self.is_integer(&r) { Some(Type::Pointer(a)) } else { None }
+            },
+            (l, Type::Pointer(b), Operator::Add) => {
+                if self.is_integer(&l) { Some(Type::Pointer(b)) } else { None }
+            },
+            (l, r, _) => {
+                if self.is_integer(&l) && self.is_integer(&r) { Some(Type::Int) } else { None }
+            },
+        }
+    }
+
+    fn check_add(&mut self, add: &Add) -> Result<Type, (Span, Message)> {
+        let mut left = self.check_multiply(add.head())?;
+        let mut left_span = add.head().span();
+        for (operator, multiply) in add.tail() {
+            let right = self.check_multiply(multiply)?;
+            left_span = left_span.plus(&operator.span).plus(&multiply.span());
+            left = match self.add_result(operator.value, &left, &right) {
+                Some(t) => t,
+                None if operator.value == Operator::Add => return Err((left_span, Message::CannotAdd(
+                    self.resolve(&left).name(),
+                    self.resolve(&right).name(),
+                ))),
+                None => return Err((left_span, Message::CannotSubtract(
+                    self.resolve(&left).name(),
+                    self.resolve(&right).name(),
+                ))),
+            };
+        }
+        Ok(self.record(add.span(), left))
+    }
+
+    fn check_multiply(&mut self, multiply: &Multiply) -> Result<Type, (Span, Message)> {
+        let head = self.check_unary(multiply.head())?;
+        if multiply.tail().next().is_none() {
+            return Ok(self.record(multiply.span(), head));
+        }
+        self.expect(&head, &Type::Int, multiply.head().span())?;
+        for (_, unary) in multiply.tail() {
+            let right = self.check_unary(unary)?;
+            self.expect(&right, &Type::Int, unary.span())?;
+        }
+        Ok(self.record(multiply.span(), Type::Int))
+    }
+
+    fn is_lvalue(unary: &Unary) -> bool {
+        match unary {
+            Unary::Dereference(_, _) => true,
+            _ => matches!(
+                unary.primary(),
+                Some(Primary::Identifier(_)) | Some(Primary::Index(_, _, _)) | Some(Primary::Field(_, _))
+            ),
+        }
+    }
+
+    fn check_unary(&mut self, unary: &Unary) -> Result<Type, (Span, Message)> {
+        let t = match unary {
+            Unary::Positive(primary, span) | Unary::Negative(primary, span) => {
+                let t = self.check_primary(primary)?;
+                // 符号のない項はそのままの型
+                if *span == primary.span() {
+                    t
+                } else {
+                    match self.expect(&t, &Type::Int, primary.span()) {
+                        Ok(_) => Type::Int,
+                        Err(e) => return Err(e),
+                    }
+                }
+            },
+            Unary::Address(operand, _) => {
+                if !Self::is_lvalue(operand) {
+                    return Err((operand.span(), Message::NotAddressable));
+                }
+                if let Some(name) = operand.identifier() {
+                    if self.is_constant(name) {
+                        return Err((operand.span(), Message::AddressOfConstant));
+                    }
+                }
+                match self.check_unary(operand) {
+                    // 配列のアドレスは配列へのポインタ
+                    Ok(t) => match self.decayed.get(&operand.span()) {
+                        Some(array) => Type::Pointer(Box::new(array.clone())),
+                        None => Type::Pointer(Box::new(t)),
+                    },
+                    Err(e) => return Err(e),
+                }
+            },
+            Unary::Dereference(operand, _) => {
+                let t = self.check_unary(operand)?;
+                let pointee = self.new_variable();
+                if !self.unify(&t, &Type::Pointer(Box::new(pointee.clone()))) {
+                    return Err((operand.span(), Message::ExpectedPointer(
+                        self.resolve(&t).name(),
+                    )));
+                }
+                self.decay(unary.span(), pointee)
+            },
+            Unary::BitNot(operand, _) => {
+                let t = self.check_unary(operand)?;
+                match self.expect(&t, &Type::Int, operand.span()) {
+                    Ok(_) => Type::Int,
+                    Err(e) => return Err(e),
+                }
+            },
+        };
+        Ok(self.record(unary.span(), t))
+    }
+
+    fn check_primary(&mut self, primary: &Primary) -> Result<Type, (Span, Message)> {
+        let t = match primary {
+            Primary::Integer(_) => Type::Int,
+            Primary::Boolean(_) => Type::Bool,
+            Primary::Character(_) => Type::Char,
+            Primary::String(_) => Type::Pointer(Box::new(Type::Char)),
+            Primary::Identifier(name) => match self.lookup_variable(name) {
+                Some(t) => self.decay(name.span, t),
+                // 未定義の変数は報告だけして、検査は続ける
+                None => {
+                    self.errors.push(Diagnostic::error(code::UNDEFINED_SYMBOL, Some(name.span), Message::UndefinedSymbol));
+                    self.new_variable()
+                },
+            },
+            Primary::Expression(expression) => self.check_expression(expression)?,
+            Primary::FnCall(fn_call) => self.check_fn_call(fn_call)?,
+            Primary::Index(base, index, span) => self.check_index(base, index, *span)?,
+            Primary::Field(base, field) => self.check_field(base, field, primary.span())?,
+            Primary::StructLiteral(literal) => self.check_struct_literal(literal)?,
+        };
+        Ok(self.record(primary.span(), t))
+    }
+
+    // 構造体へのポインタは自動的に参照をはずす
+    fn check_field(&mut self, base: &Primary, field: &Code<String>, span: Span) -> Result<Type, (Span, Message)> {
+        let base_type = self.check_primary(base)?;
+        let name = match self.resolve(&base_type) {
+            Type::Struct(name) => name,
+            Type::Pointer(pointee) => match self.resolve(&pointee) {
+                Type::Struct(name) => name,
+                t => return Err((base.span(), Message::ExpectedStruct(
+                    format!("*{}", t.name()),
+                ))),
+            },
+            t => return Err((base.span(), Message::ExpectedStruct(
+                t.name(),
+            ))),
+        };
+        let field_type = match self.layouts.get(&name).and_then(|layout| layout.field(&field.value)) {
+            Some(field_layout) => field_layout.field_type.clone(),
+            None => return Err((field.span, Message::NoSuchField(name, field.value.clone()))),
+        };
+        Ok(self.decay(span, field_type))
+    }
+
+    fn check_struct_literal(&mut self, literal: &StructLiteral) -> Result<Type, (Span, Message)> {
+        let field_types: Vec<(String, Type)> = match self.layouts.get(&literal.name.value) {
+            Some(layout) => layout.fields.iter()
+                .map(|field| (field.name.clone(), field.field_type.clone()))
+                .collect(),
+            None => return Err((literal.name.span, Message::UndefinedStruct)),
+        };
+        for (i, (field, value)) in literal.fields.iter().enumerate() {
+            if literal.fields[..i].iter().any(|(f, _)| f.value == field.value) {
+                return Err((field.span, Message::DuplicateFieldValue));
+            }
+            let expected = match field_types.iter().find(|(name, _)| *name == field.value) {
+                Some((_, t)) => t.clone(),
+                None => return Err((field.span, Message::NoSuchField(literal.name.value.clone(), field.value.clone()))),
+            };
+            let actual = self.check_expression(value)?;
+            self.expect(&actual, &expected, value.span())?;
+        }
+        for (name, _) in &field_types {
+            if !literal.fields.iter().any(|(f, _)| f.value == *name) {
+                return Err((literal.span(), Message::MissingFieldValue(name.clone())));
+            }
+        }
+        Ok(Type::Struct(literal.name.value.clone()))
+    }
+
+    fn check_index(&mut self, base: &Primary, index: &Expression, span: Span) -> Result<Type, (Span, Message)> {
+        let base_type = self.check_primary(base)?;
+        let element = self.new_variable();
+        if !self.unify(&base_type, &Type::Pointer(Box::new(element.clone()))) {
+            return Err((base.span(), Message::ExpectedArrayOrPointer(
+                self.resolve(&base_type).name(),
+            )));
+        }
+        match self.check_expression(index) {
+            Ok(t) => self.expect(&t, &Type::Int, index.span())?,
+            Err(e) => return Err(e),
+        }
+        Ok(self.decay(span, element))
+    }
+
+    fn check_fn_call(&mut self, fn_call: &FnCall) -> Result<Type, (Span, Message)> {
+        let mut arg_types = Vec::new();
+        for arg in &fn_call.args {
+            match self.check_expression(arg) {
+                Ok(t) if self.is_struct(&t) => {
+                    self.report_struct_by_value(arg.span(), Message::StructByValueArgument);
+                    arg_types.push(t);
+                },
+                Ok(t) => arg_types.push(t),
+                Err(e) => return Err(e),
+            }
+        }
+        let (args, return_type) = match self.functions.get(&fn_call.func.value) {
+            Some(signature) => (signature.args.clone(), signature.return_type.clone()),
+            // 定義されていない関数は外部の関数としてintを返すものとみなす
+            None => return Ok(Type::Int),
+        };
+        if args.len() != arg_types.len() {
+            return Err((fn_call.span(), Message::WrongArgumentCount(
+                args.len(),
+                arg_types.len(),
+            )));
+        }
+        for ((expected, actual), arg) in args.iter().zip(arg_types).zip(&fn_call.args) {
+            self.expect(&actual, expected, arg.span())?;
+        }
+        Ok(return_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use parse::TokenReader;
+    use token::tokenize;
+
+    fn check(src: &str) -> Result<TypeTable, Vec<Diagnostic>> {
+        let tokens = tokenize(&src.to_string()).unwrap();
+        let mut token_reader = TokenReader::new(&tokens);
+        let root = Root::parse(&mut token_reader).unwrap();
+        TypeChecker::check(&root)
+    }
+
+    #[test]
+    fn test_check_annotated() {
+        assert!(check("func main() {
+            let x: int := 1
+            let b: bool := x < 2
+            if b then x else 0
+        }").is_ok());
+        assert!(check("func main() {
+            let x: bool := 1
+            0
+        }").is_err());
+    }
+
+    #[test]
+    fn test_check_condition() {
+        assert!(check("func main() if 1 then 2 else 3").is_err());
+        assert!(check("func main() while 1 do 0").is_err());
+        assert!(check("func main() if true then 2 else false").is_err());
+    }
+
+    #[test]
+    fn test_check_operators() {
+        assert!(check("func main() 1 + (2 < 3)").is_err());
+        assert!(check("func main() 1 == true").is_err());
+        assert!(check("func main() true == (1 < 2)").is_ok());
+        assert!(check("func main() 1 < 2 < 3").is_err());
+    }
+
+    #[test]
+    fn test_check_inference() {
+        assert!(check("func main() {
+            let x := 1
+            let y := f(x)
+            if y then 1 else 0
+        }
+
+        func f(a): bool a == 1").is_ok());
+        assert!(check("func main() g(true)
+
+        func g(a) a + 1").is_err());
+        assert!(check("func main() fib(10)
+
+        func fib(n) if n < 2 then n else fib(n - 1) + fib(n - 2)").is_ok());
+    }
+
+    #[test]
+    fn test_check_error_span() {
+        let errors = check("func main() {
+            let x := 1
+            x := false
+            x
+        }").err().unwrap();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, code::TYPE);
+        assert_eq!(errors[0].primary, Some(Span::new(2, 17, 5)));
+    }
+
+    #[test]
+    fn test_check_multiple_errors() {
+        let errors = check("func main() {
+            let x := a + 1
+            b
+        }
+        func f() x
+        func g() 1 + true").err().unwrap();
+
+        let spans: Vec<Option<Span>> = errors.iter().map(|diagnostic| diagnostic.primary).collect();
+        assert_eq!(spans, vec![
+            Some(Span::new(1, 21, 1)),
+            Some(Span::new(2, 12, 1)),
+            Some(Span::new(4, 17, 1)),
+            Some(Span::new(5, 17, 8)),
+        ]);
+        assert_eq!(errors[0].code, code::UNDEFINED_SYMBOL);
+    }
+
+    #[test]
+    fn test_check_assign_to_undefined() {
+        let errors = check("func main() {
+            x := 1
+            y := a
+            z
+        }").err().unwrap();
+
+        let spans: Vec<Option<Span>> = errors.iter().map(|diagnostic| diagnostic.primary).collect();
+        assert_eq!(spans, vec![
+            Some(Span::new(1, 12, 1)),
+            Some(Span::new(2, 12, 1)),
+            Some(Span::new(2, 17, 1)),
+            Some(Span::new(3, 12, 1)),
+        ]);
+        assert_eq!(errors[0].message, Message::AssignToUndefined);
+        // 未定義の変数を読むときと同じ種類の誤りとして扱う
+        assert!(errors.iter().all(|diagnostic| diagnostic.code == code::UNDEFINED_SYMBOL));
+    }
+
+    #[test]
+    fn test_check_after_statement_error() {
+        let errors = check("func main() {
+            let x := 1 + true
+            x := 2
+            zz
+        }").err().unwrap();
+
+        // 型の誤りのあとの文も検査し、誤りのあった変数は重ねて報告しない
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].code, code::TYPE);
+        assert_eq!(errors[1].code, code::UNDEFINED_SYMBOL);
+        assert_eq!(errors[1].primary, Some(Span::new(3, 12, 2)));
+    }
+
+    #[test]
+    fn test_check_duplicate() {
+        let errors = check("func f() 1
+        func f() 2
+        func main() f()").err().unwrap();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, code::DUPLICATE_DEFINITION);
+        assert_eq!(errors[0].primary, Some(Span::new(1, 13, 1)));
+        assert_eq!(errors[0].secondary, vec![(Span::new(0, 5, 1), Message::FirstDefinition)]);
+    }
+
+    #[test]
+    fn test_check_pointer() {
+        assert!(check("func main() {
+            let x := 1
+            let p := &x
+            let q: **int := &p
+            *p := **q + 1
+            *p
+        }").is_ok());
+        assert!(check("func main() *1").is_err());
+        assert!(check("func main() &1").is_err());
+        assert!(check("func main() {
+            let b := true
+            let p: *int := &b
+            0
+        }").is_err());
+    }
+
+    #[test]
+    fn test_check_pointer_arithmetic() {
+        let types = check("func main() {
+            let x := 1
+            let p := &x
+            let q := p + 1
+            q - p
+        }").unwrap();
+
+        assert_eq!(types.type_of(&Span::new(3, 21, 5)), Type::Pointer(Box::new(Type::Int)));
+        assert_eq!(types.type_of(&Span::new(4, 12, 5)), Type::Int);
+
+        assert!(check("func main() {
+            let x := 1
+            let p := &x
+            1 - p
+        }").is_err());
+    }
+
+    #[test]
+    fn test_check_array() {
+        let types = check("func main() {
+            let a: [int; 3]
+            a[0] := 1
+            let p := a
+            let m: [[int; 2]; 3]
+            m[1][0] := *p
+            sum(a)
+        }
+
+        func sum(xs: [int; 3]) xs[0] + xs[1] + xs[2]").unwrap();
+
+        assert_eq!(types.variable_type(&Span::new(1, 16, 1)), Type::Array(Box::new(Type::Int), 3));
+        assert_eq!(types.type_of(&Span::new(3, 21, 1)), Type::Pointer(Box::new(Type::Int)));
+        assert!(types.decays(&Span::new(3, 21, 1)));
+        assert!(types.decays(&Span::new(5, 12, 4)));
+        assert!(!types.decays(&Span::new(5, 12, 7)));
+
+        assert!(check("func main() {
+            let a: [int; 3]
+            a[true]
+        }").is_err());
+        assert!(check("func main() {
+            let a: [int; 3]
+            let b: [int; 3]
+            a := b
+            0
+        }").is_err());
+        assert!(check("func main() {
+            let a: [int; 0]
+            0
+        }").is_err());
+        assert!(check("func main() {
+            let x
+            0
+        }").is_err());
+        assert!(check("func main() 1[0]").is_err());
+    }
+
+    #[test]
+    fn test_check_char() {
+        let types = check("func main() {
+            let s := \"hello\"
+            let c: char := s[1] - 'a' + 'A'
+            let n: int := c
+            c == 'E'
+        }").unwrap();
+
+        assert_eq!(types.type_of(&Span::new(1, 21, 7)), Type::Pointer(Box::new(Type::Char)));
+        assert!(types.narrows(&Span::new(2, 27, 16)));
+        assert!(!types.narrows(&Span::new(3, 26, 1)));
+
+        assert!(check("func main() {
+            let c: char := true
+            0
+        }").is_err());
+        assert!(check("func main() {
+            let p: *int := \"abc\"
+            0
+        }").is_err());
+    }
+
+    #[test]
+    fn test_check_global() {
+        let types = check("const N := 3
+        let total: int
+        let flags: [bool; 3]
+
+        func main() {
+            total := total + N
+            flags[0] := true
+            let N := 1
+            N := 2
+            total
+        }").unwrap();
+
+        assert_eq!(types.variable_type(&Span::new(2, 12, 5)), Type::Array(Box::new(Type::Bool), 3));
+
+        assert!(check("const N := 3
+        func main() {
+            N := 4
+            N
+        }").is_err());
+        assert!(check("const N := 3
+        func main() &N").is_err());
+        assert!(check("let a := b
+        let b := 1
+        func main() a").is_err());
+        assert!(check("let a := 1
+        let a := 2
+        func main() a").is_err());
+    }
+
+    #[test]
+    fn test_check_logical() {
+        let types = check("func main() {
+            let ok := !(1 > 2) && 3 == 3 || false
+            if ok then 1 else 0
+        }").unwrap();
+
+        assert_eq!(types.variable_type(&Span::new(1, 16, 2)), Type::Bool);
+
+        assert!(check("func main() 1 && true").is_err());
+        assert!(check("func main() true || 0").is_err());
+        assert!(check("func main() !1").is_err());
+    }
+
+    #[test]
+    fn test_check_bitwise() {
+        let types = check("func main() {
+            let c: char := 'a'
+            let mask := 1 << 3 | c & ~7 ^ 5 % 2 >> 1
+            if mask < 0 || (mask & 1) == 0 then 1 else 0
+        }").unwrap();
+
+        assert_eq!(types.variable_type(&Span::new(2, 16, 4)), Type::Int);
+
+        assert!(check("func main() 1 | true").is_err());
+        assert!(check("func main() ~false").is_err());
+        assert!(check("func main() {
+            let x := 1
+            (&x) << 1
+        }").is_err());
+    }
+
+    #[test]
+    fn test_check_struct() {
+        let types = check("struct Point { x: int, y: int }
+        struct Tagged { tag: char, point: Point }
+
+        func main() {
+            let t := Tagged { tag: 'a', point: Point { x: 1, y: 2 } }
+            let p := &t.point
+            p.x := 3
+            t.point.y + p.x
+        }").unwrap();
+
+        assert_eq!(types.variable_type(&Span::new(4, 16, 1)), Type::Struct("Tagged".to_string()));
+        assert_eq!(types.variable_type(&Span::new(5, 16, 1)), Type::Pointer(Box::new(Type::Struct("Point".to_string()))));
+        assert_eq!(types.size_of(&Type::Struct("Tagged".to_string())), 24);
+        assert_eq!(types.struct_layout("Tagged").unwrap().field("point").unwrap().offset, 8);
+
+        assert!(check("struct P { x: int }
+        func main() P { x: 1 }.y").is_err());
+        assert!(check("struct P { x: int, y: int }
+        func main() P { x: 1 }.x").is_err());
+        assert!(check("struct P { x: int }
+        func main() P { x: 1, x: 2 }.x").is_err());
+        assert!(check("struct P { x: int }
+        func main() f(P { x: 1 })
+        func f(p: P) p.x").is_err());
+        assert!(check("struct P { next: P }
+        func main() 0").is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use super::types::Type;
+
+pub struct FieldLayout {
+    pub name: String,
+    pub offset: i64,
+    pub field_type: Type,
+}
+
+pub struct StructLayout {
+    pub fields: Vec<FieldLayout>,
+    pub size: i64,
+    pub align: i64,
+}
+
+impl StructLayout {
+    pub fn field(&self, name: &str) -> Option<&FieldLayout> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+// 型の大きさと境界。構造体は名前で引く
+pub struct Layouts {
+    structs: HashMap<String, StructLayout>,
+}
+
+impl Layouts {
+    pub fn new() -> Self {
+        Self {
+            structs: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&StructLayout> {
+        self.structs.get(name)
+    }
+
+    pub fn size_of(&self, t: &Type) -> i64 {
+        match t {
+            Type::Char => 1,
+            Type::Array(element, length) => self.size_of(element) * length,
+            Type::Struct(name) => self.structs.get(name).map(|layout| layout.size).unwrap_or(8),
+            _ => 8,
+        }
+    }
+
+    pub fn align_of(&self, t: &Type) -> i64 {
+        match t {
+            Type::Char => 1,
+            Type::Array(element, _) => self.align_of(element),
+            Type::Struct(name) => self.structs.get(name).map(|layout| layout.align).unwrap_or(8),
+            _ => 8,
+        }
+    }
+
+    // フィールドを宣言順に、それぞれの境界に揃えて並べる
+    pub fn define(&mut self, name: &str, fields: Vec<(String, Type)>) {
+        let mut offset = 0;
+        let mut align = 1;
+        let mut field_layouts = Vec::new();
+        for (field_name, field_type) in fields {
+            let field_align = self.align_of(&field_type);
+            offset = align_to(offset, field_align);
+            align = align.max(field_align);
+            field_layouts.push(FieldLayout { name: field_name, offset, field_type: field_type.clone() });
+            offset += self.size_of(&field_type);
+        }
+        let layout = StructLayout {
+            fields: field_layouts,
+            size: align_to(offset, align),
+            align,
+        };
+        self.structs.insert(name.to_string(), layout);
+    }
+}
+
+pub fn align_to(n: i64, align: i64) -> i64 {
+    (n + align - 1) / align * align
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_define() {
+        let mut layouts = Layouts::new();
+        layouts.define("Pair", vec![
+            ("tag".to_string(), Type::Char),
+            ("value".to_string(), Type::Int),
+            ("name".to_string(), Type::Array(Box::new(Type::Char), 3)),
+        ]);
+        layouts.define("Wrapper", vec![
+            ("flag".to_string(), Type::Char),
+            ("pair".to_string(), Type::Struct("Pair".to_string())),
+        ]);
+
+        let pair = layouts.get("Pair").unwrap();
+        assert_eq!(pair.field("tag").unwrap().offset, 0);
+        assert_eq!(pair.field("value").unwrap().offset, 8);
+        assert_eq!(pair.field("name").unwrap().offset, 16);
+        assert_eq!(pair.size, 24);
+        assert_eq!(pair.align, 8);
+
+        let wrapper = layouts.get("Wrapper").unwrap();
+        assert_eq!(wrapper.field("pair").unwrap().offset, 8);
+        assert_eq!(layouts.size_of(&Type::Struct("Wrapper".to_string())), 32);
+        assert_eq!(layouts.size_of(&Type::Array(Box::new(Type::Struct("Pair".to_string())), 2)), 48);
+
+        layouts.define("Chars", vec![
+            ("a".to_string(), Type::Char),
+            ("b".to_string(), Type::Char),
+        ]);
+        assert_eq!(layouts.size_of(&Type::Struct("Chars".to_string())), 2);
+        assert_eq!(layouts.align_of(&Type::Struct("Chars".to_string())), 1);
+    }
+}
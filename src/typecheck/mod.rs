@@ -0,0 +1,8 @@
+mod types;
+mod layout;
+mod checker;
+
+pub use self::types::Type;
+pub use self::types::TypeTable;
+pub use self::checker::TypeChecker;
+pub use self::layout::align_to;
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use sourcecode::Span;
+
+use super::layout::Layouts;
+use super::layout::StructLayout;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Type {
+    Int,
+    Bool,
+    Char,
+    Pointer(Box<Type>),
+    Array(Box<Type>, i64),
+    Struct(String),
+    // 推論中の型。TypeCheckerが解決する
+    Variable(usize),
+}
+
+impl Type {
+    pub fn name(&self) -> String {
+        match self {
+            Type::Int => "int".to_string(),
+            Type::Bool => "bool".to_string(),
+            Type::Char => "char".to_string(),
+            Type::Pointer(pointee) => format!("*{}", pointee.name()),
+            Type::Array(element, length) => format!("[{}; {}]", element.name(), length),
+            Type::Struct(name) => name.clone(),
+            Type::Variable(_) => "?".to_string(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Type> {
+        match name {
+            "int" => Some(Type::Int),
+            "bool" => Some(Type::Bool),
+            "char" => Some(Type::Char),
+            _ => None,
+        }
+    }
+
+    pub fn pointee(&self) -> Option<&Type> {
+        match self {
+            Type::Pointer(pointee) => Some(pointee),
+            _ => None,
+        }
+    }
+}
+
+// 型検査の結果。式の型を式の範囲で、変数の型を宣言した識別子の範囲で引く
+pub struct TypeTable {
+    expressions: HashMap<Span, Type>,
+    variables: HashMap<Span, Type>,
+    decayed: HashSet<Span>,
+    narrowed: HashSet<Span>,
+    layouts: Layouts,
+}
+
+impl TypeTable {
+    pub fn new(
+        expressions: HashMap<Span, Type>,
+        variables: HashMap<Span, Type>,
+        decayed: HashSet<Span>,
+        narrowed: HashSet<Span>,
+        layouts: Layouts,
+    ) -> TypeTable {
+        TypeTable { expressions, variables, decayed, narrowed, layouts }
+    }
+
+    pub fn size_of(&self, t: &Type) -> i64 {
+        self.layouts.size_of(t)
+    }
+
+    pub fn align_of(&self, t: &Type) -> i64 {
+        self.layouts.align_of(t)
+    }
+
+    pub fn struct_layout(&self, name: &str) -> Option<&StructLayout> {
+        self.layouts.get(name)
+    }
+
+    pub fn type_of(&self, span: &Span) -> Type {
+        self.expressions.get(span).cloned().unwrap_or(Type::Int)
+    }
+
+    pub fn variable_type(&self, span: &Span) -> Type {
+        self.variables.get(span).cloned().unwrap_or(Type::Int)
+    }
+
+    // 配列がポインタに読み替えられた式。値ではなくアドレスを積む
+    pub fn decays(&self, span: &Span) -> bool {
+        self.decayed.contains(span)
+    }
+
+    // intからcharへ暗黙に変換される式。下位1バイトに切り詰める
+    pub fn narrows(&self, span: &Span) -> bool {
+        self.narrowed.contains(span)
+    }
+}
@@ -4,15 +4,67 @@ try() {
   expected="$1"
   input="$2"
 
-  ./main "$input" > tmp.s
-  gcc -o tmp tmp.s
-  ./tmp
-  actual="$?"
+  echo "$input" > tmp.src
+  # 最適化しても結果が変わらないことを確かめる
+  for flags in "" "-O" "-O2"; do
+    ./main $flags -o tmp.s tmp.src || exit 1
+    gcc -o tmp tmp.s
+    ./tmp
+    actual="$?"
+
+    if [ "$actual" != "$expected" ]; then
+      echo "$input ($flags) => $expected expected, but got $actual"
+      exit 1
+    fi
+  done
+  echo "$input => $actual"
+}
+
+output() {
+  expected="$1"
+  input="$2"
+
+  echo "$input" > tmp.src
+  for flags in "" "-O" "-O2"; do
+    ./main $flags -o tmp.s tmp.src || exit 1
+    gcc -o tmp tmp.s
+    actual="$(./tmp)"
+
+    if [ "$actual" != "$expected" ]; then
+      echo "$input ($flags) => \"$expected\" expected, but got \"$actual\""
+      exit 1
+    fi
+  done
+  echo "$input => $actual"
+}
+
+error() {
+  input="$1"
+
+  echo "$input" > tmp.src
+  if ./main -o tmp.s tmp.src 2> /dev/null; then
+    echo "$input => error expected, but compiled"
+    exit 1
+  else
+    echo "$input => error"
+  fi
+}
+
+errors() {
+  expected="$1"
+  input="$2"
+
+  echo "$input" > tmp.src
+  if ./main --error-format=json -o tmp.s tmp.src 2> tmp.err; then
+    echo "$input => $expected errors expected, but compiled"
+    exit 1
+  fi
+  actual="$(grep -c '"severity":"error"' tmp.err)"
 
   if [ "$actual" = "$expected" ]; then
-    echo "$input => $actual"
+    echo "$input => $actual errors"
   else
-    echo "$input => $expected expected, but got $actual"
+    echo "$input => $expected errors expected, but got $actual"
     exit 1
   fi
 }
@@ -78,5 +130,673 @@ func fib(n) {
   else if n == 1 then { 1 }
   else { fib(n-1) + fib(n-2) }
 }"
+try 6 "func main() {
+  let x := 1
+  x := x + 2
+  x := x * 2
+  x
+}"
+try 3 "func main() {
+  let x := 1
+  let y := {
+    let x := 10
+    x := x + 1
+    x
+  }
+  x := x + 2
+  if y == 11 then x else 0
+}"
+try 55 "func main() {
+  let i := 0
+  let sum := 0
+  while i < 10 do {
+    i := i + 1
+    sum := sum + i
+  }
+  sum
+}"
+try 25 "func main() {
+  let i := 0
+  let sum := 0
+  while true do {
+    i := i + 1
+    if i > 9 then break else 0
+    if i - i / 2 * 2 == 0 then continue else 0
+    let odd := i
+    sum := sum + odd
+  }
+  sum
+}"
+try 12 "func main() {
+  let i := 0
+  let count := 0
+  while i < 3 do {
+    let j := 0
+    while true do {
+      j := j + 1
+      if j > 4 then break else 0
+      count := count + 1
+    }
+    i := i + 1
+  }
+  count
+}"
+try 9 "func main() {
+  let i := 0
+  while i < 10 do {
+    if i == 9 then 1 + (break) else 0
+    i := i + 1
+  }
+  i
+}"
+try 233 "func main() fib(13)
+
+func fib(n) {
+  let a := 0
+  let b := 1
+  let i := 0
+  while i < n do {
+    let c := a + b
+    a := b
+    b := c
+    i := i + 1
+  }
+  if a > 0 then a else 0
+}"
+try 7 "func main() {
+  return 7
+  3
+}"
+try 4 "func main() f(3) + f(10)
+
+func f(x) {
+  let y := if x > 5 then {
+    if x > 8 then {
+      return 3
+    } else 0
+  } else {
+    let z := x - 2
+    return z
+  }
+  100
+}"
+try 10 "func main() {
+  let i := 0
+  while true do {
+    i := i + 1
+    if i == 10 then {
+      return i
+    } else 0
+  }
+  0
+}"
+try 6 "func main() 1 + g(2) * 2 + 1
+
+func g(n) {
+  let x := n * 10 + ({
+    return n
+  })
+  x
+}"
+try 81 "func main() sum(1, 2, 3, 4, 5, 6, 7, 8, 9, 10)
+
+func sum(a, b, c, d, e, f, g, h, i, j) {
+  a + b * 2 + c + d + e + f + g * 3 + h * 4 + i * 5 + j * 6 - 100
+}"
+try 37 "func main() {
+  let x := 1
+  let y := weighted(x, 2, 3, 4, 5, 6, 7) + weighted(0, 0, 0, 0, 0, 0, 1)
+  y
+}
+
+func weighted(a, b, c, d, e, f, g) {
+  a + b + c + d + e + f + g * 2
+}"
+try 12 "func main(): int {
+  let flag: bool := isbig(30)
+  let n: int := if flag then 10 else 20
+  n + twice(1)
+}
+
+func isbig(x: int): bool x > 20
+
+func twice(x) x * 2"
+try 1 "func main() {
+  let b := true
+  if b == (1 < 2) then 1 else 0
+}"
+error "func main() if 1 then 2 else 3"
+error "func main() 1 + true"
+error "func main() {
+  let x: int := 1 < 2
+  x
+}"
+error "func main() f(1)
+
+func f(b: bool): int if b then 1 else 0"
+try 21 "func main() {
+  let a := 1
+  let b := 2
+  swap(&a, &b)
+  a * 10 + b
+}
+
+func swap(x: *int, y: *int) {
+  let tmp := *x
+  *x := *y
+  *y := tmp
+}"
+try 42 "func main() {
+  let result := 0
+  divmod(44, 10, &result)
+  result * 10 + 2
+}
+
+func divmod(a, b, quotient: *int): int {
+  *quotient := a / b
+  a - a / b * b
+}"
+try 5 "func main() {
+  let x := 3
+  let p := &x
+  let pp := &p
+  **pp := **pp + 2
+  x
+}"
+try 3 "func main() {
+  let x := 0
+  let p := &x
+  let q := p + 3
+  q - p
+}"
+error "func main() {
+  let x := 1
+  *x
+}"
+try 45 "func main() {
+  let a: [int; 10]
+  let i := 0
+  while i < 10 do {
+    a[i] := i
+    i := i + 1
+  }
+  sum(a, 10)
+}
+
+func sum(xs: *int, n: int) {
+  let s := 0
+  let i := 0
+  while i < n do {
+    s := s + xs[i]
+    i := i + 1
+  }
+  s
+}"
+try 7 "func main() {
+  let x := 1
+  let a: [int; 3]
+  let y := 2
+  a[0] := 3
+  a[2] := 4
+  x + y + *(a + 2)
+}"
+try 12 "func main() {
+  let m: [[int; 3]; 2]
+  m[1][2] := 5
+  m[0][2] := 7
+  let p := &m[1][2]
+  *p + m[0][2]
+}"
+try 9 "func main() {
+  let a: [int; 2]
+  let pa := &a
+  (*pa)[1] := 9
+  a[1]
+}"
+error "func main() {
+  let a: [int; 2]
+  let b: [int; 2]
+  a := b
+  0
+}"
+try 15 "let counter := 10
+
+func main() {
+  bump()
+  bump()
+  counter + 3
+}
+
+func bump() {
+  counter := counter + 1
+}"
+try 21 "const N := 6
+const M: int := N * (N + 1) / 2
+let total := M
+let values: [int; 6]
+
+func main() {
+  let i := 0
+  while i < N do {
+    values[i] := i + 1
+    i := i + 1
+  }
+  sum(values) + total - M
+}
+
+func sum(xs: *int) {
+  let s := 0
+  let i := 0
+  while i < N do {
+    s := s + xs[i]
+    i := i + 1
+  }
+  s
+}"
+try 5 "let x := 1
+
+func main() {
+  let x := 5
+  x
+}"
+try 8 "let g: int
+
+func main() {
+  let p := &g
+  *p := 8
+  g
+}"
+error "const N := 1
+
+func main() {
+  N := 2
+  N
+}"
+error "let x := f()
+
+func f() 1
+
+func main() x"
+error "const Z := 0
+let x := 1 / Z
+
+func main() x"
+try 98 'func main() "abc"[1]'
+try 5 'func main() strlen("hello")
+
+func strlen(s: *char) {
+  let n := 0
+  while s[n] != 0 do { n := n + 1 }
+  n
+}'
+try 3 "func main() {
+  let c: char := '3'
+  c - '0'
+}"
+try 44 "func main() {
+  let c: char := 300
+  c
+}"
+try 1 "func main() {
+  let buf: [char; 4]
+  buf[0] := 'h'
+  buf[1] := 'i'
+  buf[2] := 0
+  *(buf + 1) == 'i'
+}"
+output "hello" 'func main() {
+  puts("hello")
+  0
+}'
+output "42-\"x\"" 'func main() {
+  printf("%d-\"%s\"\n", 42, "x")
+  0
+}'
+output "Hi" "func main() {
+  let s: [char; 3]
+  s[0] := 'H'
+  s[1] := 'i'
+  s[2] := 0
+  puts(s)
+  0
+}"
+error 'func main() {
+  let p: *int := "abc"
+  0
+}'
+error 'func main() "abc'
+try 1 "func main() if 1 < 2 && 3 < 4 then 1 else 0"
+try 0 "func main() if 1 < 2 && 4 < 3 then 1 else 0"
+try 1 "func main() if 2 < 1 || 3 < 4 then 1 else 0"
+try 1 "func main() if !(2 < 1) && !false then 1 else 0"
+try 1 "func main() if !1 == 2 then 1 else 0"
+try 21 "let calls := 0
+
+func main() {
+  let a := touch(false) && touch(true)
+  let b := touch(true) || touch(false)
+  let c := touch(true) && touch(false) || touch(true)
+  let flags := if a then 100 else 0
+  flags + (if b then 10 else 0) + (if c then 1 else 0) + calls * 2
+}
+
+func touch(b: bool): bool {
+  calls := calls + 1
+  b
+}"
+try 3 "func main() {
+  let xs: [int; 3]
+  xs[0] := 1
+  xs[1] := 2
+  xs[2] := 0
+  let i := 0
+  while i < 3 && xs[i] != 0 do { i := i + 1 }
+  let found := i < 3 || xs[5] == 0
+  if found then i + 1 else 0
+}"
+try 2 "func main() 17 % 5"
+try 254 "func main() -7 % 5 + 256"
+try 2 "func main() 6 & 3"
+try 7 "func main() 6 | 3"
+try 5 "func main() 6 ^ 3"
+try 6 "func main() ~5 + 12"
+try 40 "func main() 5 << 3"
+try 3 "func main() 100 >> 5"
+try 254 "func main() (-8 >> 2) + 256"
+try 15 "func main() 1 + 2 << 2 | 1 ^ 3 & 2"
+try 1 "func main() if (3 & 1) == 1 then 1 else 0"
+error "func main() if 3 & 1 == 1 then 1 else 0"
+try 1 "func main() if 1 << 2 < 5 then 1 else 0"
+try 133 "func main() {
+  let h := 5381
+  let s := \"abc\"
+  let i := 0
+  while s[i] != 0 do {
+    h := (h << 5) + h ^ s[i]
+    i := i + 1
+  }
+  h & 255
+}"
+error "func main() 1 | true"
+error "func main() ~false"
+error "func main() 1 && true"
+error "func main() !3"
+try 3 "struct Point { x: int, y: int }
+
+func main() {
+  let p := Point { x: 1, y: 2 }
+  p.x + p.y
+}"
+try 12 "struct Point { x: int, y: int }
+
+func main() {
+  let p: Point
+  p.x := 5
+  p.y := p.x + 2
+  p.x + p.y
+}"
+try 30 "struct Point {
+  x: int
+  y: int
+}
+
+func main() {
+  let p := Point { x: 2, y: 3 }
+  scale(&p, 6)
+  p.x + p.y
+}
+
+func scale(p: *Point, k: int) {
+  p.x := p.x * k
+  (*p).y := (*p).y * k
+}"
+try 10 "struct Point { x: int, y: int }
+struct Rect { min: Point, max: Point }
+
+func main() {
+  let r := Rect { min: Point { x: 1, y: 2 }, max: Point { x: 4, y: 6 } }
+  area(&r) - 2
+}
+
+func area(r: *Rect) (r.max.x - r.min.x) * (r.max.y - r.min.y)"
+try 10 "struct Point { x: int, y: int }
+
+func main() {
+  let ps: [Point; 3]
+  let i := 0
+  while i < 3 do {
+    ps[i].x := i
+    ps[i].y := i * 3
+    i := i + 1
+  }
+  let q := &ps[2]
+  q.x + q.y + ps[1].y - ps[0].x - 1
+}"
+try 7 "struct Point { x: int, y: int }
+
+func main() {
+  let a := Point { x: 3, y: 4 }
+  let b := a
+  b.x := 100
+  a.x + a.y
+}"
+try 16 "struct Item { tag: char, count: int, flag: char }
+
+func main() {
+  let items: [Item; 2]
+  items[1].tag := 'a'
+  items[1].count := 5
+  items[1].flag := 1
+  let base: *char := &items[0].tag
+  let next: *char := &items[1].tag
+  next - base - 24 + items[1].count + items[1].flag + items[1].tag - 'a' + 10
+}"
+error "struct Point { x: int, y: int }
+
+func main() f(Point { x: 1, y: 2 })
+
+func f(p: Point) p.x"
+error "struct Point { x: int, y: int }
+
+func main() Point { x: 1 }.x"
+error "struct Point { x: int, y: int }
+
+func main() Point { x: 1, y: 2, z: 3 }.x"
+try 6 "// 合計を返す
+func main() {
+  let x := 1 // 一つ目
+  /* 二つ目は
+     複数行のコメントのあと */ let y := 2
+  x + y /* 途中 */ * /**/ 2 + 1
+}"
+error "func main() {
+  /* 閉じていない
+  1
+}"
+try 3 $'func main() {\r\n\tlet x := 1\r\n\tx +\t2\r\n}'
+try 12 "const MAX_SIZE := 10
+
+func main() {
+  let _count := 2
+  let value_1 := add_two(MAX_SIZE)
+  value_1 + _count - 2
+}
+
+func add_two(n) n + 2"
+error "func main() {
+  let x := 1x
+  x
+}"
+try 255 "func main() 0xff"
+try 42 "func main() 0b10_1010"
+try 15 "func main() 0o17"
+try 100 "func main() 1_000 / 10"
+try 1 "func main() 0x1_0000_0000 >> 32"
+try 7 "func main() 0x7fff_ffff_ffff_ffff >> 60"
+try 5 "func main() {
+  let big := 5_000_000_000
+  big / 1_000_000_000
+}"
+try 45 "func id(x) x
+
+func main() {
+  let a := id(1)
+  let b := id(2)
+  let c := id(3)
+  let d := id(4)
+  let e := id(5)
+  let f := id(6)
+  let g := id(7)
+  let h := id(8)
+  let i := id(9)
+  a + b + c + d + e + f + g + h + i
+}"
+try 36 "func sum(a, b, c, d, e, f, g, h) a + b + c + d + e + f + g + h
+
+func main() {
+  let x := 1
+  let y := 2
+  sum(x, y, x + y, y * 2, 5, 6, 7, x + 7)
+}"
+error "func main() 9223372036854775808"
+error "func main() 4 / 0"
+error "func main() if 1 > 2 then 1 % 0 else 2"
+error "func main() 4 / (2 - 2)"
+error "const Z := 0
+func main() 4 / Z"
+# 変数を通して0で割るものは、実行したときに止まる (SIGFPE)
+try 136 "func main() {
+  let zero := 0
+  4 / zero
+}"
+try 7 "func f(x) x * 1 + 0 - (x - x) + 7 * (x / 1 - x)
+func main() f(7)"
+try 4 "func main() if 1 > 2 && 1 / 1 == 1 then 3 else 4"
+error "func main() 0b102"
+error "func main() 0x"
+errors 3 "func f(x {
+  x
+}
+
+func main() {
+  let := 1
+  let y := (1 + 2
+  y
+}"
+errors 2 "struct P {
+  x int
+}
+
+func main() {
+  if 1 then {
+    a := *
+  } else 0
+}"
+errors 3 "func main() {
+  let x := a + 1
+  b
+}
+
+func f() x"
+echo "func main() {
+  let a := 1
+  let a := 2
+  a
+}
+func f() b" > tmp.src
+./main --error-format=json tmp.src 2> tmp.err
+if [ "$(grep -c '^{"code":"E0' tmp.err)" != 2 ] || ! grep -q '"code":"E0101","severity":"error"' tmp.err; then
+  echo "json diagnostics failed"
+  exit 1
+fi
+echo "func main() {
+  return 3
+  4
+}" > tmp.src
+./main --error-format=json -o tmp.s tmp.src 2> tmp.err || exit 1
+if ! grep -q '"code":"W0001","severity":"warning"' tmp.err; then
+  echo "unreachable code warning expected"
+  exit 1
+fi
+echo "func main() x" > tmp.src
+./main --lang=en tmp.src 2> tmp.err
+if ! grep -q 'error\[E0101\]' tmp.err || ! grep -q 'undefined symbol' tmp.err; then
+  echo "english diagnostics expected"
+  exit 1
+fi
+LANG=en_US.UTF-8 ./main tmp.src 2> tmp.err
+if ! grep -q 'undefined symbol' tmp.err; then
+  echo "english diagnostics expected from LANG"
+  exit 1
+fi
+LANG=en_US.UTF-8 ./main --lang=ja tmp.src 2> tmp.err
+if ! grep -q '未定義のシンボルです。' tmp.err; then
+  echo "--lang should take precedence over LANG"
+  exit 1
+fi
+echo "func f(x) if x < 2 then 3 else 4
+func main() f(1)" > tmp.src
+./main --emit=ir tmp.src > tmp.ir || exit 1
+if ! grep -q '^func f(%0) {$' tmp.ir || ! grep -q '^  br %' tmp.ir || ! grep -q '^bb3(%[0-9]*):$' tmp.ir; then
+  echo "ir dump expected"
+  exit 1
+fi
+echo "func id(x) x
+func main() id(1) + id(2)" > tmp.src
+./main -o tmp.s tmp.src || exit 1
+./main -O -o tmp-O.s tmp.src || exit 1
+if [ "$(grep -c '^  ' tmp-O.s)" -ge "$(grep -c '^  ' tmp.s)" ]; then
+  echo "-O should reduce instructions"
+  exit 1
+fi
+echo "func at(i) {
+  let a: [int; 4]
+  a[i] := 5
+  a[i]
+}
+func main() at(2)" > tmp.src
+./main -o tmp.s tmp.src || exit 1
+if ! grep -q '^  lea [a-z0-9]*, \[rbp + [a-z0-9]*\*8 - [0-9]*\]$' tmp.s || grep -q 'imul' tmp.s; then
+  echo "element address should use scaled addressing"
+  exit 1
+fi
+echo "func square(x) x * x
+func main() square(3)" > tmp.src
+./main -O2 --emit=ir --print-after=inline --time-passes tmp.src > tmp.ir 2> tmp.err || exit 1
+if ! grep -q '^--- inline ---$' tmp.err || ! grep -q '^inline: [0-9.]*ms$' tmp.err || grep -q 'call square' tmp.ir; then
+  echo "-O2 should inline square"
+  exit 1
+fi
+./main -O0 --emit=ir tmp.src > tmp.ir || exit 1
+if ! grep -q 'call square' tmp.ir; then
+  echo "-O0 should not inline"
+  exit 1
+fi
+./main --passes=peephole --print-after=peephole tmp.src > /dev/null 2> tmp.err || exit 1
+if ! grep -q '^--- peephole ---$' tmp.err; then
+  echo "--print-after=peephole expected"
+  exit 1
+fi
+if ./main --print-after=unroll tmp.src 2> /dev/null; then
+  echo "unknown pass should be rejected"
+  exit 1
+fi
+if ./main --emit=ir --print-after=peephole tmp.src > /dev/null 2>&1; then
+  echo "--print-after=peephole with --emit=ir should be rejected"
+  exit 1
+fi
+echo "func main() if 1 < 2 then 3 else 4" > tmp.src
+./main -O --emit=ir tmp.src > tmp.ir || exit 1
+if grep -q '^  br %' tmp.ir || [ "$(grep -c '^bb' tmp.ir)" != 1 ]; then
+  echo "constant condition should be folded"
+  exit 1
+fi
+echo "func main() 42" | ./main - > tmp.s
+gcc -o tmp tmp.s
+./tmp
+if [ "$?" != 42 ]; then
+  echo "reading from stdin failed"
+  exit 1
+fi
 echo OK
 